@@ -13,11 +13,48 @@ pub trait BaseAir<F>: Sync {
     fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
         None
     }
+
+    /// The number of consecutive rows the constraints read. The matrices returned by
+    /// [`AirBuilder::main`], [`PairBuilder::preprocessed`] and
+    /// [`PermutationAirBuilder::permutation`] hold the rows `i, i + 1, ..., i + window_size - 1`,
+    /// wrapping around at the end of the trace, and transition windows of up to this many rows
+    /// can be selected with [`AirBuilder::is_transition_window`]. It must be at least 2.
+    fn window_size(&self) -> usize {
+        2
+    }
+
+    /// The number of extension field challenges returned by
+    /// [`PermutationAirBuilder::permutation_randomness`], sampled after the main trace is committed.
+    fn num_permutation_challenges(&self) -> usize {
+        0
+    }
+
+    /// The number of extension field columns in the trace returned by [`Self::permutation_trace`].
+    fn permutation_width(&self) -> usize {
+        0
+    }
+
+    /// Return the permutation trace of `main` under `challenges`, to be committed after the
+    /// challenges are sampled and read through [`PermutationAirBuilder::permutation`], or `None`
+    /// if the AIR has no permutation columns.
+    fn permutation_trace<EF: ExtensionField<F>>(
+        &self,
+        _main: &RowMajorMatrix<F>,
+        _challenges: &[EF],
+    ) -> Option<RowMajorMatrix<EF>>
+    where
+        F: Field,
+    {
+        None
+    }
 }
 
 /// An extension of `BaseAir` that includes support for public values.
 pub trait BaseAirWithPublicValues<F>: BaseAir<F> {
     /// Return the number of expected public values.
+    ///
+    /// The STARK verifier rejects a proof whose public values do not number exactly this many,
+    /// so an AIR which reads public values must override the default of zero.
     fn num_public_values(&self) -> usize {
         0
     }
@@ -86,7 +123,10 @@ pub trait AirBuilder: Sized {
         self.is_transition_window(2)
     }
 
-    /// Expression evaluating to 1 on rows except the last `size - 1` rows, 0 otherwise.
+    /// Expression evaluating to 1 on rows except the last `size - 1` rows, 0 otherwise, so that
+    /// it selects the rows whose window of `size` rows does not wrap around the trace.
+    ///
+    /// Builders may panic if `size` exceeds the AIR's [`BaseAir::window_size`].
     fn is_transition_window(&self, size: usize) -> Self::Expr;
 
     /// Returns a sub-builder whose constraints are enforced only when `condition` is nonzero.
@@ -126,6 +166,10 @@ pub trait AirBuilder: Sized {
         self.when(self.is_transition_window(size))
     }
 
+    /// Label the constraints asserted after this call, up to the next call, for builders which
+    /// report failing constraints by name. Other builders ignore labels.
+    fn label(&mut self, _label: &'static str) {}
+
     /// Assert that the given element is zero.
     ///
     /// Where possible, batching multiple assert_zero calls
@@ -274,6 +318,10 @@ impl<AB: AirBuilder> AirBuilder for FilteredAirBuilder<'_, AB> {
         self.inner.is_transition_window(size)
     }
 
+    fn label(&mut self, label: &'static str) {
+        self.inner.label(label);
+    }
+
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
         self.inner.assert_zero(self.condition() * x.into());
     }
@@ -0,0 +1,35 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use p3_field::Field;
+
+use crate::{BaseAir, VirtualPairCol};
+
+/// A message sent or received over a bus shared between the tables of a batch proof.
+///
+/// Every row of the table contributes the tuple `fields` to bus `bus`, `count` times. Across all
+/// tables of a batch, every tuple must be sent exactly as many times as it is received.
+#[derive(Clone, Debug)]
+pub struct Interaction<F: Field> {
+    /// The values of the tuple, as affine combinations of the row's main columns.
+    pub fields: Vec<VirtualPairCol<F>>,
+    /// The multiplicity of the tuple on the row, usually a selector or a multiplicity column.
+    pub count: VirtualPairCol<F>,
+    /// The bus the tuple is sent over. Tuples on different buses never balance each other.
+    pub bus: usize,
+}
+
+/// An AIR which communicates with other AIRs of a batch proof over buses.
+///
+/// Interactions may only refer to main trace columns.
+pub trait InteractionAir<F: Field>: BaseAir<F> {
+    /// The tuples each row of the table sends.
+    fn sends(&self) -> Vec<Interaction<F>> {
+        vec![]
+    }
+
+    /// The tuples each row of the table receives.
+    fn receives(&self) -> Vec<Interaction<F>> {
+        vec![]
+    }
+}
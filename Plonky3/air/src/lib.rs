@@ -5,8 +5,10 @@
 extern crate alloc;
 
 mod air;
+mod interaction;
 pub mod utils;
 mod virtual_column;
 
 pub use air::*;
+pub use interaction::*;
 pub use virtual_column::*;
@@ -86,6 +86,27 @@ pub struct CirclePcsProof<
     >,
 }
 
+impl<Val, Challenge, InputMmcs, FriMmcs, Witness>
+    CirclePcsProof<Val, Challenge, InputMmcs, FriMmcs, Witness>
+where
+    Val: Field,
+    Challenge: Field,
+    InputMmcs: Mmcs<Val>,
+    FriMmcs: Mmcs<Challenge>,
+{
+    /// The FRI proof of the low degree of the DEEP quotient, after its first layer.
+    pub const fn fri_proof(
+        &self,
+    ) -> &CircleFriProof<
+        Challenge,
+        FriMmcs,
+        Witness,
+        CircleInputProof<Val, Challenge, InputMmcs, FriMmcs>,
+    > {
+        &self.fri_proof
+    }
+}
+
 impl<Val, InputMmcs, FriMmcs, Challenge, Challenger> Pcs<Challenge, Challenger>
     for CirclePcs<Val, InputMmcs, FriMmcs>
 where
@@ -1,6 +1,6 @@
 use core::borrow::Borrow;
 
-use p3_air::{Air, AirBuilder, BaseAir};
+use p3_air::{Air, AirBuilder, BaseAir, BaseAirWithPublicValues};
 use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
 use p3_challenger::DuplexChallenger;
 use p3_commit::ExtensionMmcs;
@@ -52,6 +52,8 @@ impl<F> BaseAir<F> for FibonacciChip {
     }
 }
 
+impl<F> BaseAirWithPublicValues<F> for FibonacciChip {}
+
 /// Implement Air trait for FibonacciChip, defining constraints
 impl<AB: AirBuilder> Air<AB> for FibonacciChip {
     fn eval(&self, builder: &mut AB) {
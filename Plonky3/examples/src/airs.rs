@@ -1,4 +1,4 @@
-use p3_air::{Air, AirBuilder, BaseAir};
+use p3_air::{Air, AirBuilder, BaseAir, BaseAirWithPublicValues};
 use p3_blake3_air::Blake3Air;
 use p3_challenger::FieldChallenger;
 use p3_commit::PolynomialSpace;
@@ -9,7 +9,7 @@ use p3_poseidon2::GenericPoseidon2LinearLayers;
 use p3_poseidon2_air::{Poseidon2Air, VectorizedPoseidon2Air};
 use p3_uni_stark::{
     DebugConstraintBuilder, ProverConstraintFolder, StarkGenericConfig, SymbolicAirBuilder,
-    SymbolicExpression, VerifierConstraintFolder,
+    SymbolicExpression, Val, VerifierConstraintFolder,
 };
 use rand::distr::StandardUniform;
 use rand::prelude::Distribution;
@@ -48,8 +48,8 @@ pub enum ProofObjective<
 /// A key feature is the ability to randomly generate a trace which proves
 /// the output of some number of hashes using a given hash function.
 pub trait ExampleHashAir<F: Field, SC: StarkGenericConfig>:
-    BaseAir<F>
-    + for<'a> Air<DebugConstraintBuilder<'a, F>>
+    BaseAirWithPublicValues<F>
+    + for<'a> Air<DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>
     + Air<SymbolicAirBuilder<F>>
     + for<'a> Air<ProverConstraintFolder<'a, SC>>
     + for<'a> Air<VerifierConstraintFolder<'a, SC>>
@@ -94,6 +94,29 @@ impl<
     }
 }
 
+impl<
+    F: Field,
+    LinearLayers: Sync,
+    const WIDTH: usize,
+    const SBOX_DEGREE: u64,
+    const SBOX_REGISTERS: usize,
+    const HALF_FULL_ROUNDS: usize,
+    const PARTIAL_ROUNDS: usize,
+    const VECTOR_LEN: usize,
+> BaseAirWithPublicValues<F>
+    for ProofObjective<
+        F,
+        LinearLayers,
+        WIDTH,
+        SBOX_DEGREE,
+        SBOX_REGISTERS,
+        HALF_FULL_ROUNDS,
+        PARTIAL_ROUNDS,
+        VECTOR_LEN,
+    >
+{
+}
+
 impl<
     AB: AirBuilder,
     LinearLayers: GenericPoseidon2LinearLayers<AB::Expr, WIDTH>,
@@ -0,0 +1,235 @@
+//! Run, prove and verify ALU programs written in the `zkalu` assembly syntax.
+//!
+//! ```text
+//! cargo run --release --bin zkalu -- run program.s --regs 1,2,5,0 --trace trace.csv
+//! cargo run --release --bin zkalu -- prove program.s --regs 1,2,5,0 --proof program.proof
+//! cargo run --release --bin zkalu -- verify program.s --regs 1,2,5,0 --final-regs 3,2,3,5 --proof program.proof
+//! ```
+//!
+//! Every program is proven against its ROM, so its proof shows that this program was run.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Args, Parser, Subcommand};
+use p3_uni_stark::Proof;
+use zkalu::config::{AluConfig, Val, alu_config};
+use zkalu::{
+    AluChip, Instruction, NUM_REGISTERS, RomAluChip, assemble, try_execute, try_prove_rom_program,
+    verify_rom_program,
+};
+
+#[derive(Parser, Debug)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Execute a program and print the final registers.
+    Run {
+        #[command(flatten)]
+        program: ProgramArgs,
+
+        /// Also write the execution trace to this file, as JSON if its name ends in `.json` and
+        /// as CSV otherwise.
+        #[arg(short, long)]
+        trace: Option<PathBuf>,
+    },
+
+    /// Execute a program, prove the run and write the proof to a file.
+    Prove {
+        #[command(flatten)]
+        program: ProgramArgs,
+
+        /// The file to write the proof to.
+        #[arg(short, long)]
+        proof: PathBuf,
+    },
+
+    /// Verify that a proof file shows a program mapping the initial registers to the final ones.
+    Verify {
+        #[command(flatten)]
+        program: ProgramArgs,
+
+        /// The claimed final registers, comma separated.
+        #[arg(short, long, value_delimiter = ',', required = true)]
+        final_regs: Vec<u32>,
+
+        /// The file to read the proof from.
+        #[arg(short, long)]
+        proof: PathBuf,
+    },
+}
+
+#[derive(Args, Debug)]
+struct ProgramArgs {
+    /// The assembly source of the program.
+    program: PathBuf,
+
+    /// The initial registers, comma separated. Defaults to all zeros.
+    #[arg(short, long, value_delimiter = ',')]
+    regs: Vec<u32>,
+}
+
+impl ProgramArgs {
+    fn load(&self) -> Result<(Vec<Instruction>, [u32; NUM_REGISTERS]), Box<dyn Error>> {
+        let source = fs::read_to_string(&self.program)
+            .map_err(|e| format!("cannot read {}: {e}", self.program.display()))?;
+        let program = assemble(&source).map_err(|e| format!("{}: {e}", self.program.display()))?;
+        if program.is_empty() {
+            return Err(format!("{} contains no instructions", self.program.display()).into());
+        }
+        for inst in &program {
+            let max_reg = inst.dest.max(inst.src1).max(inst.src2);
+            if max_reg >= NUM_REGISTERS {
+                return Err(format!(
+                    "`{inst}` uses r{max_reg}, but there are only {NUM_REGISTERS} registers"
+                )
+                .into());
+            }
+        }
+        let regs = match self.regs.as_slice() {
+            [] => [0; NUM_REGISTERS],
+            regs => parse_regs(regs, "--regs")?,
+        };
+        Ok((program, regs))
+    }
+}
+
+fn parse_regs(regs: &[u32], flag: &str) -> Result<[u32; NUM_REGISTERS], Box<dyn Error>> {
+    regs.try_into().map_err(|_| {
+        format!(
+            "{flag} takes {NUM_REGISTERS} registers, but {} were given",
+            regs.len()
+        )
+        .into()
+    })
+}
+
+/// The chip proving `program` against its ROM, which binds the proof to the program.
+fn rom_chip(program: Vec<Instruction>) -> Result<RomAluChip, Box<dyn Error>> {
+    if let Some(inst) = program
+        .iter()
+        .find(|inst| inst.op.is_branch() && inst.imm as usize > program.len())
+    {
+        return Err(format!(
+            "`{inst}` branches past {}, the pc just after the program",
+            program.len()
+        )
+        .into());
+    }
+    Ok(RomAluChip::new(program))
+}
+
+fn format_regs(regs: &[u32]) -> String {
+    regs.iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The largest proof file `verify` reads, which bounds what a malformed file can make the decoder
+/// allocate. Proofs of the longest runs are far smaller.
+const MAX_PROOF_SIZE: usize = 1 << 26;
+
+fn bincode_config() -> impl bincode::config::Config {
+    bincode::config::standard()
+        .with_little_endian()
+        .with_fixed_int_encoding()
+        .with_limit::<MAX_PROOF_SIZE>()
+}
+
+fn write_proof(path: &Path, proof: &Proof<AluConfig>) -> Result<(), Box<dyn Error>> {
+    let bytes = bincode::serde::encode_to_vec(proof, bincode_config())?;
+    fs::write(path, &bytes).map_err(|e| format!("cannot write {}: {e}", path.display()))?;
+    println!(
+        "Wrote a proof of {} bytes to {}",
+        bytes.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+fn read_proof(path: &Path) -> Result<Proof<AluConfig>, Box<dyn Error>> {
+    let size = fs::metadata(path)
+        .map_err(|e| format!("cannot read {}: {e}", path.display()))?
+        .len();
+    if size > MAX_PROOF_SIZE as u64 {
+        return Err(format!(
+            "{} is {size} bytes, but a proof is at most {MAX_PROOF_SIZE} bytes",
+            path.display()
+        )
+        .into());
+    }
+    let bytes = fs::read(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    let (proof, _) = bincode::serde::decode_from_slice(&bytes, bincode_config())
+        .map_err(|e| format!("{} is not a proof: {e}", path.display()))?;
+    Ok(proof)
+}
+
+/// Write the trace of running `program` against its ROM from `initial_regs` to `path`, in the
+/// format its extension names, and return the final registers.
+fn write_trace(
+    path: &Path,
+    program: Vec<Instruction>,
+    initial_regs: [u32; NUM_REGISTERS],
+) -> Result<[u32; NUM_REGISTERS], Box<dyn Error>> {
+    let (trace, final_regs) = rom_chip(program)?.try_generate_trace::<Val>(initial_regs)?;
+    let text = if path.extension().is_some_and(|ext| ext == "json") {
+        AluChip::<NUM_REGISTERS>::trace_to_json(&trace)
+    } else {
+        AluChip::<NUM_REGISTERS>::trace_to_csv(&trace)
+    };
+    fs::write(path, text).map_err(|e| format!("cannot write {}: {e}", path.display()))?;
+    Ok(final_regs)
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    let config = alu_config();
+    match cli.command {
+        Command::Run { program, trace } => {
+            let (program, initial_regs) = program.load()?;
+            let final_regs = match trace {
+                Some(path) => write_trace(&path, program, initial_regs)?,
+                None => try_execute(&program, initial_regs)?,
+            };
+            println!("{}", format_regs(&final_regs));
+        }
+        Command::Prove { program, proof } => {
+            let (program, initial_regs) = program.load()?;
+            let chip = rom_chip(program)?;
+            let (proof_data, final_regs) = try_prove_rom_program(&config, &chip, initial_regs)?;
+            write_proof(&proof, &proof_data)?;
+            println!("{}", format_regs(&final_regs));
+        }
+        Command::Verify {
+            program,
+            final_regs,
+            proof,
+        } => {
+            let (program, initial_regs) = program.load()?;
+            let final_regs = parse_regs(&final_regs, "--final-regs")?;
+            let proof = read_proof(&proof)?;
+            let chip = rom_chip(program)?;
+            verify_rom_program(&config, &chip, &proof, &initial_regs, &final_regs)
+                .map_err(|e| format!("verification failed: {e}"))?;
+            println!("Proof verified");
+        }
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
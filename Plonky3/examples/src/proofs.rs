@@ -1,18 +1,19 @@
-use core::fmt::Debug;
+use core::fmt::{self, Debug};
 
 use p3_challenger::{DuplexChallenger, SerializingChallenger32};
-use p3_circle::CirclePcs;
-use p3_commit::ExtensionMmcs;
+use p3_circle::{CirclePcs, CirclePcsProof};
+use p3_commit::{ExtensionMmcs, Mmcs};
 use p3_dft::TwoAdicSubgroupDft;
 use p3_field::extension::{BinomialExtensionField, ComplexExtendable};
 use p3_field::{ExtensionField, Field, PrimeField32, PrimeField64, TwoAdicField};
-use p3_fri::{TwoAdicFriPcs, create_benchmark_fri_params};
+use p3_fri::{FriProof, TwoAdicFriPcs, create_benchmark_fri_params};
 use p3_keccak::{Keccak256Hash, KeccakF};
 use p3_mersenne_31::Mersenne31;
 use p3_symmetric::{CryptographicPermutation, PaddingFreeSponge, SerializingHasher};
-use p3_uni_stark::{Proof, StarkGenericConfig, prove, verify};
+use p3_uni_stark::{PcsProof, Proof, StarkGenericConfig, prove, verify};
 use rand::distr::StandardUniform;
 use rand::prelude::Distribution;
+use serde::Serialize;
 
 use crate::airs::ExampleHashAir;
 use crate::types::{
@@ -220,19 +221,146 @@ pub fn report_result(result: Result<(), impl Debug>) {
     }
 }
 
+/// Serialize a value as [`report_proof_size`] does and return its size in bytes.
+fn serialized_size<T: Serialize>(value: &T) -> usize {
+    let config = bincode::config::standard()
+        .with_little_endian()
+        .with_fixed_int_encoding();
+    bincode::serde::encode_to_vec(value, config)
+        .expect("Failed to serialize proof")
+        .len()
+}
+
+/// An opening proof which contains a FRI proof, whose commit phase and queries a
+/// [`ProofSummary`] reports separately.
+pub trait FriOpeningProof {
+    /// The serialized sizes of the commitments of the FRI commit phase and of the query proofs.
+    fn fri_sizes(&self) -> (usize, usize);
+}
+
+impl<F, M, Witness, InputProof> FriOpeningProof for FriProof<F, M, Witness, InputProof>
+where
+    F: Field,
+    M: Mmcs<F>,
+    InputProof: Serialize,
+{
+    fn fri_sizes(&self) -> (usize, usize) {
+        (
+            serialized_size(&self.commit_phase_commits),
+            serialized_size(&self.query_proofs),
+        )
+    }
+}
+
+/// The proof of a [`HidingFriPcs`](p3_fri::HidingFriPcs), which opens its random polynomials
+/// before the FRI proof.
+impl<T, P: FriOpeningProof> FriOpeningProof for (T, P) {
+    fn fri_sizes(&self) -> (usize, usize) {
+        self.1.fri_sizes()
+    }
+}
+
+impl<Val, Challenge, InputMmcs, FriMmcs, Witness> FriOpeningProof
+    for CirclePcsProof<Val, Challenge, InputMmcs, FriMmcs, Witness>
+where
+    Val: Field,
+    Challenge: Field,
+    InputMmcs: Mmcs<Val>,
+    FriMmcs: Mmcs<Challenge>,
+{
+    fn fri_sizes(&self) -> (usize, usize) {
+        let fri_proof = self.fri_proof();
+        (
+            serialized_size(&fri_proof.commit_phase_commits),
+            serialized_size(&fri_proof.query_proofs),
+        )
+    }
+}
+
+/// The serialized size in bytes of a proof and of its components, which add up to the total.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProofSummary {
+    pub total: usize,
+    pub trace_commitment: usize,
+    /// The commitments to the permutation trace, the quotient chunks and the random polynomial.
+    pub other_commitments: usize,
+    /// The opened trace, preprocessed and permutation windows and the random polynomial.
+    pub trace_openings: usize,
+    pub quotient_openings: usize,
+    pub fri_commit_phase: usize,
+    pub fri_query_proofs: usize,
+    /// The degree bits and the rest of the opening proof, such as the final polynomial and the
+    /// proof of work witness.
+    pub other: usize,
+}
+
+impl ProofSummary {
+    pub fn new<SC>(proof: &Proof<SC>) -> Self
+    where
+        SC: StarkGenericConfig,
+        PcsProof<SC>: FriOpeningProof,
+    {
+        let commitments = proof.commitments();
+        let opened_values = proof.opened_values();
+        let total = serialized_size(proof);
+        let trace_commitment = serialized_size(commitments.trace());
+        let other_commitments = serialized_size(&commitments.permutation())
+            + serialized_size(commitments.quotient_chunks())
+            + serialized_size(&commitments.random());
+        let trace_openings = serialized_size(&opened_values.trace_window())
+            + serialized_size(&opened_values.preprocessed_window())
+            + serialized_size(&opened_values.permutation_window())
+            + serialized_size(&opened_values.random());
+        let quotient_openings = serialized_size(&opened_values.quotient_chunks());
+        let (fri_commit_phase, fri_query_proofs) = proof.opening_proof().fri_sizes();
+        let other = total
+            - trace_commitment
+            - other_commitments
+            - trace_openings
+            - quotient_openings
+            - fri_commit_phase
+            - fri_query_proofs;
+        Self {
+            total,
+            trace_commitment,
+            other_commitments,
+            trace_openings,
+            quotient_openings,
+            fri_commit_phase,
+            fri_query_proofs,
+            other,
+        }
+    }
+}
+
+impl fmt::Display for ProofSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Proof size: {} bytes", self.total)?;
+        let components = [
+            ("trace commitment", self.trace_commitment),
+            ("other commitments", self.other_commitments),
+            ("trace openings", self.trace_openings),
+            ("quotient openings", self.quotient_openings),
+            ("FRI commit phase", self.fri_commit_phase),
+            ("FRI query proofs", self.fri_query_proofs),
+            ("other", self.other),
+        ];
+        for (name, size) in components {
+            writeln!(f, "  {name}: {size} bytes")?;
+        }
+        Ok(())
+    }
+}
+
 /// Report the size of the serialized proof.
 ///
-/// Serializes the given proof instance using bincode and prints the size in bytes.
-/// Panics if serialization fails.
+/// Serializes the given proof instance using bincode and prints its size in bytes, broken down
+/// by component as in [`ProofSummary`]. Panics if serialization fails.
 #[inline]
 pub fn report_proof_size<SC>(proof: &Proof<SC>)
 where
     SC: StarkGenericConfig,
+    PcsProof<SC>: FriOpeningProof,
 {
-    let config = bincode::config::standard()
-        .with_little_endian()
-        .with_fixed_int_encoding();
-    let proof_bytes =
-        bincode::serde::encode_to_vec(proof, config).expect("Failed to serialize proof");
-    println!("Proof size: {} bytes", proof_bytes.len());
+    print!("{}", ProofSummary::new(proof));
 }
@@ -0,0 +1,101 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// A path in the temporary directory unique to this test process.
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("zkalu-cli-{}-{name}", std::process::id()))
+}
+
+fn zkalu(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_zkalu"))
+        .args(args)
+        .output()
+        .expect("failed to run zkalu")
+}
+
+fn verify(program: &str, final_regs: &str, proof: &str) -> Output {
+    zkalu(&[
+        "verify",
+        program,
+        "--final-regs",
+        final_regs,
+        "--proof",
+        proof,
+    ])
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn test_prove_and_verify() {
+    // Sums 1..=10 into r0 in a loop.
+    let program = temp_path("loop.s");
+    fs::write(
+        &program,
+        "loadi r1, 10\nbeq r1, r3, 5\nadd r0, r0, r1\naddi r1, r1, 4294967295\njmp 1\n",
+    )
+    .unwrap();
+    let proof = temp_path("loop.proof");
+    let (program, proof) = (program.to_str().unwrap(), proof.to_str().unwrap());
+
+    let output = zkalu(&["prove", program, "--proof", proof]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(
+        stdout(&output).ends_with("55,0,0,0\n"),
+        "{}",
+        stdout(&output)
+    );
+
+    let output = verify(program, "55,0,0,0", proof);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "Proof verified\n");
+
+    let output = verify(program, "56,0,0,0", proof);
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("verification failed"),
+        "{}",
+        stderr(&output)
+    );
+
+    // The proof is of this program, not of any other with the same result.
+    let other = temp_path("other.s");
+    fs::write(&other, "loadi r0, 55\n").unwrap();
+    let other = other.to_str().unwrap();
+    let output = verify(other, "55,0,0,0", proof);
+    assert!(!output.status.success());
+
+    fs::write(proof, b"not a proof").unwrap();
+    let output = verify(program, "55,0,0,0", proof);
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("is not a proof"),
+        "{}",
+        stderr(&output)
+    );
+
+    for path in [program, proof, other] {
+        fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn test_non_halting_program() {
+    let program = temp_path("spin.s");
+    fs::write(&program, "jmp 0\n").unwrap();
+    let output = zkalu(&["run", program.to_str().unwrap()]);
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).starts_with("error: program did not halt"),
+        "{}",
+        stderr(&output)
+    );
+    fs::remove_file(program).unwrap();
+}
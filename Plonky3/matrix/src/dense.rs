@@ -1227,6 +1227,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_vertically_packed_row_window() {
+        type Packed = FieldArray<BabyBear, 2>;
+
+        let matrix = RowMajorMatrix::new((1..17).map(BabyBear::new).collect::<Vec<_>>(), 4);
+
+        // Packing rows 1-2, then rows 2-3, then rows 3-0, wrapping around.
+        let packed = matrix.vertically_packed_row_window::<Packed>(1, 1, 3);
+        assert_eq!(
+            packed,
+            (5..17)
+                .map(|i| [BabyBear::new(i), BabyBear::new((i + 4 - 1) % 16 + 1)].into())
+                .collect::<Vec<_>>(),
+        );
+
+        // A window of two rows is a row pair.
+        assert_eq!(
+            matrix.vertically_packed_row_window::<Packed>(1, 2, 2),
+            matrix.vertically_packed_row_pair::<Packed>(1, 2),
+        );
+    }
+
     #[test]
     fn test_vertically_packed_row_pair_overlap() {
         type Packed = FieldArray<BabyBear, 2>;
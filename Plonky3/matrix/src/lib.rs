@@ -408,6 +408,28 @@ pub trait Matrix<T: Send + Sync + Clone>: Send + Sync {
             .collect_vec()
     }
 
+    /// Pack together a window of `len` rows, each `step` rows after the previous one.
+    ///
+    /// Returns a vector corresponding to `len` packed rows. The i'th element of the k'th row
+    /// contains the packing of the i'th element of the rows r + k * step through
+    /// r + k * step + P::WIDTH - 1. If at some point we exceed the height of the matrix, wrap
+    /// around and include initial rows. A window of 2 rows is [`Self::vertically_packed_row_pair`].
+    #[inline]
+    fn vertically_packed_row_window<P>(&self, r: usize, step: usize, len: usize) -> Vec<P>
+    where
+        T: Copy,
+        P: PackedValue<Value = T>,
+    {
+        (0..len)
+            .flat_map(|k| {
+                let rows = self.wrapping_row_slices(r + k * step, P::WIDTH);
+                (0..self.width())
+                    .map(|c| P::from_fn(|i| rows[i][c]))
+                    .collect_vec()
+            })
+            .collect()
+    }
+
     /// Returns a view over a vertically strided submatrix.
     ///
     /// The view selects rows using `r = offset + i * stride` for each `i`.
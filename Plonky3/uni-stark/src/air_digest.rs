@@ -0,0 +1,147 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use hashbrown::HashMap;
+use p3_air::Air;
+use p3_challenger::{CanObserve, CanSample};
+use p3_field::Field;
+use tracing::instrument;
+
+use crate::{
+    Entry, StarkGenericConfig, SymbolicAirBuilder, SymbolicExpression, Val,
+    get_symbolic_constraints,
+};
+
+/// The number of field elements in an [`air_digest`].
+pub const AIR_DIGEST_LEN: usize = 8;
+
+/// Encode the constraints of `air` as field elements, canonically: the encoding only depends on
+/// the shape of the AIR and the constraint DAG it builds, so AIRs which differ in either have
+/// different encodings.
+///
+/// The encoding starts with the widths of the preprocessed, main and permutation traces, the
+/// window size, the number of permutation challenges and of public values. It follows with the
+/// nodes of the DAG of [`get_symbolic_constraints`] in evaluation order, each as four elements: a
+/// tag and up to three operands, the operands of an operation being the indices of earlier nodes.
+/// It ends with the index of the root node of each constraint, in the order the AIR asserts them.
+///
+/// Structurally equal subexpressions share a node, whether or not the AIR built them once and
+/// reused them, so the encoding does not depend on how the AIR shares expressions.
+#[instrument(name = "encode the AIR", skip_all, level = "debug")]
+pub fn encode_air<F, A>(air: &A, preprocessed_width: usize, num_public_values: usize) -> Vec<F>
+where
+    F: Field,
+    A: Air<SymbolicAirBuilder<F>>,
+{
+    let constraints = get_symbolic_constraints(air, preprocessed_width, num_public_values);
+
+    let mut encoder = DagEncoder {
+        nodes: vec![],
+        indices: HashMap::new(),
+        visited: BTreeMap::new(),
+    };
+    let roots: Vec<usize> = constraints
+        .iter()
+        .map(|constraint| encoder.encode(constraint))
+        .collect();
+
+    let header = [
+        preprocessed_width,
+        air.width(),
+        air.permutation_width(),
+        air.window_size(),
+        air.num_permutation_challenges(),
+        num_public_values,
+        encoder.nodes.len() / 4,
+        roots.len(),
+    ];
+    header
+        .into_iter()
+        .map(F::from_usize)
+        .chain(encoder.nodes)
+        .chain(roots.into_iter().map(F::from_usize))
+        .collect()
+}
+
+/// A digest of `air` which the prover and the verifier observe before any commitment, so that a
+/// proof for one AIR never verifies against another: the encoding of [`encode_air`] hashed with a
+/// fresh challenger of `config`.
+///
+/// The preprocessed columns are not part of the digest, as their commitment is observed with it.
+pub fn air_digest<SC, A>(
+    config: &SC,
+    air: &A,
+    preprocessed_width: usize,
+    num_public_values: usize,
+) -> [Val<SC>; AIR_DIGEST_LEN]
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>>>,
+{
+    let mut challenger = config.initialise_challenger();
+    challenger.observe_slice(&encode_air(air, preprocessed_width, num_public_values));
+    core::array::from_fn(|_| CanSample::<Val<SC>>::sample(&mut challenger))
+}
+
+/// Flattens a DAG of symbolic expressions into nodes, each encoded once however often it occurs.
+struct DagEncoder<F> {
+    nodes: Vec<F>,
+    /// The index of each node, keyed by its encoding.
+    indices: HashMap<[F; 4], usize>,
+    /// The index of each expression already encoded, so that shared subexpressions are only
+    /// traversed once.
+    visited: BTreeMap<*const SymbolicExpression<F>, usize>,
+}
+
+impl<F: Field> DagEncoder<F> {
+    /// Encode `expr` and its operands which are not encoded yet, returning the index of its node.
+    fn encode(&mut self, expr: &SymbolicExpression<F>) -> usize {
+        let key: *const SymbolicExpression<F> = expr;
+        if let Some(&index) = self.visited.get(&key) {
+            return index;
+        }
+        let [tag, a, b, c] = match expr {
+            SymbolicExpression::Variable(v) => {
+                let (kind, offset) = match v.entry {
+                    Entry::Preprocessed { offset } => (0, offset),
+                    Entry::Main { offset } => (1, offset),
+                    Entry::Permutation { offset } => (2, offset),
+                    Entry::Public => (3, 0),
+                    Entry::Challenge => (4, 0),
+                };
+                [0, kind, offset, v.index].map(F::from_usize)
+            }
+            SymbolicExpression::IsFirstRow => [F::ONE, F::ZERO, F::ZERO, F::ZERO],
+            SymbolicExpression::IsLastRow => [F::TWO, F::ZERO, F::ZERO, F::ZERO],
+            SymbolicExpression::IsTransition => [F::from_u8(3), F::ZERO, F::ZERO, F::ZERO],
+            SymbolicExpression::Constant(c) => [F::from_u8(4), *c, F::ZERO, F::ZERO],
+            SymbolicExpression::Add { x, y, .. } => self.encode_binary(5, x, y),
+            SymbolicExpression::Sub { x, y, .. } => self.encode_binary(6, x, y),
+            SymbolicExpression::Neg { x, .. } => {
+                let x = self.encode(x);
+                [7, x, 0, 0].map(F::from_usize)
+            }
+            SymbolicExpression::Mul { x, y, .. } => self.encode_binary(8, x, y),
+            SymbolicExpression::IsTransitionWindow(size) => [9, *size, 0, 0].map(F::from_usize),
+        };
+        let node = [tag, a, b, c];
+        let index = *self.indices.entry(node).or_insert_with(|| {
+            self.nodes.extend(node);
+            self.nodes.len() / 4 - 1
+        });
+        self.visited.insert(key, index);
+        index
+    }
+
+    fn encode_binary(
+        &mut self,
+        tag: usize,
+        x: &SymbolicExpression<F>,
+        y: &SymbolicExpression<F>,
+    ) -> [F; 4] {
+        let x = self.encode(x);
+        let y = self.encode(y);
+        [tag, x, y, 0].map(F::from_usize)
+    }
+}
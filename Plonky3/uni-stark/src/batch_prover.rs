@@ -0,0 +1,312 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use itertools::{Itertools, izip};
+use p3_air::{Air, InteractionAir};
+use p3_challenger::{CanObserve, FieldChallenger};
+use p3_commit::{Pcs, PolynomialSpace};
+use p3_field::PrimeCharacteristicRing;
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_util::log2_strict_usize;
+use tracing::{info_span, instrument};
+
+use crate::lookup::LookupAir;
+use crate::prover::{quotient_values, window_points};
+use crate::{
+    BatchProof, Commitments, OpenedValues, ProverConstraintFolder, StarkGenericConfig,
+    SymbolicAirBuilder, Val, air_digest, get_log_quotient_degree, get_symbolic_constraints,
+};
+
+/// A trace to prove against an AIR in a batch, with the public values of the instance.
+#[derive(Debug)]
+pub struct StarkInstance<'a, SC: StarkGenericConfig, A> {
+    pub air: &'a A,
+    pub trace: RowMajorMatrix<Val<SC>>,
+    pub public_values: Vec<Val<SC>>,
+}
+
+/// Prove several instances at once, each trace satisfying its own AIR, and the tuples the AIRs
+/// send over buses, see [`InteractionAir`], balancing the tuples they receive.
+///
+/// The traces may have different heights. They are committed together, followed by the
+/// permutation columns of all instances, which include the LogUp columns of their interactions,
+/// and by the quotient chunks of all instances, so the proof has a single opening argument.
+/// Preprocessed columns and zero-knowledge PCSs are not supported.
+#[instrument(skip_all)]
+#[allow(clippy::multiple_bound_locations)] // cfg not supported in where clauses?
+pub fn prove_batch<
+    SC,
+    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>,
+    #[cfg(not(debug_assertions))] A,
+>(
+    config: &SC,
+    instances: Vec<StarkInstance<'_, SC, A>>,
+) -> BatchProof<SC>
+where
+    SC: StarkGenericConfig,
+    A: InteractionAir<Val<SC>>
+        + Air<SymbolicAirBuilder<Val<SC>>>
+        + for<'a> Air<ProverConstraintFolder<'a, SC>>,
+{
+    assert!(
+        !SC::Pcs::ZK,
+        "batch proofs do not support a zero-knowledge PCS"
+    );
+    assert!(
+        !instances.is_empty(),
+        "a batch must have at least one instance"
+    );
+    for instance in &instances {
+        assert!(
+            instance.air.preprocessed_trace().is_none(),
+            "batch proofs do not support preprocessed columns"
+        );
+    }
+
+    let pcs = config.pcs();
+    let mut challenger = config.initialise_challenger();
+
+    let airs = instances
+        .iter()
+        .map(|instance| LookupAir::new(instance.air))
+        .collect_vec();
+    let degree_bits = instances
+        .iter()
+        .map(|instance| log2_strict_usize(instance.trace.height()))
+        .collect_vec();
+    let trace_domains = instances
+        .iter()
+        .map(|instance| pcs.natural_domain_for_degree(instance.trace.height()))
+        .collect_vec();
+
+    // Commit to all traces in one batch, which the PCS supports for matrices of any heights.
+    let (trace_commit, trace_data) = info_span!("commit to trace data").in_scope(|| {
+        pcs.commit(
+            trace_domains
+                .iter()
+                .zip(&instances)
+                .map(|(&domain, instance)| (domain, instance.trace.clone())),
+        )
+    });
+
+    // Observe the instances: their heights, the digests of their AIRs, including the LogUp
+    // constraints of their interactions, their traces and public values.
+    for &bits in &degree_bits {
+        challenger.observe(Val::<SC>::from_u8(bits as u8));
+    }
+    for (air, instance) in airs.iter().zip(&instances) {
+        challenger.observe_slice(&air_digest(config, air, 0, instance.public_values.len()));
+    }
+    challenger.observe(trace_commit.clone());
+    for instance in &instances {
+        challenger.observe_slice(&instance.public_values);
+    }
+
+    // Sample the challenges of each AIR's own permutation columns, and then `beta` and `gamma`,
+    // which all instances share so that their buses are fingerprinted alike.
+    let mut permutation_challenges = instances
+        .iter()
+        .map(|instance| {
+            (0..instance.air.num_permutation_challenges())
+                .map(|_| challenger.sample_algebra_element())
+                .collect_vec()
+        })
+        .collect_vec();
+    let any_interactions = airs.iter().any(LookupAir::has_interactions);
+    let (beta, gamma): (SC::Challenge, SC::Challenge) = if any_interactions {
+        (
+            challenger.sample_algebra_element(),
+            challenger.sample_algebra_element(),
+        )
+    } else {
+        Default::default()
+    };
+
+    // Each permutation trace is the AIR's own permutation columns followed by its LogUp columns.
+    let mut cumulative_sums = Vec::with_capacity(instances.len());
+    let permutation_traces = izip!(&instances, &airs, &mut permutation_challenges)
+        .map(|(instance, air, challenges)| {
+            let permutation = instance.air.permutation_trace(&instance.trace, challenges);
+            assert_eq!(
+                permutation.as_ref().map_or(0, Matrix::width),
+                instance.air.permutation_width(),
+                "permutation trace width does not match the AIR's permutation width"
+            );
+            let lookup = air.lookup_trace(&instance.trace, beta, gamma);
+            let cumulative_sum = lookup.as_ref().map(|lookup| *lookup.values.last().unwrap());
+            cumulative_sums.push(cumulative_sum);
+            if let Some(cumulative_sum) = cumulative_sum {
+                challenges.extend([beta, gamma, cumulative_sum]);
+            }
+            match (permutation, lookup) {
+                (Some(permutation), Some(lookup)) => {
+                    let width = permutation.width() + lookup.width();
+                    let values = permutation
+                        .rows()
+                        .zip(lookup.rows())
+                        .flat_map(|(row, lookup_row)| row.chain(lookup_row))
+                        .collect();
+                    Some(RowMajorMatrix::new(values, width))
+                }
+                (permutation, lookup) => permutation.or(lookup),
+            }
+        })
+        .collect_vec();
+
+    #[cfg(debug_assertions)]
+    for (instance, air, permutation, challenges) in izip!(
+        &instances,
+        &airs,
+        &permutation_traces,
+        &permutation_challenges
+    ) {
+        crate::check_constraints::check_constraints_with_permutation(
+            air,
+            &instance.trace,
+            permutation.as_ref(),
+            challenges,
+            &instance.public_values,
+        );
+    }
+
+    // Commit to the permutation traces of the instances which have one in a second batch,
+    // flattened to the base field. Their cumulative sums are observed with the commitment.
+    let permutation_indices = permutation_traces
+        .iter()
+        .scan(0, |next, permutation| {
+            Some(permutation.as_ref().map(|_| {
+                *next += 1;
+                *next - 1
+            }))
+        })
+        .collect_vec();
+    let permutation_commit_and_data = permutation_traces.iter().any(Option::is_some).then(|| {
+        info_span!("commit to permutation traces").in_scope(|| {
+            pcs.commit(trace_domains.iter().zip(permutation_traces).filter_map(
+                |(&domain, permutation)| {
+                    permutation.map(|permutation| (domain, permutation.flatten_to_base()))
+                },
+            ))
+        })
+    });
+    if let Some((permutation_commit, _)) = &permutation_commit_and_data {
+        challenger.observe(permutation_commit.clone());
+    }
+    for cumulative_sum in cumulative_sums.iter().flatten() {
+        challenger.observe_algebra_element(*cumulative_sum);
+    }
+    let (permutation_commit, permutation_data) = permutation_commit_and_data.unzip();
+
+    // One `alpha` combines the constraints of every instance.
+    let alpha: SC::Challenge = challenger.sample_algebra_element();
+
+    // Compute the quotient of each instance on its own quotient domain and split it into chunks,
+    // all of which are committed in a third batch.
+    let mut quotient_degrees = Vec::with_capacity(instances.len());
+    let mut quotient_chunks = Vec::new();
+    for (i, (instance, air, &trace_domain)) in izip!(&instances, &airs, &trace_domains).enumerate()
+    {
+        let public_values = &instance.public_values;
+        let constraint_count = get_symbolic_constraints(air, 0, public_values.len()).len();
+        let log_quotient_degree =
+            get_log_quotient_degree::<Val<SC>, _>(air, 0, public_values.len(), 0);
+        let quotient_degree = 1 << log_quotient_degree;
+        quotient_degrees.push(quotient_degree);
+
+        let quotient_domain =
+            trace_domain.create_disjoint_domain(1 << (degree_bits[i] + log_quotient_degree));
+        let trace_on_quotient_domain =
+            pcs.get_evaluations_on_domain(&trace_data, i, quotient_domain);
+        let permutation_on_quotient_domain = permutation_indices[i].map(|idx| {
+            pcs.get_evaluations_on_domain(permutation_data.as_ref().unwrap(), idx, quotient_domain)
+        });
+        let quotient_values = quotient_values(
+            air,
+            public_values,
+            trace_domain,
+            quotient_domain,
+            trace_on_quotient_domain,
+            None,
+            permutation_on_quotient_domain,
+            &permutation_challenges[i],
+            alpha,
+            constraint_count,
+        );
+        let quotient_flat = RowMajorMatrix::new_col(quotient_values).flatten_to_base();
+        quotient_chunks.extend(
+            quotient_domain
+                .split_domains(quotient_degree)
+                .into_iter()
+                .zip(quotient_domain.split_evals(quotient_degree, quotient_flat)),
+        );
+    }
+    let (quotient_commit, quotient_data) =
+        info_span!("commit to quotient poly chunks").in_scope(|| pcs.commit(quotient_chunks));
+    challenger.observe(quotient_commit.clone());
+
+    let commitments = Commitments {
+        trace: trace_commit,
+        permutation: permutation_commit,
+        quotient_chunks: quotient_commit,
+        random: None,
+    };
+
+    // Open every instance at the same out-of-domain point and at its successors in the instance's
+    // own trace domain, one per row of the instance's window.
+    let zeta: SC::Challenge = challenger.sample_algebra_element();
+    let window_points = trace_domains
+        .iter()
+        .zip(&instances)
+        .map(|(&domain, instance)| window_points(domain, zeta, instance.air.window_size()))
+        .collect_vec();
+
+    let (opened_values, opening_proof) = info_span!("open").in_scope(|| {
+        let round1 = (&trace_data, window_points.clone());
+        let num_chunks = quotient_degrees.iter().sum();
+        let round2 = (&quotient_data, vec![vec![zeta]; num_chunks]);
+        let round3 = permutation_data.as_ref().map(|data| {
+            let points = window_points
+                .iter()
+                .zip(&permutation_indices)
+                .filter(|(_, idx)| idx.is_some())
+                .map(|(points, _)| points.clone())
+                .collect();
+            (data, points)
+        });
+        let rounds = [round1, round2].into_iter().chain(round3).collect();
+        pcs.open(rounds, &mut challenger)
+    });
+
+    let trace_idx = SC::Pcs::TRACE_IDX;
+    let quotient_idx = SC::Pcs::QUOTIENT_IDX;
+    let mut opened_quotient_chunks = opened_values[quotient_idx].iter();
+    let opened_values = quotient_degrees
+        .iter()
+        .zip(&permutation_indices)
+        .enumerate()
+        .map(|(i, (&quotient_degree, permutation_idx))| {
+            let trace = &opened_values[trace_idx][i];
+            let permutation = permutation_idx.map(|idx| &opened_values[quotient_idx + 1][idx]);
+            OpenedValues {
+                trace_window: trace.clone(),
+                preprocessed_window: None,
+                permutation_window: permutation.cloned(),
+                quotient_chunks: opened_quotient_chunks
+                    .by_ref()
+                    .take(quotient_degree)
+                    .map(|chunk| chunk[0].clone())
+                    .collect(),
+                random: None,
+            }
+        })
+        .collect();
+
+    BatchProof {
+        commitments,
+        opened_values,
+        cumulative_sums,
+        opening_proof,
+        degree_bits,
+    }
+}
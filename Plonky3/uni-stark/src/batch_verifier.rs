@@ -0,0 +1,260 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use itertools::{Itertools, izip};
+use p3_air::{Air, BaseAir, BaseAirWithPublicValues, InteractionAir};
+use p3_challenger::{CanObserve, FieldChallenger};
+use p3_commit::{Pcs, PolynomialSpace};
+use p3_field::{BasedVectorSpace, PrimeCharacteristicRing};
+use p3_util::log2_strict_usize;
+use tracing::instrument;
+
+use crate::lookup::LookupAir;
+use crate::prover::window_points;
+use crate::symbolic_builder::{SymbolicAirBuilder, get_log_quotient_degree};
+use crate::verifier::{
+    check_public_values, check_quotient_chunks, check_window, verify_constraints,
+};
+use crate::{
+    BatchProof, Domain, PcsError, StarkGenericConfig, Val, VerificationError,
+    VerifierConstraintFolder, air_digest,
+};
+
+/// Verify a proof produced by [`prove_batch`](crate::prove_batch), given the AIR and the public
+/// values of each instance in the order they were proven. The public values of each instance are
+/// counted as in [`verify`](crate::verify).
+#[instrument(skip_all)]
+pub fn verify_batch<SC, A>(
+    config: &SC,
+    airs: &[&A],
+    proof: &BatchProof<SC>,
+    public_values: &[Vec<Val<SC>>],
+) -> Result<(), VerificationError<PcsError<SC>>>
+where
+    SC: StarkGenericConfig,
+    A: InteractionAir<Val<SC>>
+        + BaseAirWithPublicValues<Val<SC>>
+        + Air<SymbolicAirBuilder<Val<SC>>>
+        + for<'a> Air<VerifierConstraintFolder<'a, SC>>,
+{
+    assert!(
+        !SC::Pcs::ZK,
+        "batch proofs do not support a zero-knowledge PCS"
+    );
+    let BatchProof {
+        commitments,
+        opened_values,
+        cumulative_sums,
+        opening_proof,
+        degree_bits,
+    } = proof;
+
+    let num_instances = airs.len();
+    if public_values.len() != num_instances
+        || opened_values.len() != num_instances
+        || cumulative_sums.len() != num_instances
+        || degree_bits.len() != num_instances
+    {
+        return Err(VerificationError::InvalidProofShape);
+    }
+    if commitments.random.is_some() || opened_values.iter().any(|v| v.random.is_some()) {
+        return Err(VerificationError::RandomizationError);
+    }
+
+    let pcs = config.pcs();
+    let lookup_airs = airs.iter().map(|&air| LookupAir::new(air)).collect_vec();
+
+    // Check the shape of each instance's openings against its AIR. An instance has permutation
+    // openings exactly when it has permutation or LogUp columns, and a cumulative sum exactly
+    // when it has interactions.
+    let mut quotient_degrees = Vec::with_capacity(num_instances);
+    for (air, values, cumulative_sum, public_values) in
+        izip!(&lookup_airs, opened_values, cumulative_sums, public_values)
+    {
+        check_public_values(air.air, public_values)?;
+        let log_quotient_degree =
+            get_log_quotient_degree::<Val<SC>, _>(air, 0, public_values.len(), 0);
+        let quotient_degree = 1 << log_quotient_degree;
+        quotient_degrees.push(quotient_degree);
+
+        let window_size = air.window_size();
+        let permutation_width = air.permutation_width() * SC::Challenge::DIMENSION;
+        match &values.permutation_window {
+            Some(window) if permutation_width > 0 => {
+                check_window(
+                    window,
+                    window_size,
+                    permutation_width,
+                    |expected, actual| VerificationError::PermutationWidthMismatch {
+                        expected,
+                        actual,
+                    },
+                )?;
+            }
+            None if permutation_width == 0 => {}
+            _ => return Err(VerificationError::InvalidProofShape),
+        }
+        check_window(
+            &values.trace_window,
+            window_size,
+            air.width(),
+            |expected, actual| VerificationError::TraceWidthMismatch { expected, actual },
+        )?;
+        check_quotient_chunks(
+            &values.quotient_chunks,
+            quotient_degree,
+            SC::Challenge::DIMENSION,
+        )?;
+        if values.preprocessed_window.is_some()
+            || cumulative_sum.is_some() != air.has_interactions()
+        {
+            return Err(VerificationError::InvalidProofShape);
+        }
+    }
+    let has_permutation = opened_values
+        .iter()
+        .any(|values| values.permutation_window.is_some());
+    if commitments.permutation.is_some() != has_permutation {
+        return Err(VerificationError::InvalidProofShape);
+    }
+
+    // The traces were committed on domains of the claimed heights.
+    let trace_domains = degree_bits
+        .iter()
+        .map(|&bits| pcs.natural_domain_for_degree(1 << bits))
+        .collect_vec();
+
+    // Replay the transcript of the prover.
+    let mut challenger = config.initialise_challenger();
+    for &bits in degree_bits {
+        challenger.observe(Val::<SC>::from_usize(bits));
+    }
+    for (air, public_values) in lookup_airs.iter().zip(public_values) {
+        challenger.observe_slice(&air_digest(config, air, 0, public_values.len()));
+    }
+    challenger.observe(commitments.trace.clone());
+    for public_values in public_values {
+        challenger.observe_slice(public_values);
+    }
+
+    let mut permutation_challenges = airs
+        .iter()
+        .map(|air| {
+            (0..air.num_permutation_challenges())
+                .map(|_| challenger.sample_algebra_element())
+                .collect_vec()
+        })
+        .collect_vec();
+    if lookup_airs.iter().any(LookupAir::has_interactions) {
+        let (beta, gamma): (SC::Challenge, SC::Challenge) = (
+            challenger.sample_algebra_element(),
+            challenger.sample_algebra_element(),
+        );
+        for (challenges, cumulative_sum) in permutation_challenges.iter_mut().zip(cumulative_sums) {
+            if let Some(cumulative_sum) = cumulative_sum {
+                challenges.extend([beta, gamma, *cumulative_sum]);
+            }
+        }
+    }
+
+    if let Some(permutation_commit) = &commitments.permutation {
+        challenger.observe(permutation_commit.clone());
+    }
+    for cumulative_sum in cumulative_sums.iter().flatten() {
+        challenger.observe_algebra_element(*cumulative_sum);
+    }
+
+    let alpha: SC::Challenge = challenger.sample_algebra_element();
+    challenger.observe(commitments.quotient_chunks.clone());
+
+    let zeta: SC::Challenge = challenger.sample_algebra_element();
+
+    let quotient_chunks_domains = izip!(&trace_domains, degree_bits, &quotient_degrees)
+        .map(|(trace_domain, &bits, &quotient_degree)| {
+            trace_domain
+                .create_disjoint_domain(1 << (bits + log2_strict_usize(quotient_degree)))
+                .split_domains(quotient_degree)
+        })
+        .collect_vec();
+
+    // The rows of a window are paired with the points they were opened at.
+    let window_openings = |domain: &Domain<SC>, window: &Vec<Vec<SC::Challenge>>| {
+        let points = window_points(*domain, zeta, window.len());
+        (
+            *domain,
+            points.into_iter().zip(window.iter().cloned()).collect(),
+        )
+    };
+    let mut coms_to_verify = vec![
+        (
+            commitments.trace.clone(),
+            trace_domains
+                .iter()
+                .zip(opened_values)
+                .map(|(domain, values)| window_openings(domain, &values.trace_window))
+                .collect_vec(),
+        ),
+        (
+            commitments.quotient_chunks.clone(),
+            quotient_chunks_domains
+                .iter()
+                .zip(opened_values)
+                .flat_map(|(domains, values)| {
+                    domains
+                        .iter()
+                        .zip(&values.quotient_chunks)
+                        .map(|(&domain, chunk)| (domain, vec![(zeta, chunk.clone())]))
+                })
+                .collect_vec(),
+        ),
+    ];
+    if let Some(permutation_commit) = &commitments.permutation {
+        coms_to_verify.push((
+            permutation_commit.clone(),
+            trace_domains
+                .iter()
+                .zip(opened_values)
+                .filter_map(|(domain, values)| {
+                    let window = values.permutation_window.as_ref()?;
+                    Some(window_openings(domain, window))
+                })
+                .collect_vec(),
+        ));
+    }
+
+    pcs.verify(coms_to_verify, opening_proof, &mut challenger)
+        .map_err(VerificationError::InvalidOpeningArgument)?;
+
+    for (air, values, challenges, public_values, &trace_domain, domains) in izip!(
+        &lookup_airs,
+        opened_values,
+        &permutation_challenges,
+        public_values,
+        &trace_domains,
+        &quotient_chunks_domains
+    ) {
+        verify_constraints(
+            air,
+            values,
+            challenges,
+            public_values,
+            trace_domain,
+            domains,
+            zeta,
+            alpha,
+        )?;
+    }
+
+    // Every tuple sent over a bus is received, exactly when the cumulative sums cancel.
+    if cumulative_sums
+        .iter()
+        .flatten()
+        .copied()
+        .sum::<SC::Challenge>()
+        != SC::Challenge::ZERO
+    {
+        return Err(VerificationError::UnbalancedInteractions);
+    }
+
+    Ok(())
+}
@@ -1,83 +1,309 @@
+use alloc::vec;
 use alloc::vec::Vec;
+use core::fmt;
 
-use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues};
-use p3_field::Field;
+use p3_air::{
+    Air, AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, PairBuilder,
+    PermutationAirBuilder,
+};
+use p3_field::{ExtensionField, Field};
 use p3_matrix::Matrix;
 use p3_matrix::dense::{RowMajorMatrix, RowMajorMatrixView};
-use p3_matrix::stack::{VerticalPair, ViewPair};
 use tracing::instrument;
 
-/// Runs constraint checks using a given AIR definition and trace matrix.
-///
-/// Iterates over every row in `main`, providing both the current and next row
-/// (with wraparound) to the AIR logic. Also injects public values into the builder
-/// for first/last row assertions.
+/// A constraint which does not vanish on a row of the trace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConstraintFailure<EF> {
+    /// The row the constraint was evaluated on, the first row of its window.
+    pub row: usize,
+    /// The index of the constraint in the order `eval` asserts them, which is also its index in
+    /// [`get_symbolic_constraints`](crate::get_symbolic_constraints).
+    pub constraint: usize,
+    /// The label the AIR gave the constraint with [`AirBuilder::label`], if any.
+    pub label: Option<&'static str>,
+    /// The nonzero value of the constraint.
+    pub value: EF,
+}
+
+/// Every constraint failure found in a trace, ordered by row and then by constraint index.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConstraintReport<EF> {
+    pub failures: Vec<ConstraintFailure<EF>>,
+}
+
+impl<EF> ConstraintReport<EF> {
+    /// Whether every constraint vanishes on every row.
+    pub const fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// The rows on which a constraint labelled `label` fails, in increasing order.
+    pub fn failing_rows(&self, label: &str) -> Vec<usize> {
+        let mut rows: Vec<_> = self
+            .failures
+            .iter()
+            .filter(|failure| failure.label == Some(label))
+            .map(|failure| failure.row)
+            .collect();
+        rows.dedup();
+        rows
+    }
+}
+
+/// Lists the failures of each constraint together, e.g.
+/// `constraint 12 (dest one-hot) fails on rows 12 (2), 40 (2)`, with the values in parentheses.
+impl<EF: fmt::Display> fmt::Display for ConstraintReport<EF> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.failures.is_empty() {
+            return write!(f, "all constraints are satisfied");
+        }
+        let mut failures: Vec<_> = self.failures.iter().collect();
+        failures.sort_by_key(|failure| (failure.constraint, failure.row));
+        for (i, group) in failures
+            .chunk_by(|a, b| a.constraint == b.constraint)
+            .enumerate()
+        {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "constraint {}", group[0].constraint)?;
+            if let Some(label) = group[0].label {
+                write!(f, " ({label})")?;
+            }
+            let rows = if group.len() == 1 { "row" } else { "rows" };
+            write!(f, " fails on {rows} ")?;
+            for (j, failure) in group.iter().enumerate() {
+                let separator = if j == 0 { "" } else { ", " };
+                write!(f, "{separator}{} ({})", failure.row, failure.value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Runs constraint checks using a given AIR definition and trace matrix, panicking if any
+/// constraint fails.
+#[cfg(test)]
+pub(crate) fn check_constraints<F, A>(air: &A, main: &RowMajorMatrix<F>, public_values: &[F])
+where
+    F: Field,
+    A: for<'a> Air<DebugConstraintBuilder<'a, F>>,
+{
+    assert_constraints(&collect_failures(air, main, None, &[], public_values));
+}
+
+/// Runs constraint checks as [`check_constraints`] does, also providing the rows of the
+/// permutation trace built from `permutation_challenges`, if the AIR has one.
+#[cfg(debug_assertions)]
+pub(crate) fn check_constraints_with_permutation<F, EF, A>(
+    air: &A,
+    main: &RowMajorMatrix<F>,
+    permutation: Option<&RowMajorMatrix<EF>>,
+    permutation_challenges: &[EF],
+    public_values: &[F],
+) where
+    F: Field,
+    EF: ExtensionField<F>,
+    A: for<'a> Air<DebugConstraintBuilder<'a, F, EF>>,
+{
+    assert_constraints(&collect_failures(
+        air,
+        main,
+        permutation,
+        permutation_challenges,
+        public_values,
+    ));
+}
+
+/// Panic with `report` unless it is empty, naming the row of the first failure.
+#[cfg(any(test, debug_assertions))]
+fn assert_constraints<EF: Field>(report: &ConstraintReport<EF>) {
+    if let Some(first) = report.failures.first() {
+        panic!(
+            "constraints had nonzero value on row {}\n{report}",
+            first.row
+        );
+    }
+}
+
+/// Evaluates every constraint of `air` on every row of `main` and returns all the failures,
+/// instead of panicking on the first one as the prover's debug checks do.
 ///
-/// # Arguments
-/// - `air`: The AIR logic to run
-/// - `main`: The trace matrix (rows of witness values)
-/// - `public_values`: Public values provided to the builder
-#[instrument(name = "check constraints", skip_all)]
-pub(crate) fn check_constraints<F, A>(air: &A, main: &RowMajorMatrix<F>, public_values: &Vec<F>)
+/// The AIR must not have a permutation trace; see [`find_constraint_failures_with_permutation`].
+pub fn find_constraint_failures<F, A>(
+    air: &A,
+    main: &RowMajorMatrix<F>,
+    public_values: &[F],
+) -> ConstraintReport<F>
 where
     F: Field,
     A: for<'a> Air<DebugConstraintBuilder<'a, F>>,
+{
+    collect_failures(air, main, None, &[], public_values)
+}
+
+/// Evaluates every constraint of `air` as [`find_constraint_failures`] does, together with the
+/// AIR's permutation trace built from `permutation_challenges`.
+pub fn find_constraint_failures_with_permutation<F, EF, A>(
+    air: &A,
+    main: &RowMajorMatrix<F>,
+    permutation_challenges: &[EF],
+    public_values: &[F],
+) -> ConstraintReport<EF>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    A: for<'a> Air<DebugConstraintBuilder<'a, F, EF>>,
+{
+    let permutation = air.permutation_trace(main, permutation_challenges);
+    collect_failures(
+        air,
+        main,
+        permutation.as_ref(),
+        permutation_challenges,
+        public_values,
+    )
+}
+
+/// Iterates over every row in `main`, providing the window of rows starting at it (with
+/// wraparound) to the AIR logic, along with the matching rows of the AIR's preprocessed trace
+/// and of `permutation` if there are any, and collects the constraints which do not vanish.
+#[instrument(name = "check constraints", skip_all)]
+fn collect_failures<F, EF, A>(
+    air: &A,
+    main: &RowMajorMatrix<F>,
+    permutation: Option<&RowMajorMatrix<EF>>,
+    permutation_challenges: &[EF],
+    public_values: &[F],
+) -> ConstraintReport<EF>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    A: for<'a> Air<DebugConstraintBuilder<'a, F, EF>>,
 {
     let height = main.height();
+    let preprocessed = air.preprocessed_trace();
+    if let Some(preprocessed) = &preprocessed {
+        assert_eq!(
+            preprocessed.height(),
+            height,
+            "preprocessed trace height does not match the trace height"
+        );
+    }
+    if let Some(permutation) = permutation {
+        assert_eq!(
+            permutation.height(),
+            height,
+            "permutation trace height does not match the trace height"
+        );
+    }
 
+    let window_size = air.window_size();
+    let mut failures = Vec::new();
     (0..height).for_each(|row_index| {
-        let row_index_next = (row_index + 1) % height;
-
-        // row_index < height so we can used unchecked indexing.
-        let local = unsafe { main.row_slice_unchecked(row_index) };
-        // row_index_next < height so we can used unchecked indexing.
-        let next = unsafe { main.row_slice_unchecked(row_index_next) };
-        let main = VerticalPair::new(
-            RowMajorMatrixView::new_row(&*local),
-            RowMajorMatrixView::new_row(&*next),
+        let main = window(main, row_index, window_size);
+        let preprocessed = preprocessed.as_ref().map_or_else(
+            || RowMajorMatrix::new(vec![], 0),
+            |p| window(p, row_index, window_size),
+        );
+        let permutation = permutation.map_or_else(
+            || RowMajorMatrix::new(vec![], 0),
+            |p| window(p, row_index, window_size),
         );
 
         let mut builder = DebugConstraintBuilder {
             row_index,
-            main,
+            height,
+            main: main.as_view(),
+            preprocessed: preprocessed.as_view(),
+            permutation: permutation.as_view(),
+            permutation_challenges,
             public_values,
             is_first_row: F::from_bool(row_index == 0),
             is_last_row: F::from_bool(row_index == height - 1),
-            is_transition: F::from_bool(row_index != height - 1),
+            constraint_index: 0,
+            label: None,
+            failures: Vec::new(),
         };
 
         air.eval(&mut builder);
+        failures.append(&mut builder.failures);
     });
+    ConstraintReport { failures }
 }
 
-/// A builder that runs constraint assertions during testing.
+/// The `window_size` rows of `matrix` starting at `row`, wrapping around its end.
+fn window<T: Clone + Send + Sync>(
+    matrix: &RowMajorMatrix<T>,
+    row: usize,
+    window_size: usize,
+) -> RowMajorMatrix<T> {
+    let values = (0..window_size)
+        .flat_map(|k| {
+            let row = matrix.row_slice((row + k) % matrix.height()).unwrap();
+            row.to_vec()
+        })
+        .collect();
+    RowMajorMatrix::new(values, matrix.width())
+}
+
+/// A builder which evaluates the constraints on one row of a trace and records those which do
+/// not vanish.
 ///
-/// Used in conjunction with [`check_constraints`] to simulate
-/// an execution trace and verify that the AIR logic enforces all constraints.
+/// Used by [`find_constraint_failures`] and the prover's debug checks to verify that a trace
+/// satisfies the AIR before it is proven.
 #[derive(Debug)]
-pub struct DebugConstraintBuilder<'a, F: Field> {
+pub struct DebugConstraintBuilder<'a, F: Field, EF: ExtensionField<F> = F> {
     /// The index of the row currently being evaluated.
     row_index: usize,
-    /// A view of the current and next row as a vertical pair.
-    main: ViewPair<'a, F>,
+    /// The height of the trace.
+    height: usize,
+    /// A view of the window of rows starting at the current row.
+    main: RowMajorMatrixView<'a, F>,
+    /// A view of the window of rows of the preprocessed trace, empty if there is none.
+    preprocessed: RowMajorMatrixView<'a, F>,
+    /// A view of the window of rows of the permutation trace, empty if there is none.
+    permutation: RowMajorMatrixView<'a, EF>,
+    /// The challenges the permutation trace was built from.
+    permutation_challenges: &'a [EF],
     /// The public values provided for constraint validation (e.g. inputs or outputs).
     public_values: &'a [F],
     /// A flag indicating whether this is the first row.
     is_first_row: F,
     /// A flag indicating whether this is the last row.
     is_last_row: F,
-    /// A flag indicating whether this is a transition row (not the last row).
-    is_transition: F,
+    /// The index of the next constraint asserted on this row.
+    constraint_index: usize,
+    /// The label of the constraints currently being asserted.
+    label: Option<&'static str>,
+    /// The constraints which did not vanish on this row.
+    failures: Vec<ConstraintFailure<EF>>,
+}
+
+impl<F: Field, EF: ExtensionField<F>> DebugConstraintBuilder<'_, F, EF> {
+    /// Record the next constraint, which evaluates to `value`.
+    fn record(&mut self, value: EF) {
+        if !value.is_zero() {
+            self.failures.push(ConstraintFailure {
+                row: self.row_index,
+                constraint: self.constraint_index,
+                label: self.label,
+                value,
+            });
+        }
+        self.constraint_index += 1;
+    }
 }
 
-impl<'a, F> AirBuilder for DebugConstraintBuilder<'a, F>
+impl<'a, F, EF> AirBuilder for DebugConstraintBuilder<'a, F, EF>
 where
     F: Field,
+    EF: ExtensionField<F>,
 {
     type F = F;
     type Expr = F;
     type Var = F;
-    type M = ViewPair<'a, F>;
+    type M = RowMajorMatrixView<'a, F>;
 
     fn main(&self) -> Self::M {
         self.main
@@ -92,36 +318,62 @@ where
     }
 
     /// # Panics
-    /// This function panics if `size` is not `2`.
+    /// This function panics if `size` is less than `2` or greater than the window size.
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            self.is_transition
-        } else {
-            panic!("only supports a window size of 2")
-        }
+        assert!(
+            (2..=self.main.height()).contains(&size),
+            "a transition window must have between 2 and {} rows, not {size}",
+            self.main.height()
+        );
+        F::from_bool(self.row_index + size <= self.height)
+    }
+
+    fn label(&mut self, label: &'static str) {
+        self.label = Some(label);
     }
 
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
-        assert_eq!(
-            x.into(),
-            F::ZERO,
-            "constraints had nonzero value on row {}",
-            self.row_index
-        );
+        self.record(x.into().into());
     }
+}
 
-    fn assert_eq<I1: Into<Self::Expr>, I2: Into<Self::Expr>>(&mut self, x: I1, y: I2) {
-        let x = x.into();
-        let y = y.into();
-        assert_eq!(
-            x, y,
-            "values didn't match on row {}: {} != {}",
-            self.row_index, x, y
-        );
+impl<F: Field, EF: ExtensionField<F>> PairBuilder for DebugConstraintBuilder<'_, F, EF> {
+    fn preprocessed(&self) -> Self::M {
+        self.preprocessed
     }
 }
 
-impl<F: Field> AirBuilderWithPublicValues for DebugConstraintBuilder<'_, F> {
+impl<F: Field, EF: ExtensionField<F>> ExtensionBuilder for DebugConstraintBuilder<'_, F, EF> {
+    type EF = EF;
+    type ExprEF = EF;
+    type VarEF = EF;
+
+    fn assert_zero_ext<I>(&mut self, x: I)
+    where
+        I: Into<Self::ExprEF>,
+    {
+        self.record(x.into());
+    }
+}
+
+impl<'a, F: Field, EF: ExtensionField<F>> PermutationAirBuilder
+    for DebugConstraintBuilder<'a, F, EF>
+{
+    type MP = RowMajorMatrixView<'a, EF>;
+    type RandomVar = EF;
+
+    fn permutation(&self) -> Self::MP {
+        self.permutation
+    }
+
+    fn permutation_randomness(&self) -> &[Self::RandomVar] {
+        self.permutation_challenges
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>> AirBuilderWithPublicValues
+    for DebugConstraintBuilder<'_, F, EF>
+{
     type PublicVar = Self::F;
 
     fn public_values(&self) -> &[Self::F] {
@@ -131,11 +383,12 @@ impl<F: Field> AirBuilderWithPublicValues for DebugConstraintBuilder<'_, F> {
 
 #[cfg(test)]
 mod tests {
+    use alloc::string::ToString;
     use alloc::vec;
 
     use p3_air::{BaseAir, BaseAirWithPublicValues};
     use p3_baby_bear::BabyBear;
-    use p3_field::PrimeCharacteristicRing;
+    use p3_field::{PrimeCharacteristicRing, PrimeField32};
 
     use super::*;
 
@@ -160,9 +413,10 @@ mod tests {
         fn eval(&self, builder: &mut DebugConstraintBuilder<'_, F>) {
             let main = builder.main();
 
+            builder.label("increment");
             for col in 0..W {
-                let a = main.top.get(0, col).unwrap();
-                let b = main.bottom.get(0, col).unwrap();
+                let a = main.get(0, col).unwrap();
+                let b = main.get(1, col).unwrap();
 
                 // New logic: enforce row[i+1] = row[i] + 1, only on transitions
                 builder.when_transition().assert_eq(b, a + F::ONE);
@@ -170,9 +424,10 @@ mod tests {
 
             // Add public value equality on last row for extra coverage
             let public_values = builder.public_values;
+            builder.label("public values");
             let mut when_last = builder.when(builder.is_last_row);
             for (i, &pv) in public_values.iter().enumerate().take(W) {
-                when_last.assert_eq(main.top.get(0, i).unwrap(), pv);
+                when_last.assert_eq(main.get(0, i).unwrap(), pv);
             }
         }
     }
@@ -193,11 +448,11 @@ mod tests {
             BabyBear::new(4), // Row 3 (last)
         ];
         let main = RowMajorMatrix::new(values, 2);
-        check_constraints(&air, &main, &vec![BabyBear::new(4); 2]);
+        check_constraints(&air, &main, &[BabyBear::new(4); 2]);
     }
 
     #[test]
-    #[should_panic]
+    #[should_panic(expected = "constraints had nonzero value on row 1")]
     fn test_incorrect_increment_logic() {
         // Row 2 does not equal row 1 + 1 → should fail on transition from row 1 to 2.
         let air = RowLogicAir::<2>;
@@ -212,11 +467,45 @@ mod tests {
             BabyBear::new(6), // Row 3
         ];
         let main = RowMajorMatrix::new(values, 2);
-        check_constraints(&air, &main, &vec![BabyBear::new(6); 2]);
+        check_constraints(&air, &main, &[BabyBear::new(6); 2]);
+    }
+
+    #[test]
+    fn test_find_every_failure() {
+        let air = RowLogicAir::<2>;
+        let values = [1, 1, 2, 2, 5, 5, 6, 6].map(BabyBear::new).to_vec();
+        let main = RowMajorMatrix::new(values, 2);
+        let report = find_constraint_failures(&air, &main, &[BabyBear::new(6), BabyBear::new(7)]);
+        let failure = |row, constraint, label, value| ConstraintFailure {
+            row,
+            constraint,
+            label: Some(label),
+            value: BabyBear::new(value),
+        };
+        assert_eq!(
+            report.failures,
+            [
+                failure(1, 0, "increment", 2),
+                failure(1, 1, "increment", 2),
+                failure(3, 3, "public values", BabyBear::ORDER_U32 - 1),
+            ]
+        );
+        assert_eq!(report.failing_rows("increment"), [1]);
+        assert_eq!(report.failing_rows("public values"), [3]);
+        assert_eq!(
+            report.to_string(),
+            "constraint 0 (increment) fails on row 1 (2)\n\
+             constraint 1 (increment) fails on row 1 (2)\n\
+             constraint 3 (public values) fails on row 3 (2013265920)"
+        );
+
+        let values = [1, 1, 2, 2, 3, 3, 4, 4].map(BabyBear::new).to_vec();
+        let main = RowMajorMatrix::new(values, 2);
+        assert!(find_constraint_failures(&air, &main, &[BabyBear::new(4); 2]).is_ok());
     }
 
     #[test]
-    #[should_panic]
+    #[should_panic(expected = "constraints had nonzero value on row 3")]
     fn test_wrong_last_row_public_value() {
         // The transition logic is fine, but public value check fails at the last row.
         let air = RowLogicAir::<2>;
@@ -232,7 +521,7 @@ mod tests {
         ];
         let main = RowMajorMatrix::new(values, 2);
         // Wrong public value on column 1
-        check_constraints(&air, &main, &vec![BabyBear::new(4), BabyBear::new(5)]);
+        check_constraints(&air, &main, &[BabyBear::new(4), BabyBear::new(5)]);
     }
 
     #[test]
@@ -246,6 +535,6 @@ mod tests {
             BabyBear::new(77), // Row 0
         ];
         let main = RowMajorMatrix::new(values, 2);
-        check_constraints(&air, &main, &vec![BabyBear::new(99), BabyBear::new(77)]);
+        check_constraints(&air, &main, &[BabyBear::new(99), BabyBear::new(77)]);
     }
 }
@@ -1,9 +1,10 @@
 use alloc::vec::Vec;
 
-use p3_air::{AirBuilder, AirBuilderWithPublicValues};
+use p3_air::{
+    AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, PairBuilder, PermutationAirBuilder,
+};
 use p3_field::{BasedVectorSpace, PackedField};
 use p3_matrix::dense::RowMajorMatrixView;
-use p3_matrix::stack::ViewPair;
 
 use crate::{PackedChallenge, PackedVal, StarkGenericConfig, Val};
 
@@ -14,16 +15,26 @@ use crate::{PackedChallenge, PackedVal, StarkGenericConfig, Val};
 /// `C_0 + alpha C_1 + alpha^2 C_2 + ...`
 #[derive(Debug)]
 pub struct ProverConstraintFolder<'a, SC: StarkGenericConfig> {
-    /// The matrix containing rows on which the constraint polynomial is to be evaluated
+    /// The matrix containing the window of rows on which the constraint polynomial is to be
+    /// evaluated
     pub main: RowMajorMatrixView<'a, PackedVal<SC>>,
+    /// The matrix containing the window of rows of the preprocessed columns, empty if the AIR has
+    /// none
+    pub preprocessed: RowMajorMatrixView<'a, PackedVal<SC>>,
+    /// The matrix containing the window of rows of the permutation columns, empty if the AIR has
+    /// none
+    pub permutation: RowMajorMatrixView<'a, PackedChallenge<SC>>,
+    /// Challenges the permutation columns were built from
+    pub permutation_challenges: &'a [SC::Challenge],
     /// Public inputs to the AIR
     pub public_values: &'a Vec<Val<SC>>,
     /// Evaluations of the Selector polynomial for the first row of the trace
     pub is_first_row: PackedVal<SC>,
     /// Evaluations of the Selector polynomial for the last row of the trace
     pub is_last_row: PackedVal<SC>,
-    /// Evaluations of the Selector polynomial for rows where transition constraints should be applied
-    pub is_transition: PackedVal<SC>,
+    /// Evaluations of the Selector polynomials for rows where transition constraints should be
+    /// applied, for transition windows of 2, 3, ... up to the window size rows
+    pub is_transition_windows: Vec<PackedVal<SC>>,
     /// Challenge powers used for randomized constraint combination
     pub alpha_powers: &'a [SC::Challenge],
     /// Challenge powers decomposed into their base field component.
@@ -41,22 +52,45 @@ pub struct ProverConstraintFolder<'a, SC: StarkGenericConfig> {
 /// using a more efficient accumulation method for verification.
 #[derive(Debug)]
 pub struct VerifierConstraintFolder<'a, SC: StarkGenericConfig> {
-    /// Pair of consecutive rows from the committed polynomial evaluations
-    pub main: ViewPair<'a, SC::Challenge>,
+    /// Window of consecutive rows from the committed polynomial evaluations
+    pub main: RowMajorMatrixView<'a, SC::Challenge>,
+    /// Window of consecutive rows of the preprocessed columns, empty if the AIR has none
+    pub preprocessed: RowMajorMatrixView<'a, SC::Challenge>,
+    /// Window of consecutive rows of the permutation columns, empty if the AIR has none
+    pub permutation: RowMajorMatrixView<'a, SC::Challenge>,
+    /// Challenges the permutation columns were built from
+    pub permutation_challenges: &'a [SC::Challenge],
     /// Public values that are inputs to the computation
     pub public_values: &'a Vec<Val<SC>>,
     /// Evaluations of the Selector polynomial for the first row of the trace
     pub is_first_row: SC::Challenge,
     /// Evaluations of the Selector polynomial for the last row of the trace
     pub is_last_row: SC::Challenge,
-    /// Evaluations of the Selector polynomial for rows where transition constraints should be applied
-    pub is_transition: SC::Challenge,
+    /// Evaluations of the Selector polynomials for rows where transition constraints should be
+    /// applied, for transition windows of 2, 3, ... up to the window size rows
+    pub is_transition_windows: Vec<SC::Challenge>,
     /// Single challenge value used for constraint combination
     pub alpha: SC::Challenge,
     /// Running accumulator for all constraints
     pub accumulator: SC::Challenge,
 }
 
+/// The selector of the transition window of `size` rows, given the selectors of the windows of
+/// 2, 3, ... rows.
+#[inline]
+fn transition_window<T: Copy>(is_transition_windows: &[T], size: usize) -> T {
+    match size
+        .checked_sub(2)
+        .and_then(|i| is_transition_windows.get(i))
+    {
+        Some(&selector) => selector,
+        None => panic!(
+            "a transition window must have between 2 and {} rows, not {size}",
+            is_transition_windows.len() + 1
+        ),
+    }
+}
+
 impl<'a, SC: StarkGenericConfig> AirBuilder for ProverConstraintFolder<'a, SC> {
     type F = Val<SC>;
     type Expr = PackedVal<SC>;
@@ -81,14 +115,10 @@ impl<'a, SC: StarkGenericConfig> AirBuilder for ProverConstraintFolder<'a, SC> {
     /// Returns an expression indicating rows where transition constraints should be checked.
     ///
     /// # Panics
-    /// This function panics if `size` is not `2`.
+    /// This function panics if `size` is less than `2` or greater than the window size.
     #[inline]
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            self.is_transition
-        } else {
-            panic!("uni-stark only supports a window size of 2")
-        }
+        transition_window(&self.is_transition_windows, size)
     }
 
     #[inline]
@@ -119,11 +149,49 @@ impl<SC: StarkGenericConfig> AirBuilderWithPublicValues for ProverConstraintFold
     }
 }
 
+impl<SC: StarkGenericConfig> PairBuilder for ProverConstraintFolder<'_, SC> {
+    #[inline]
+    fn preprocessed(&self) -> Self::M {
+        self.preprocessed
+    }
+}
+
+impl<SC: StarkGenericConfig> ExtensionBuilder for ProverConstraintFolder<'_, SC> {
+    type EF = SC::Challenge;
+    type ExprEF = PackedChallenge<SC>;
+    type VarEF = PackedChallenge<SC>;
+
+    #[inline]
+    fn assert_zero_ext<I>(&mut self, x: I)
+    where
+        I: Into<Self::ExprEF>,
+    {
+        let alpha_power = self.alpha_powers[self.constraint_index];
+        self.accumulator += Into::<PackedChallenge<SC>>::into(alpha_power) * x.into();
+        self.constraint_index += 1;
+    }
+}
+
+impl<'a, SC: StarkGenericConfig> PermutationAirBuilder for ProverConstraintFolder<'a, SC> {
+    type MP = RowMajorMatrixView<'a, PackedChallenge<SC>>;
+    type RandomVar = SC::Challenge;
+
+    #[inline]
+    fn permutation(&self) -> Self::MP {
+        self.permutation
+    }
+
+    #[inline]
+    fn permutation_randomness(&self) -> &[Self::RandomVar] {
+        self.permutation_challenges
+    }
+}
+
 impl<'a, SC: StarkGenericConfig> AirBuilder for VerifierConstraintFolder<'a, SC> {
     type F = Val<SC>;
     type Expr = SC::Challenge;
     type Var = SC::Challenge;
-    type M = ViewPair<'a, SC::Challenge>;
+    type M = RowMajorMatrixView<'a, SC::Challenge>;
 
     fn main(&self) -> Self::M {
         self.main
@@ -140,13 +208,9 @@ impl<'a, SC: StarkGenericConfig> AirBuilder for VerifierConstraintFolder<'a, SC>
     /// Returns an expression indicating rows where transition constraints should be checked.
     ///
     /// # Panics
-    /// This function panics if `size` is not `2`.
+    /// This function panics if `size` is less than `2` or greater than the window size.
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            self.is_transition
-        } else {
-            panic!("uni-stark only supports a window size of 2")
-        }
+        transition_window(&self.is_transition_windows, size)
     }
 
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
@@ -162,3 +226,35 @@ impl<SC: StarkGenericConfig> AirBuilderWithPublicValues for VerifierConstraintFo
         self.public_values
     }
 }
+
+impl<SC: StarkGenericConfig> PairBuilder for VerifierConstraintFolder<'_, SC> {
+    fn preprocessed(&self) -> Self::M {
+        self.preprocessed
+    }
+}
+
+impl<SC: StarkGenericConfig> ExtensionBuilder for VerifierConstraintFolder<'_, SC> {
+    type EF = SC::Challenge;
+    type ExprEF = SC::Challenge;
+    type VarEF = SC::Challenge;
+
+    fn assert_zero_ext<I>(&mut self, x: I)
+    where
+        I: Into<Self::ExprEF>,
+    {
+        self.assert_zero(x);
+    }
+}
+
+impl<'a, SC: StarkGenericConfig> PermutationAirBuilder for VerifierConstraintFolder<'a, SC> {
+    type MP = RowMajorMatrixView<'a, SC::Challenge>;
+    type RandomVar = SC::Challenge;
+
+    fn permutation(&self) -> Self::MP {
+        self.permutation
+    }
+
+    fn permutation_randomness(&self) -> &[Self::RandomVar] {
+        self.permutation_challenges
+    }
+}
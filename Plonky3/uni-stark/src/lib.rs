@@ -4,8 +4,13 @@
 
 extern crate alloc;
 
+mod air_digest;
+mod batch_prover;
+mod batch_verifier;
 mod config;
 mod folder;
+mod lookup;
+mod preprocessed;
 mod proof;
 mod prover;
 mod symbolic_builder;
@@ -15,9 +20,14 @@ mod verifier;
 
 mod check_constraints;
 
+pub use air_digest::*;
+pub use batch_prover::*;
+pub use batch_verifier::*;
 pub use check_constraints::*;
 pub use config::*;
 pub use folder::*;
+pub use lookup::*;
+pub use preprocessed::*;
 pub use proof::*;
 pub use prover::*;
 pub use symbolic_builder::*;
@@ -0,0 +1,353 @@
+//! LogUp lookup arguments, within an AIR and between the tables of a batch proof.
+//!
+//! With challenges `beta` and `gamma`, a tuple `(f_0, ..., f_{k-1})` sent over bus `b` has the
+//! fingerprint `b + gamma * f_0 + ... + gamma^k * f_{k-1}`. For every interaction, a permutation
+//! column `h` holds `m / (beta - fingerprint)` on each row, where the multiplicity `m` is the
+//! interaction's count for sends and its negation for receives. A final column `s` is the running
+//! sum of all `h` columns, and its value on the last row is the cumulative sum. If the cumulative
+//! sums of all tables add up to zero then, with high probability, every tuple is sent over its bus
+//! as often as it is received.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Add;
+
+use p3_air::{
+    Air, BaseAir, ExtensionBuilder, Interaction, InteractionAir, PermutationAirBuilder,
+    VirtualPairCol,
+};
+use p3_field::{ExtensionField, Field, PrimeCharacteristicRing, batch_multiplicative_inverse};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+
+/// A LogUp argument over the interactions of an AIR.
+///
+/// An AIR which looks values up in a table of its own, such as a [`RangeTable`], adds
+/// [`Self::width`] permutation columns and [`LogUp::NUM_CHALLENGES`] permutation challenges,
+/// builds the columns with [`Self::generate_trace`] and constrains them with [`Self::eval`].
+#[derive(Clone, Debug)]
+pub struct LogUp<F: Field> {
+    /// The tuples sent, e.g. the values looked up.
+    pub sends: Vec<Interaction<F>>,
+    /// The tuples received, e.g. the rows of a table with their multiplicities.
+    pub receives: Vec<Interaction<F>>,
+}
+
+impl<F: Field> LogUp<F> {
+    /// The number of challenges the argument reads: `beta` and `gamma`.
+    pub const NUM_CHALLENGES: usize = 2;
+
+    /// The number of permutation columns: one per interaction and the running sum.
+    pub fn width(&self) -> usize {
+        self.sends.len() + self.receives.len() + 1
+    }
+
+    fn interactions(&self) -> impl Iterator<Item = (&Interaction<F>, bool)> {
+        let sends = self.sends.iter().map(|interaction| (interaction, true));
+        sends.chain(self.receives.iter().map(|interaction| (interaction, false)))
+    }
+
+    /// Build the permutation columns of the argument over `preprocessed` and `main` under the
+    /// challenges `[beta, gamma]`.
+    pub fn generate_trace<EF: ExtensionField<F>>(
+        &self,
+        preprocessed: Option<&RowMajorMatrix<F>>,
+        main: &RowMajorMatrix<F>,
+        challenges: &[EF],
+    ) -> RowMajorMatrix<EF> {
+        let (beta, gamma) = (challenges[0], challenges[1]);
+        let (denominators, multiplicities): (Vec<EF>, Vec<F>) = (0..main.height())
+            .flat_map(|r| {
+                let main = main.row_slice(r).expect("row index is in range");
+                let preprocessed = preprocessed
+                    .map(|p| {
+                        p.row_slice(r)
+                            .expect("preprocessed trace is too short")
+                            .to_vec()
+                    })
+                    .unwrap_or_default();
+                self.interactions()
+                    .map(|(interaction, is_send)| {
+                        let fields = interaction
+                            .fields
+                            .iter()
+                            .map(|field| field.apply::<F, F>(&preprocessed, &main));
+                        let count = interaction.count.apply::<F, F>(&preprocessed, &main);
+                        (
+                            beta - fingerprint(interaction.bus, fields, gamma),
+                            if is_send { count } else { -count },
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unzip();
+        let inverses = batch_multiplicative_inverse(&denominators);
+
+        let num_interactions = self.width() - 1;
+        let mut values = Vec::with_capacity(main.height() * self.width());
+        let mut sum = EF::ZERO;
+        for (inverses, multiplicities) in inverses
+            .chunks_exact(num_interactions)
+            .zip(multiplicities.chunks_exact(num_interactions))
+        {
+            let start = values.len();
+            values.extend(
+                inverses
+                    .iter()
+                    .zip(multiplicities)
+                    .map(|(&inv, &m)| inv * m),
+            );
+            sum += values[start..].iter().copied().sum::<EF>();
+            values.push(sum);
+        }
+        RowMajorMatrix::new(values, self.width())
+    }
+
+    /// Constrain the permutation columns starting at `permutation_offset`, under the challenges
+    /// `beta` and `gamma` starting at `challenge_offset`, to sum the interactions of the rows
+    /// `preprocessed` and `main` up to `cumulative_sum`.
+    ///
+    /// An AIR whose sends and receives balance on their own has a cumulative sum of zero.
+    pub fn eval<AB>(
+        &self,
+        builder: &mut AB,
+        preprocessed: &[AB::Var],
+        main: &[AB::Var],
+        permutation_offset: usize,
+        challenge_offset: usize,
+        cumulative_sum: AB::ExprEF,
+    ) where
+        AB: PermutationAirBuilder<F = F>,
+        AB::Var: Copy,
+    {
+        let permutation = builder.permutation();
+        let num_interactions = self.width() - 1;
+        let (perm_local, perm_next) = (
+            permutation.row_slice(0).expect("Matrix is empty?"),
+            permutation.row_slice(1).expect("Matrix only has 1 row?"),
+        );
+        let columns = permutation_offset..permutation_offset + num_interactions;
+        let (h, s): (&[AB::VarEF], AB::ExprEF) =
+            (&perm_local[columns.clone()], perm_local[columns.end].into());
+        let (h_next, s_next): (&[AB::VarEF], AB::ExprEF) =
+            (&perm_next[columns.clone()], perm_next[columns.end].into());
+        let randomness = builder.permutation_randomness();
+        let (beta, gamma): (AB::ExprEF, AB::ExprEF) = (
+            randomness[challenge_offset].into(),
+            randomness[challenge_offset + 1].into(),
+        );
+
+        for ((interaction, is_send), &h) in self.interactions().zip(h) {
+            let fields = interaction.fields.iter().map(|field| {
+                AB::ExprEF::from(field.apply::<AB::Expr, AB::Var>(preprocessed, main))
+            });
+            let count = interaction
+                .count
+                .apply::<AB::Expr, AB::Var>(preprocessed, main);
+            let multiplicity = if is_send { count } else { -count };
+            let denominator = beta.clone() - fingerprint(interaction.bus, fields, gamma.clone());
+            let h: AB::ExprEF = h.into();
+            builder.assert_eq_ext(h * denominator, multiplicity);
+        }
+
+        let sum = |h: &[AB::VarEF]| h.iter().map(|&h| h.into()).sum::<AB::ExprEF>();
+        builder.when_first_row().assert_eq_ext(s.clone(), sum(h));
+        builder
+            .when_transition()
+            .assert_eq_ext(s_next, s.clone() + sum(h_next));
+        builder.when_last_row().assert_eq_ext(s, cumulative_sum);
+    }
+}
+
+/// `bus + gamma * fields[0] + gamma^2 * fields[1] + ...`
+fn fingerprint<T, E>(bus: usize, fields: impl DoubleEndedIterator<Item = T>, gamma: E) -> E
+where
+    E: PrimeCharacteristicRing + Add<T, Output = E> + Clone,
+{
+    fields
+        .rev()
+        .fold(E::ZERO, |acc, field| (acc + field) * gamma.clone())
+        + E::from_usize(bus)
+}
+
+/// A preprocessed column holding every value in `0..2^log_size`, in which an AIR range checks
+/// values with a [`LogUp`] argument: each value to check is sent over `bus`, and the table
+/// receives each of its rows as often as the row's multiplicity, a main trace column.
+///
+/// The trace must have at least `2^log_size` rows. The column is padded with zeros below the
+/// table, which is harmless as zero is in range.
+///
+/// As the table is a preprocessed column, it only serves lookups from the AIR it is part of,
+/// proven with [`prove_with_preprocessed`](crate::prove_with_preprocessed): batch proofs do not
+/// support preprocessed columns, so it cannot be a table of its own in a batch.
+#[derive(Clone, Copy, Debug)]
+pub struct RangeTable {
+    pub log_size: usize,
+    pub bus: usize,
+}
+
+impl RangeTable {
+    /// A table of all bytes, on bus 0.
+    pub const BYTE: Self = Self::new(8, 0);
+
+    /// A table of all 16-bit values, on bus 1, so that it can be used alongside [`Self::BYTE`].
+    pub const U16: Self = Self::new(16, 1);
+
+    pub const fn new(log_size: usize, bus: usize) -> Self {
+        Self { log_size, bus }
+    }
+
+    /// The number of values in the table, which must fit in a trace of `height` rows.
+    fn size(&self, height: usize) -> usize {
+        let size = 1 << self.log_size;
+        assert!(
+            height >= size,
+            "a range table of {size} values needs a trace of at least {size} rows"
+        );
+        size
+    }
+
+    /// The preprocessed column of the table for a trace of `height` rows.
+    pub fn column<F: Field>(&self, height: usize) -> Vec<F> {
+        let size = self.size(height);
+        let mut column = F::zero_vec(height);
+        for (value, entry) in column.iter_mut().enumerate().take(size) {
+            *entry = F::from_usize(value);
+        }
+        column
+    }
+
+    /// The multiplicity column of the table for a trace of `height` rows: how often each table
+    /// row is looked up by `values`. Values outside the table are not counted, so their lookups
+    /// do not balance.
+    pub fn multiplicities<F: Field>(
+        &self,
+        height: usize,
+        values: impl IntoIterator<Item = u64>,
+    ) -> Vec<F> {
+        let size = self.size(height) as u64;
+        let mut counts = vec![0u32; height];
+        for value in values {
+            if value < size {
+                counts[value as usize] += 1;
+            }
+        }
+        counts.into_iter().map(F::from_u32).collect()
+    }
+
+    /// Look `value` up in the table `count` times, usually once or when a selector is set.
+    pub fn lookup<F: Field>(
+        &self,
+        value: VirtualPairCol<F>,
+        count: VirtualPairCol<F>,
+    ) -> Interaction<F> {
+        Interaction {
+            fields: vec![value],
+            count,
+            bus: self.bus,
+        }
+    }
+
+    /// Answer the lookups from the table in the preprocessed column `column`, with the
+    /// multiplicities in the main column `multiplicity`.
+    pub fn table<F: Field>(&self, column: usize, multiplicity: usize) -> Interaction<F> {
+        Interaction {
+            fields: vec![VirtualPairCol::single_preprocessed(column)],
+            count: VirtualPairCol::single_main(multiplicity),
+            bus: self.bus,
+        }
+    }
+}
+
+/// An AIR extended with the LogUp columns and constraints of its bus interactions in a batch.
+///
+/// The LogUp columns follow the AIR's own permutation columns, and `beta`, `gamma` and the
+/// cumulative sum follow its own permutation challenges.
+pub(crate) struct LookupAir<'a, F: Field, A> {
+    pub(crate) air: &'a A,
+    /// The argument over the interactions of the AIR, if it has any.
+    logup: Option<LogUp<F>>,
+}
+
+impl<'a, F: Field, A: InteractionAir<F>> LookupAir<'a, F, A> {
+    pub(crate) fn new(air: &'a A) -> Self {
+        let logup = LogUp {
+            sends: air.sends(),
+            receives: air.receives(),
+        };
+        let has_interactions = !logup.sends.is_empty() || !logup.receives.is_empty();
+        Self {
+            air,
+            logup: has_interactions.then_some(logup),
+        }
+    }
+}
+
+impl<F: Field, A> LookupAir<'_, F, A> {
+    pub(crate) const fn has_interactions(&self) -> bool {
+        self.logup.is_some()
+    }
+
+    /// Build the LogUp columns of `main` under `beta` and `gamma`, if the AIR has interactions.
+    pub(crate) fn lookup_trace<EF: ExtensionField<F>>(
+        &self,
+        main: &RowMajorMatrix<F>,
+        beta: EF,
+        gamma: EF,
+    ) -> Option<RowMajorMatrix<EF>> {
+        self.logup
+            .as_ref()
+            .map(|logup| logup.generate_trace(None, main, &[beta, gamma]))
+    }
+}
+
+impl<F: Field, A: BaseAir<F>> BaseAir<F> for LookupAir<'_, F, A> {
+    fn width(&self) -> usize {
+        self.air.width()
+    }
+
+    fn window_size(&self) -> usize {
+        self.air.window_size()
+    }
+
+    fn num_permutation_challenges(&self) -> usize {
+        // `beta`, `gamma` and the cumulative sum.
+        self.air.num_permutation_challenges()
+            + self
+                .logup
+                .as_ref()
+                .map_or(0, |_| LogUp::<F>::NUM_CHALLENGES + 1)
+    }
+
+    fn permutation_width(&self) -> usize {
+        self.air.permutation_width() + self.logup.as_ref().map_or(0, LogUp::width)
+    }
+}
+
+impl<AB, A> Air<AB> for LookupAir<'_, AB::F, A>
+where
+    AB: PermutationAirBuilder,
+    AB::Var: Copy,
+    A: Air<AB>,
+{
+    fn eval(&self, builder: &mut AB) {
+        self.air.eval(builder);
+        let Some(logup) = &self.logup else {
+            return;
+        };
+
+        let main = builder.main();
+        let local = main.row_slice(0).expect("Matrix is empty?");
+        let offset = self.air.num_permutation_challenges();
+        let cumulative_sum =
+            builder.permutation_randomness()[offset + LogUp::<AB::F>::NUM_CHALLENGES];
+        logup.eval(
+            builder,
+            &[],
+            &local,
+            self.air.permutation_width(),
+            offset,
+            cumulative_sum.into(),
+        );
+    }
+}
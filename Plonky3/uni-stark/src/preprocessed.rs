@@ -0,0 +1,102 @@
+use p3_air::BaseAir;
+use p3_commit::Pcs;
+use p3_matrix::Matrix;
+use p3_util::log2_strict_usize;
+use serde::{Deserialize, Serialize};
+use tracing::instrument;
+
+use crate::{Com, PcsProverData, StarkGenericConfig, Val};
+
+/// The prover's commitment data for the preprocessed columns of an AIR.
+///
+/// Produced once by [`setup_preprocessed`] and passed to [`prove_with_preprocessed`] for every
+/// proof of the AIR.
+///
+/// [`prove_with_preprocessed`]: crate::prove_with_preprocessed
+pub struct PreprocessedProverData<SC: StarkGenericConfig> {
+    pub(crate) width: usize,
+    pub(crate) degree_bits: usize,
+    pub(crate) commitment: Com<SC>,
+    pub(crate) prover_data: PcsProverData<SC>,
+}
+
+/// The verifier's view of the preprocessed columns of an AIR: their width, the log of their
+/// height and the commitment to them.
+///
+/// The verifier must obtain this key from a trusted source, typically by running
+/// [`setup_preprocessed`] itself, as it fixes the preprocessed columns the proof is checked
+/// against.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct PreprocessedVerifierKey<SC: StarkGenericConfig> {
+    pub(crate) width: usize,
+    pub(crate) degree_bits: usize,
+    pub(crate) commitment: Com<SC>,
+}
+
+impl<SC: StarkGenericConfig> PreprocessedVerifierKey<SC> {
+    /// The number of preprocessed columns.
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The log of the height of the preprocessed columns, which every trace must match.
+    pub const fn degree_bits(&self) -> usize {
+        self.degree_bits
+    }
+}
+
+impl<SC: StarkGenericConfig> Clone for PreprocessedVerifierKey<SC> {
+    fn clone(&self) -> Self {
+        Self {
+            width: self.width,
+            degree_bits: self.degree_bits,
+            commitment: self.commitment.clone(),
+        }
+    }
+}
+
+/// Commit to the preprocessed trace of `air`, if it has one.
+///
+/// Returns `None` if [`BaseAir::preprocessed_trace`] does. Otherwise every trace proven against
+/// the returned data must have the same height as the preprocessed trace.
+///
+/// # Panics
+/// This function panics if the PCS is zero-knowledge: a hiding commitment is randomised, so a
+/// verifier could not reproduce the preprocessed commitment.
+#[instrument(skip_all)]
+pub fn setup_preprocessed<SC, A>(
+    config: &SC,
+    air: &A,
+) -> Option<(PreprocessedProverData<SC>, PreprocessedVerifierKey<SC>)>
+where
+    SC: StarkGenericConfig,
+    A: BaseAir<Val<SC>>,
+{
+    let preprocessed = air.preprocessed_trace()?;
+    assert!(
+        !SC::Pcs::ZK,
+        "preprocessed columns are not supported with a zero-knowledge PCS"
+    );
+
+    let width = preprocessed.width();
+    let degree = preprocessed.height();
+    let degree_bits = log2_strict_usize(degree);
+
+    let pcs = config.pcs();
+    let domain = pcs.natural_domain_for_degree(degree);
+    let (commitment, prover_data) = pcs.commit([(domain, preprocessed)]);
+
+    let verifier_key = PreprocessedVerifierKey {
+        width,
+        degree_bits,
+        commitment: commitment.clone(),
+    };
+    let prover_data = PreprocessedProverData {
+        width,
+        degree_bits,
+        commitment,
+        prover_data,
+    };
+    Some((prover_data, verifier_key))
+}
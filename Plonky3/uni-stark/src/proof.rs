@@ -5,15 +5,21 @@ use serde::{Deserialize, Serialize};
 
 use crate::StarkGenericConfig;
 
-type Com<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
+pub(crate) type Com<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
     <SC as StarkGenericConfig>::Challenge,
     <SC as StarkGenericConfig>::Challenger,
 >>::Commitment;
-type PcsProof<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
+pub(crate) type PcsProverData<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
+    <SC as StarkGenericConfig>::Challenge,
+    <SC as StarkGenericConfig>::Challenger,
+>>::ProverData;
+/// The opening proof of the configuration's PCS.
+pub type PcsProof<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
     <SC as StarkGenericConfig>::Challenge,
     <SC as StarkGenericConfig>::Challenger,
 >>::Proof;
 
+/// A proof that a trace satisfies an AIR, see [`prove`](crate::prove).
 #[derive(Serialize, Deserialize)]
 #[serde(bound = "")]
 pub struct Proof<SC: StarkGenericConfig> {
@@ -23,17 +29,111 @@ pub struct Proof<SC: StarkGenericConfig> {
     pub(crate) degree_bits: usize,
 }
 
+impl<SC: StarkGenericConfig> Proof<SC> {
+    /// The commitments to the trace, the permutation trace, the quotient chunks and, with a
+    /// zero-knowledge PCS, the random polynomial.
+    pub const fn commitments(&self) -> &Commitments<Com<SC>> {
+        &self.commitments
+    }
+
+    /// The values the committed polynomials were opened to at the out-of-domain point.
+    pub const fn opened_values(&self) -> &OpenedValues<SC::Challenge> {
+        &self.opened_values
+    }
+
+    /// The PCS's proof that the opened values are correct, e.g. a FRI proof.
+    pub const fn opening_proof(&self) -> &PcsProof<SC> {
+        &self.opening_proof
+    }
+
+    /// The base 2 logarithm of the trace height. With a zero-knowledge PCS the committed trace is
+    /// randomized to twice this height.
+    pub const fn degree_bits(&self) -> usize {
+        self.degree_bits - SC::Pcs::ZK as usize
+    }
+}
+
+/// A proof that several traces, of possibly different heights, satisfy their AIRs and that the
+/// tuples they send and receive over buses balance. See [`prove_batch`](crate::prove_batch).
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct BatchProof<SC: StarkGenericConfig> {
+    /// The traces, the permutation traces and the quotient chunks of all instances are each
+    /// committed in a single batch.
+    pub(crate) commitments: Commitments<Com<SC>>,
+    pub(crate) opened_values: Vec<OpenedValues<SC::Challenge>>,
+    /// The cumulative sum of the LogUp columns of each instance with interactions.
+    pub(crate) cumulative_sums: Vec<Option<SC::Challenge>>,
+    pub(crate) opening_proof: PcsProof<SC>,
+    pub(crate) degree_bits: Vec<usize>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Commitments<Com> {
     pub(crate) trace: Com,
+    pub(crate) permutation: Option<Com>,
     pub(crate) quotient_chunks: Com,
     pub(crate) random: Option<Com>,
 }
 
+impl<Com> Commitments<Com> {
+    /// The commitment to the main trace.
+    pub const fn trace(&self) -> &Com {
+        &self.trace
+    }
+
+    /// The commitment to the permutation trace, if the AIR has one.
+    pub const fn permutation(&self) -> Option<&Com> {
+        self.permutation.as_ref()
+    }
+
+    /// The commitment to the chunks the quotient polynomial is split into.
+    pub const fn quotient_chunks(&self) -> &Com {
+        &self.quotient_chunks
+    }
+
+    /// The commitment to the random polynomial of a zero-knowledge PCS.
+    pub const fn random(&self) -> Option<&Com> {
+        self.random.as_ref()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OpenedValues<Challenge> {
-    pub(crate) trace_local: Vec<Challenge>,
-    pub(crate) trace_next: Vec<Challenge>,
+    /// The trace columns opened at `zeta * g^k` for each row `k` of the AIR's window, where `g`
+    /// generates the trace domain: the local row, the next row and so on.
+    pub(crate) trace_window: Vec<Vec<Challenge>>,
+    pub(crate) preprocessed_window: Option<Vec<Vec<Challenge>>>,
+    /// The permutation columns flattened to the base field, as committed.
+    pub(crate) permutation_window: Option<Vec<Vec<Challenge>>>,
     pub(crate) quotient_chunks: Vec<Vec<Challenge>>,
     pub(crate) random: Option<Vec<Challenge>>,
 }
+
+impl<Challenge> OpenedValues<Challenge> {
+    /// The trace columns of each row of the AIR's window, starting with the local row.
+    pub fn trace_window(&self) -> &[Vec<Challenge>] {
+        &self.trace_window
+    }
+
+    /// The preprocessed columns of each row of the window, if the AIR has any.
+    pub fn preprocessed_window(&self) -> Option<&[Vec<Challenge>]> {
+        self.preprocessed_window.as_deref()
+    }
+
+    /// The permutation columns, flattened to the base field, of each row of the window, if the
+    /// AIR has any.
+    pub fn permutation_window(&self) -> Option<&[Vec<Challenge>]> {
+        self.permutation_window.as_deref()
+    }
+
+    /// Each quotient chunk flattened to the base field.
+    pub fn quotient_chunks(&self) -> &[Vec<Challenge>] {
+        &self.quotient_chunks
+    }
+
+    /// The random polynomial of a zero-knowledge PCS, flattened to the base field.
+    pub fn random(&self) -> Option<&[Challenge]> {
+        self.random.as_deref()
+    }
+}
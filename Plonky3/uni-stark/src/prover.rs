@@ -1,11 +1,12 @@
 use alloc::vec;
 use alloc::vec::Vec;
+use core::iter;
 
 use itertools::Itertools;
 use p3_air::Air;
 use p3_challenger::{CanObserve, FieldChallenger};
 use p3_commit::{Pcs, PolynomialSpace};
-use p3_field::{BasedVectorSpace, PackedValue, PrimeCharacteristicRing};
+use p3_field::{BasedVectorSpace, ExtensionField, Field, PackedValue, PrimeCharacteristicRing};
 use p3_matrix::Matrix;
 use p3_matrix::dense::RowMajorMatrix;
 use p3_maybe_rayon::prelude::*;
@@ -13,15 +14,16 @@ use p3_util::log2_strict_usize;
 use tracing::{debug_span, info_span, instrument};
 
 use crate::{
-    Commitments, Domain, OpenedValues, PackedChallenge, PackedVal, Proof, ProverConstraintFolder,
-    StarkGenericConfig, SymbolicAirBuilder, Val, get_log_quotient_degree, get_symbolic_constraints,
+    Commitments, Domain, OpenedValues, PackedChallenge, PackedVal, PreprocessedProverData, Proof,
+    ProverConstraintFolder, StarkGenericConfig, SymbolicAirBuilder, Val, air_digest,
+    get_log_quotient_degree, get_symbolic_constraints,
 };
 
 #[instrument(skip_all)]
 #[allow(clippy::multiple_bound_locations)] // cfg not supported in where clauses?
 pub fn prove<
     SC,
-    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>>>,
+    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>,
     #[cfg(not(debug_assertions))] A,
 >(
     config: &SC,
@@ -33,16 +35,54 @@ where
     SC: StarkGenericConfig,
     A: Air<SymbolicAirBuilder<Val<SC>>> + for<'a> Air<ProverConstraintFolder<'a, SC>>,
 {
-    #[cfg(debug_assertions)]
-    crate::check_constraints::check_constraints(air, &trace, public_values);
+    prove_with_preprocessed(config, air, trace, public_values, None)
+}
 
+/// Prove that `trace` satisfies `air`, whose preprocessed columns were committed to by
+/// [`setup_preprocessed`](crate::setup_preprocessed).
+///
+/// `preprocessed` must be `Some` exactly when the AIR reads [`PairBuilder::preprocessed`], and
+/// the trace must have the same height as the preprocessed trace.
+///
+/// If the AIR has permutation columns, see [`BaseAir::permutation_trace`], they are built from
+/// the trace and challenges sampled after it is committed, and committed in a round of their own.
+///
+/// [`PairBuilder::preprocessed`]: p3_air::PairBuilder::preprocessed
+/// [`BaseAir::permutation_trace`]: p3_air::BaseAir::permutation_trace
+#[instrument(skip_all)]
+#[allow(clippy::multiple_bound_locations)] // cfg not supported in where clauses?
+pub fn prove_with_preprocessed<
+    SC,
+    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>,
+    #[cfg(not(debug_assertions))] A,
+>(
+    config: &SC,
+    air: &A,
+    trace: RowMajorMatrix<Val<SC>>,
+    public_values: &Vec<Val<SC>>,
+    preprocessed: Option<&PreprocessedProverData<SC>>,
+) -> Proof<SC>
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>>> + for<'a> Air<ProverConstraintFolder<'a, SC>>,
+{
     // Compute the height `N = 2^n` and `log_2(height)`, `n`, of the trace.
     let degree = trace.height();
     let log_degree = log2_strict_usize(degree);
     let log_ext_degree = log_degree + config.is_zk();
 
+    // The preprocessed columns are committed over the same domain as the trace.
+    let preprocessed_width = preprocessed.map_or(0, |p| p.width);
+    if let Some(preprocessed) = preprocessed {
+        assert_eq!(
+            preprocessed.degree_bits, log_degree,
+            "trace height does not match the preprocessed trace height"
+        );
+    }
+
     // Compute the constraint polynomials as vectors of symbolic expressions.
-    let symbolic_constraints = get_symbolic_constraints(air, 0, public_values.len());
+    let symbolic_constraints =
+        get_symbolic_constraints(air, preprocessed_width, public_values.len());
 
     // Count the number of constraints that we have.
     let constraint_count = symbolic_constraints.len();
@@ -75,13 +115,21 @@ where
     // The `+1` is due to the `is_transition` selector which is not accounted for in `deg(C)`. Note
     // that S_i^2 should never appear in a constraint as it should just be replaced by `S_i`.
     //
+    // An AIR with a window of `w` rows also reads `T_i(h^2 x), ..., T_i(h^{w-1} x)`, and its
+    // transition window selectors have degree up to `w - 1`, which is why the constraint degree
+    // is padded to at least the window size.
+    //
     // For now in comments we assume that `deg(C) = 3` meaning `deg(C(x)) <= 3N - 2`
 
     // From the degree of the constraint polynomial, compute the number
     // of quotient polynomials we will split Q(x) into. This is chosen to
     // always be a power of 2.
-    let log_quotient_degree =
-        get_log_quotient_degree::<Val<SC>, A>(air, 0, public_values.len(), config.is_zk());
+    let log_quotient_degree = get_log_quotient_degree::<Val<SC>, A>(
+        air,
+        preprocessed_width,
+        public_values.len(),
+        config.is_zk(),
+    );
     let quotient_degree = 1 << (log_quotient_degree + config.is_zk());
 
     // Initialize the PCS and the Challenger.
@@ -106,14 +154,28 @@ where
     //      trace_commit contains the root of the tree
     //      trace_data contains the entire tree.
     //          - trace_data.leaves is the matrix containing `ET`.
-    let (trace_commit, trace_data) =
-        info_span!("commit to trace data").in_scope(|| pcs.commit([(ext_trace_domain, trace)]));
+    //
+    // The trace itself is kept, as the permutation trace and the debug constraint check read it.
+    let (trace_commit, trace_data) = info_span!("commit to trace data")
+        .in_scope(|| pcs.commit([(ext_trace_domain, trace.clone())]));
 
     // Observe the instance.
     // degree < 2^255 so we can safely cast log_degree to a u8.
     challenger.observe(Val::<SC>::from_u8(log_ext_degree as u8));
     challenger.observe(Val::<SC>::from_u8(log_degree as u8));
-    // TODO: Might be best practice to include other instance data here; see verifier comment.
+
+    // Observe the digest of the AIR, so the proof is bound to the constraints it was proven for.
+    challenger.observe_slice(&air_digest(
+        config,
+        air,
+        preprocessed_width,
+        public_values.len(),
+    ));
+
+    // Observe the commitment to the preprocessed columns, which fixes them as part of the instance.
+    if let Some(preprocessed) = preprocessed {
+        challenger.observe(preprocessed.commitment.clone());
+    }
 
     // Observe the Merkle root of the trace commitment.
     challenger.observe(trace_commit.clone());
@@ -121,6 +183,40 @@ where
     // Observe the public input values.
     challenger.observe_slice(public_values);
 
+    // Sample the challenges for the permutation columns, if the AIR has any, and commit to the
+    // permutation trace built from them. The commitment is observed before `alpha` is sampled,
+    // so the permutation trace is fixed before the constraints are combined.
+    let permutation_width = air.permutation_width();
+    let permutation_challenges: Vec<SC::Challenge> = (0..air.num_permutation_challenges())
+        .map(|_| challenger.sample_algebra_element())
+        .collect();
+    let permutation_trace = air.permutation_trace(&trace, &permutation_challenges);
+    assert_eq!(
+        permutation_trace.as_ref().map_or(0, Matrix::width),
+        permutation_width,
+        "permutation trace width does not match the AIR's permutation width"
+    );
+
+    #[cfg(debug_assertions)]
+    crate::check_constraints::check_constraints_with_permutation(
+        air,
+        &trace,
+        permutation_trace.as_ref(),
+        &permutation_challenges,
+        public_values,
+    );
+
+    // Like the quotient, the extension field permutation columns are committed as base field
+    // columns, `DIMENSION` per extension column.
+    let permutation_commit_and_data = permutation_trace.map(|permutation| {
+        info_span!("commit to permutation trace")
+            .in_scope(|| pcs.commit([(ext_trace_domain, permutation.flatten_to_base())]))
+    });
+    if let Some((permutation_commit, _)) = &permutation_commit_and_data {
+        challenger.observe(permutation_commit.clone());
+    }
+    let (permutation_commit, permutation_data) = permutation_commit_and_data.unzip();
+
     // Get the first Fiat Shamir challenge which will be used to combine all constraint polynomials
     // into a single polynomial.
     //
@@ -132,15 +228,8 @@ where
     // This is a polynomial of degree n, so it has at most n roots. Thus the probability of this
     // occurring for a given trace and set of constraints is n/|EF|.
     //
-    // Currently, we do not observe data about the constraint polynomials directly. In particular
-    // a prover could take a trace and fiddle around with the AIR it claims to satisfy without
-    // changing this sample alpha.
-    //
-    // In particular this means that a malicious prover could create a custom AIR for a given trace
-    // such that equation (1) holds. However, such AIRs would need to be very specific and
-    // so such tampering should be obvious to spot. The verifier needs to check the AIR anyway to
-    // confirm that satisfying it indeed proves what the prover claims. Hence this should not be
-    // a soundness issue.
+    // The digest of the AIR was observed above, so a prover cannot fiddle around with the AIR it
+    // claims to satisfy, after seeing alpha, to make equation (1) hold for a given trace.
     let alpha: SC::Challenge = challenger.sample_algebra_element();
 
     // A domain large enough to uniquely identify the quotient polynomial.
@@ -155,6 +244,11 @@ where
     // This only works if the trace domain is `gH'` and the quotient domain is `gK` for some subgroup `K` contained in `H'`.
     // TODO: Make this explicit in `get_evaluations_on_domain` or otherwise fix this.
     let trace_on_quotient_domain = pcs.get_evaluations_on_domain(&trace_data, 0, quotient_domain);
+    let preprocessed_on_quotient_domain =
+        preprocessed.map(|p| pcs.get_evaluations_on_domain(&p.prover_data, 0, quotient_domain));
+    let permutation_on_quotient_domain = permutation_data
+        .as_ref()
+        .map(|data| pcs.get_evaluations_on_domain(data, 0, quotient_domain));
 
     // Compute the quotient polynomial `Q(x)` by evaluating
     //          `C(T_1(x), ..., T_w(x), T_1(hx), ..., T_w(hx), selectors(x)) / Z_H(x)`
@@ -166,6 +260,9 @@ where
         trace_domain,
         quotient_domain,
         trace_on_quotient_domain,
+        preprocessed_on_quotient_domain,
+        permutation_on_quotient_domain,
+        &permutation_challenges,
         alpha,
         constraint_count,
     );
@@ -222,6 +319,7 @@ where
     // will be passed to the verifier.
     let commitments = Commitments {
         trace: trace_commit,
+        permutation: permutation_commit,
         quotient_chunks: quotient_commit,
         random: opt_r_commit.clone(),
     };
@@ -242,34 +340,52 @@ where
     // by zero errors. This doesn't lead to a soundness issue as the verifier will just reject in those
     // cases but it is a completeness issue and contributes a completeness error of |gK| = 2N/|EF|.
     let zeta: SC::Challenge = challenger.sample_algebra_element();
-    let zeta_next = trace_domain.next_point(zeta).unwrap();
+    // The rows of the window are opened at `zeta`, `zeta_next` and the following points.
+    let window_points = window_points(trace_domain, zeta, air.window_size());
 
     let is_random = opt_r_data.is_some();
     let (opened_values, opening_proof) = info_span!("open").in_scope(|| {
         let round0 = opt_r_data.as_ref().map(|r_data| (r_data, vec![vec![zeta]]));
-        let round1 = (&trace_data, vec![vec![zeta, zeta_next]]);
+        let round1 = (&trace_data, vec![window_points.clone()]);
         let round2 = (&quotient_data, vec![vec![zeta]; quotient_degree]); // open every chunk at zeta
-
-        let rounds = round0.into_iter().chain([round1, round2]).collect();
+        let round3 = preprocessed.map(|p| (&p.prover_data, vec![window_points.clone()]));
+        let round4 = permutation_data
+            .as_ref()
+            .map(|data| (data, vec![window_points.clone()]));
+
+        let rounds = round0
+            .into_iter()
+            .chain([round1, round2])
+            .chain(round3)
+            .chain(round4)
+            .collect();
 
         pcs.open(rounds, &mut challenger)
     });
     let trace_idx = SC::Pcs::TRACE_IDX;
     let quotient_idx = SC::Pcs::QUOTIENT_IDX;
-    let trace_local = opened_values[trace_idx][0][0].clone();
-    let trace_next = opened_values[trace_idx][0][1].clone();
+    let trace_window = opened_values[trace_idx][0].clone();
     let quotient_chunks = opened_values[quotient_idx]
         .iter()
         .map(|v| v[0].clone())
         .collect_vec();
+    // The preprocessed round, if any, is opened after the quotient chunks and the permutation
+    // round, if any, after that.
+    let preprocessed_window = preprocessed
+        .is_some()
+        .then(|| opened_values[quotient_idx + 1][0].clone());
+    let permutation_window = permutation_data
+        .is_some()
+        .then(|| opened_values[quotient_idx + 1 + usize::from(preprocessed.is_some())][0].clone());
     let random = if is_random {
         Some(opened_values[0][0][0].clone())
     } else {
         None
     };
     let opened_values = OpenedValues {
-        trace_local,
-        trace_next,
+        trace_window,
+        preprocessed_window,
+        permutation_window,
         quotient_chunks,
         random,
     };
@@ -284,12 +400,15 @@ where
 #[instrument(name = "compute quotient polynomial", skip_all)]
 // TODO: Group some arguments to remove the `allow`?
 #[allow(clippy::too_many_arguments)]
-fn quotient_values<SC, A, Mat>(
+pub(crate) fn quotient_values<SC, A, Mat>(
     air: &A,
     public_values: &Vec<Val<SC>>,
     trace_domain: Domain<SC>,
     quotient_domain: Domain<SC>,
     trace_on_quotient_domain: Mat,
+    preprocessed_on_quotient_domain: Option<Mat>,
+    permutation_on_quotient_domain: Option<Mat>,
+    permutation_challenges: &[SC::Challenge],
     alpha: SC::Challenge,
     constraint_count: usize,
 ) -> Vec<SC::Challenge>
@@ -300,20 +419,36 @@ where
 {
     let quotient_size = quotient_domain.size();
     let width = trace_on_quotient_domain.width();
+    let preprocessed_width = preprocessed_on_quotient_domain
+        .as_ref()
+        .map_or(0, Matrix::width);
+    let permutation_width = permutation_on_quotient_domain
+        .as_ref()
+        .map_or(0, |p| p.width() / SC::Challenge::DIMENSION);
+    let window_size = air.window_size();
     let mut sels = debug_span!("Compute Selectors")
         .in_scope(|| trace_domain.selectors_on_coset(quotient_domain));
 
     let qdb = log2_strict_usize(quotient_domain.size()) - log2_strict_usize(trace_domain.size());
     let next_step = 1 << qdb;
 
+    let is_transition_windows =
+        transition_window_selectors(&sels.is_transition, next_step, window_size);
+
     // We take PackedVal::<SC>::WIDTH worth of values at a time from a quotient_size slice, so we need to
     // pad with default values in the case where quotient_size is smaller than PackedVal::<SC>::WIDTH.
-    for _ in quotient_size..PackedVal::<SC>::WIDTH {
-        sels.is_first_row.push(Val::<SC>::default());
-        sels.is_last_row.push(Val::<SC>::default());
-        sels.is_transition.push(Val::<SC>::default());
-        sels.inv_vanishing.push(Val::<SC>::default());
-    }
+    let padding = PackedVal::<SC>::WIDTH.saturating_sub(quotient_size);
+    let pad = |values: &mut Vec<Val<SC>>| values.extend(vec![Val::<SC>::default(); padding]);
+    pad(&mut sels.is_first_row);
+    pad(&mut sels.is_last_row);
+    pad(&mut sels.inv_vanishing);
+    let is_transition_windows = is_transition_windows
+        .into_iter()
+        .map(|mut selector| {
+            pad(&mut selector);
+            selector
+        })
+        .collect_vec();
 
     let mut alpha_powers = alpha.powers().collect_n(constraint_count);
     alpha_powers.reverse();
@@ -335,21 +470,53 @@ where
 
             let is_first_row = *PackedVal::<SC>::from_slice(&sels.is_first_row[i_range.clone()]);
             let is_last_row = *PackedVal::<SC>::from_slice(&sels.is_last_row[i_range.clone()]);
-            let is_transition = *PackedVal::<SC>::from_slice(&sels.is_transition[i_range.clone()]);
+            let is_transition_windows = is_transition_windows
+                .iter()
+                .map(|selector| *PackedVal::<SC>::from_slice(&selector[i_range.clone()]))
+                .collect();
             let inv_vanishing = *PackedVal::<SC>::from_slice(&sels.inv_vanishing[i_range]);
 
             let main = RowMajorMatrix::new(
-                trace_on_quotient_domain.vertically_packed_row_pair(i_start, next_step),
+                trace_on_quotient_domain.vertically_packed_row_window(
+                    i_start,
+                    next_step,
+                    window_size,
+                ),
                 width,
             );
+            let preprocessed = RowMajorMatrix::new(
+                preprocessed_on_quotient_domain
+                    .as_ref()
+                    .map_or_else(Vec::new, |p| {
+                        p.vertically_packed_row_window(i_start, next_step, window_size)
+                    }),
+                preprocessed_width,
+            );
+            // Reassemble each permutation column from its `DIMENSION` base field columns.
+            let permutation = RowMajorMatrix::new(
+                permutation_on_quotient_domain
+                    .as_ref()
+                    .map_or_else(Vec::new, |p| {
+                        p.vertically_packed_row_window(i_start, next_step, window_size)
+                            .chunks_exact(SC::Challenge::DIMENSION)
+                            .map(|coeffs| {
+                                PackedChallenge::<SC>::from_basis_coefficients_fn(|i| coeffs[i])
+                            })
+                            .collect()
+                    }),
+                permutation_width,
+            );
 
             let accumulator = PackedChallenge::<SC>::ZERO;
             let mut folder = ProverConstraintFolder {
                 main: main.as_view(),
+                preprocessed: preprocessed.as_view(),
+                permutation: permutation.as_view(),
+                permutation_challenges,
                 public_values,
                 is_first_row,
                 is_last_row,
-                is_transition,
+                is_transition_windows,
                 alpha_powers: &alpha_powers,
                 decomposed_alpha_powers: &decomposed_alpha_powers,
                 accumulator,
@@ -369,3 +536,38 @@ where
         })
         .collect()
 }
+
+/// The points at which the rows of a window of `window_size` rows starting at `zeta` are opened:
+/// `zeta`, the next point of `trace_domain` after it, and so on.
+pub(crate) fn window_points<D: PolynomialSpace, EF: ExtensionField<D::Val>>(
+    trace_domain: D,
+    zeta: EF,
+    window_size: usize,
+) -> Vec<EF> {
+    iter::successors(Some(zeta), |&point| {
+        Some(trace_domain.next_point(point).unwrap())
+    })
+    .take(window_size)
+    .collect()
+}
+
+/// The selectors of the transition windows of 2, 3, ... up to `window_size` rows, given the
+/// values of `is_transition` over a domain whose next row is `next_step` positions later. The
+/// selector of a window of `k + 2` rows is the product of `is_transition` over its first `k + 1`
+/// rows, and so vanishes on the last `k + 1` rows of the trace.
+fn transition_window_selectors<F: Field>(
+    is_transition: &[F],
+    next_step: usize,
+    window_size: usize,
+) -> Vec<Vec<F>> {
+    let len = is_transition.len();
+    let mut selectors = vec![is_transition.to_vec()];
+    for k in 1..window_size.saturating_sub(1) {
+        let previous = selectors.last().unwrap();
+        let selector = (0..len)
+            .map(|i| previous[i] * is_transition[(i + k * next_step) % len])
+            .collect();
+        selectors.push(selector);
+    }
+    selectors
+}
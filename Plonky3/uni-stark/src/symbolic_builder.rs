@@ -1,8 +1,12 @@
 use alloc::vec;
 use alloc::vec::Vec;
 
-use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, PairBuilder};
+use p3_air::{
+    Air, AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, PairBuilder,
+    PermutationAirBuilder,
+};
 use p3_field::Field;
+use p3_matrix::Matrix;
 use p3_matrix::dense::RowMajorMatrix;
 use p3_util::log2_ceil_usize;
 use tracing::instrument;
@@ -24,8 +28,12 @@ where
 {
     assert!(is_zk <= 1, "is_zk must be either 0 or 1");
     // We pad to at least degree 2, since a quotient argument doesn't make sense with smaller degrees.
-    let constraint_degree =
-        (get_max_constraint_degree(air, preprocessed_width, num_public_values) + is_zk).max(2);
+    // We also pad to at least the window size: the selector of a transition window of `w` rows has
+    // degree `w - 1`, which the degree of the quotient must leave room for.
+    let constraint_degree = (get_max_constraint_degree(air, preprocessed_width, num_public_values)
+        + is_zk)
+        .max(2)
+        .max(air.window_size());
 
     // The quotient's actual degree is approximately (max_constraint_degree - 1) n,
     // where subtracting 1 comes from division by the vanishing polynomial.
@@ -60,41 +68,69 @@ where
     F: Field,
     A: Air<SymbolicAirBuilder<F>>,
 {
-    let mut builder = SymbolicAirBuilder::new(preprocessed_width, air.width(), num_public_values);
+    let mut builder = SymbolicAirBuilder::new(
+        preprocessed_width,
+        air.width(),
+        air.permutation_width(),
+        air.num_permutation_challenges(),
+        num_public_values,
+        air.window_size(),
+    );
     air.eval(&mut builder);
     builder.constraints()
 }
 
 /// An `AirBuilder` for evaluating constraints symbolically, and recording them for later use.
+///
+/// Only the structure and degree of the constraints matter here, so extension field constraints
+/// and permutation columns are recorded over the base field, as if it were the extension.
 #[derive(Debug)]
 pub struct SymbolicAirBuilder<F: Field> {
     preprocessed: RowMajorMatrix<SymbolicVariable<F>>,
     main: RowMajorMatrix<SymbolicVariable<F>>,
+    permutation: RowMajorMatrix<SymbolicVariable<F>>,
+    permutation_challenges: Vec<SymbolicVariable<F>>,
     public_values: Vec<SymbolicVariable<F>>,
     constraints: Vec<SymbolicExpression<F>>,
 }
 
 impl<F: Field> SymbolicAirBuilder<F> {
-    pub(crate) fn new(preprocessed_width: usize, width: usize, num_public_values: usize) -> Self {
-        let prep_values = [0, 1]
-            .into_iter()
+    pub(crate) fn new(
+        preprocessed_width: usize,
+        width: usize,
+        permutation_width: usize,
+        num_permutation_challenges: usize,
+        num_public_values: usize,
+        window_size: usize,
+    ) -> Self {
+        let prep_values = (0..window_size)
             .flat_map(|offset| {
                 (0..preprocessed_width)
                     .map(move |index| SymbolicVariable::new(Entry::Preprocessed { offset }, index))
             })
             .collect();
-        let main_values = [0, 1]
-            .into_iter()
+        let main_values = (0..window_size)
             .flat_map(|offset| {
                 (0..width).map(move |index| SymbolicVariable::new(Entry::Main { offset }, index))
             })
             .collect();
+        let perm_values = (0..window_size)
+            .flat_map(|offset| {
+                (0..permutation_width)
+                    .map(move |index| SymbolicVariable::new(Entry::Permutation { offset }, index))
+            })
+            .collect();
+        let permutation_challenges = (0..num_permutation_challenges)
+            .map(|index| SymbolicVariable::new(Entry::Challenge, index))
+            .collect();
         let public_values = (0..num_public_values)
             .map(move |index| SymbolicVariable::new(Entry::Public, index))
             .collect();
         Self {
             preprocessed: RowMajorMatrix::new(prep_values, preprocessed_width),
             main: RowMajorMatrix::new(main_values, width),
+            permutation: RowMajorMatrix::new(perm_values, permutation_width),
+            permutation_challenges,
             public_values,
             constraints: vec![],
         }
@@ -124,12 +160,17 @@ impl<F: Field> AirBuilder for SymbolicAirBuilder<F> {
     }
 
     /// # Panics
-    /// This function panics if `size` is not `2`.
+    /// This function panics if `size` is less than `2` or greater than the window size.
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            SymbolicExpression::IsTransition
-        } else {
-            panic!("uni-stark only supports a window size of 2")
+        match size {
+            2 => SymbolicExpression::IsTransition,
+            _ if size > 2 && size <= self.main.height() => {
+                SymbolicExpression::IsTransitionWindow(size)
+            }
+            _ => panic!(
+                "a transition window must have between 2 and {} rows, not {size}",
+                self.main.height()
+            ),
         }
     }
 
@@ -151,6 +192,32 @@ impl<F: Field> PairBuilder for SymbolicAirBuilder<F> {
     }
 }
 
+impl<F: Field> ExtensionBuilder for SymbolicAirBuilder<F> {
+    type EF = F;
+    type ExprEF = SymbolicExpression<F>;
+    type VarEF = SymbolicVariable<F>;
+
+    fn assert_zero_ext<I>(&mut self, x: I)
+    where
+        I: Into<Self::ExprEF>,
+    {
+        self.constraints.push(x.into());
+    }
+}
+
+impl<F: Field> PermutationAirBuilder for SymbolicAirBuilder<F> {
+    type MP = RowMajorMatrix<Self::VarEF>;
+    type RandomVar = SymbolicVariable<F>;
+
+    fn permutation(&self) -> Self::MP {
+        self.permutation.clone()
+    }
+
+    fn permutation_randomness(&self) -> &[Self::RandomVar] {
+        &self.permutation_challenges
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use alloc::vec;
@@ -269,7 +336,7 @@ mod tests {
 
     #[test]
     fn test_symbolic_air_builder_initialization() {
-        let builder = SymbolicAirBuilder::<BabyBear>::new(2, 4, 3);
+        let builder = SymbolicAirBuilder::<BabyBear>::new(2, 4, 0, 0, 3, 2);
 
         let expected_main = [
             SymbolicVariable::<BabyBear>::new(Entry::Main { offset: 0 }, 0),
@@ -296,9 +363,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_symbolic_air_builder_window() {
+        let builder = SymbolicAirBuilder::<BabyBear>::new(0, 4, 0, 0, 0, 3);
+
+        let main = builder.main();
+        assert_eq!(main.height(), 3);
+        let last = main.row_slice(2).unwrap();
+        assert_eq!(last[1].entry, Entry::Main { offset: 2 });
+        assert_eq!(last[1].index, 1);
+
+        assert!(matches!(
+            builder.is_transition(),
+            SymbolicExpression::IsTransition
+        ));
+        assert!(matches!(
+            builder.is_transition_window(3),
+            SymbolicExpression::IsTransitionWindow(3)
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "a transition window must have between 2 and 3 rows, not 4")]
+    fn test_symbolic_air_builder_window_too_large() {
+        let builder = SymbolicAirBuilder::<BabyBear>::new(0, 4, 0, 0, 0, 3);
+        builder.is_transition_window(4);
+    }
+
     #[test]
     fn test_symbolic_air_builder_is_first_last_row() {
-        let builder = SymbolicAirBuilder::<BabyBear>::new(2, 4, 3);
+        let builder = SymbolicAirBuilder::<BabyBear>::new(2, 4, 0, 0, 3, 2);
 
         assert!(
             matches!(builder.is_first_row(), SymbolicExpression::IsFirstRow),
@@ -313,7 +407,7 @@ mod tests {
 
     #[test]
     fn test_symbolic_air_builder_assert_zero() {
-        let mut builder = SymbolicAirBuilder::<BabyBear>::new(2, 4, 3);
+        let mut builder = SymbolicAirBuilder::<BabyBear>::new(2, 4, 0, 0, 3, 2);
         let expr = SymbolicExpression::Constant(BabyBear::new(5));
         builder.assert_zero(expr.clone());
 
@@ -14,6 +14,9 @@ pub enum SymbolicExpression<F> {
     IsFirstRow,
     IsLastRow,
     IsTransition,
+    /// The selector of the rows whose transition window of the given size, greater than 2, does
+    /// not wrap around the trace. A window of 2 rows is [`Self::IsTransition`].
+    IsTransitionWindow(usize),
     Constant(F),
     Add {
         x: Rc<Self>,
@@ -42,7 +45,7 @@ impl<F> SymbolicExpression<F> {
         match self {
             Self::Variable(v) => v.degree_multiple(),
             Self::IsFirstRow | Self::IsLastRow => 1,
-            Self::IsTransition | Self::Constant(_) => 0,
+            Self::IsTransition | Self::IsTransitionWindow(_) | Self::Constant(_) => 0,
             Self::Add {
                 degree_multiple, ..
             }
@@ -14,7 +14,8 @@ pub enum Entry {
     Challenge,
 }
 
-/// A variable within the evaluation window, i.e. a column in either the local or next row.
+/// A variable within the evaluation window, i.e. a column in one of its rows, the local row or one
+/// of the [`BaseAir::window_size`](p3_air::BaseAir::window_size) `- 1` rows after it.
 #[derive(Copy, Clone, Debug)]
 pub struct SymbolicVariable<F> {
     pub entry: Entry,
@@ -2,20 +2,30 @@
 
 use alloc::vec;
 use alloc::vec::Vec;
+use core::fmt;
 
 use itertools::Itertools;
-use p3_air::Air;
+use p3_air::{Air, BaseAirWithPublicValues};
 use p3_challenger::{CanObserve, FieldChallenger};
 use p3_commit::{Pcs, PolynomialSpace};
 use p3_field::{BasedVectorSpace, Field, PrimeCharacteristicRing};
-use p3_matrix::dense::RowMajorMatrixView;
-use p3_matrix::stack::VerticalPair;
+use p3_matrix::dense::RowMajorMatrix;
 use p3_util::zip_eq::zip_eq;
 use tracing::instrument;
 
+use crate::prover::window_points;
 use crate::symbolic_builder::{SymbolicAirBuilder, get_log_quotient_degree};
-use crate::{PcsError, Proof, StarkGenericConfig, Val, VerifierConstraintFolder};
+use crate::{
+    Domain, OpenedValues, PcsError, PreprocessedVerifierKey, Proof, StarkGenericConfig, Val,
+    VerifierConstraintFolder, air_digest,
+};
 
+/// Verify a proof produced by [`prove`](crate::prove) for `air` and `public_values`.
+///
+/// The number of public values must equal `air.num_public_values()`, or verification fails with
+/// [`VerificationError::PublicValueCountMismatch`]. An AIR which reads public values must
+/// therefore override [`BaseAirWithPublicValues::num_public_values`], whose default of zero
+/// rejects any public values at runtime rather than at compile time.
 #[instrument(skip_all)]
 pub fn verify<SC, A>(
     config: &SC,
@@ -25,7 +35,29 @@ pub fn verify<SC, A>(
 ) -> Result<(), VerificationError<PcsError<SC>>>
 where
     SC: StarkGenericConfig,
-    A: Air<SymbolicAirBuilder<Val<SC>>> + for<'a> Air<VerifierConstraintFolder<'a, SC>>,
+    A: BaseAirWithPublicValues<Val<SC>>
+        + Air<SymbolicAirBuilder<Val<SC>>>
+        + for<'a> Air<VerifierConstraintFolder<'a, SC>>,
+{
+    verify_with_preprocessed(config, air, proof, public_values, None)
+}
+
+/// Verify a proof produced by [`prove_with_preprocessed`](crate::prove_with_preprocessed),
+/// checking the preprocessed columns against the commitment in `preprocessed`. The public values
+/// are counted as in [`verify`].
+#[instrument(skip_all)]
+pub fn verify_with_preprocessed<SC, A>(
+    config: &SC,
+    air: &A,
+    proof: &Proof<SC>,
+    public_values: &Vec<Val<SC>>,
+    preprocessed: Option<&PreprocessedVerifierKey<SC>>,
+) -> Result<(), VerificationError<PcsError<SC>>>
+where
+    SC: StarkGenericConfig,
+    A: BaseAirWithPublicValues<Val<SC>>
+        + Air<SymbolicAirBuilder<Val<SC>>>
+        + for<'a> Air<VerifierConstraintFolder<'a, SC>>,
 {
     let Proof {
         commitments,
@@ -34,11 +66,52 @@ where
         degree_bits,
     } = proof;
 
+    check_public_values(air, public_values)?;
+
+    // The preprocessed openings must be present exactly when a preprocessed key is given, and the
+    // key's height must match the trace. This is checked before the AIR is evaluated below, as
+    // the AIR reads the preprocessed columns if it has any.
+    let window_size = air.window_size();
+    let preprocessed_width = preprocessed.map_or(0, |p| p.width);
+    match (preprocessed, &opened_values.preprocessed_window) {
+        (Some(p), Some(window)) if p.degree_bits == *degree_bits => {
+            check_window(
+                window,
+                window_size,
+                preprocessed_width,
+                |expected, actual| VerificationError::PreprocessedWidthMismatch {
+                    expected,
+                    actual,
+                },
+            )?;
+        }
+        (None, None) => {}
+        _ => return Err(VerificationError::InvalidProofShape),
+    }
+
+    // Likewise the permutation commitment and openings must be present exactly when the AIR has
+    // permutation columns, each opened as its `DIMENSION` base field columns.
+    let permutation_width = air.permutation_width();
+    match (&commitments.permutation, &opened_values.permutation_window) {
+        (Some(_), Some(window)) if permutation_width > 0 => {
+            let width = permutation_width * SC::Challenge::DIMENSION;
+            check_window(window, window_size, width, |expected, actual| {
+                VerificationError::PermutationWidthMismatch { expected, actual }
+            })?;
+        }
+        (None, None) if permutation_width == 0 => {}
+        _ => return Err(VerificationError::InvalidProofShape),
+    }
+
     let pcs = config.pcs();
 
     let degree = 1 << degree_bits;
-    let log_quotient_degree =
-        get_log_quotient_degree::<Val<SC>, A>(air, 0, public_values.len(), config.is_zk());
+    let log_quotient_degree = get_log_quotient_degree::<Val<SC>, A>(
+        air,
+        preprocessed_width,
+        public_values.len(),
+        config.is_zk(),
+    );
     let quotient_degree = 1 << (log_quotient_degree + config.is_zk());
 
     let mut challenger = config.initialise_challenger();
@@ -63,36 +136,48 @@ where
         return Err(VerificationError::RandomizationError);
     }
 
-    let air_width = A::width(air);
-    let valid_shape = opened_values.trace_local.len() == air_width
-        && opened_values.trace_next.len() == air_width
-        && opened_values.quotient_chunks.len() == quotient_degree
-        && opened_values
-            .quotient_chunks
-            .iter()
-            .all(|qc| qc.len() == SC::Challenge::DIMENSION)
-        // We've already checked that opened_values.random is present if and only if ZK is enabled.
-        && if let Some(r_comm) = &opened_values.random {
-            r_comm.len() == SC::Challenge::DIMENSION
-        } else {
-            true
-        };
-    if !valid_shape {
-        return Err(VerificationError::InvalidProofShape);
+    check_window(
+        &opened_values.trace_window,
+        window_size,
+        A::width(air),
+        |expected, actual| VerificationError::TraceWidthMismatch { expected, actual },
+    )?;
+    check_quotient_chunks(
+        &opened_values.quotient_chunks,
+        quotient_degree,
+        SC::Challenge::DIMENSION,
+    )?;
+    // We've already checked that opened_values.random is present if and only if ZK is enabled.
+    if let Some(random) = &opened_values.random {
+        check_extension_degree(random, SC::Challenge::DIMENSION)?;
     }
 
     // Observe the instance.
     challenger.observe(Val::<SC>::from_usize(proof.degree_bits));
     challenger.observe(Val::<SC>::from_usize(proof.degree_bits - config.is_zk()));
-    // TODO: Might be best practice to include other instance data here in the transcript, like some
-    // encoding of the AIR. This protects against transcript collisions between distinct instances.
-    // Practically speaking though, the only related known attack is from failing to include public
-    // values. It's not clear if failing to include other instance data could enable a transcript
-    // collision, since most such changes would completely change the set of satisfying witnesses.
+    // Observe the digest of the AIR, which binds the transcript to the constraints being checked,
+    // so that a proof for one AIR is rejected by the verifier of another.
+    challenger.observe_slice(&air_digest(
+        config,
+        air,
+        preprocessed_width,
+        public_values.len(),
+    ));
+
+    if let Some(preprocessed) = preprocessed {
+        challenger.observe(preprocessed.commitment.clone());
+    }
 
     challenger.observe(commitments.trace.clone());
     challenger.observe_slice(public_values);
 
+    let permutation_challenges: Vec<SC::Challenge> = (0..air.num_permutation_challenges())
+        .map(|_| challenger.sample_algebra_element())
+        .collect();
+    if let Some(permutation_commit) = &commitments.permutation {
+        challenger.observe(permutation_commit.clone());
+    }
+
     // Get the first Fiat Shamir challenge which will be used to combine all constraint polynomials
     // into a single polynomial.
     //
@@ -110,7 +195,15 @@ where
     //
     // Soundness Error: dN/|EF| where `N` is the trace length and our constraint polynomial has degree `d`.
     let zeta = challenger.sample_algebra_element();
-    let zeta_next = init_trace_domain.next_point(zeta).unwrap();
+    let window_points = window_points(init_trace_domain, zeta, window_size);
+    // The rows of a window are paired with the points they were opened at.
+    let window_openings = |window: &[Vec<SC::Challenge>]| {
+        window_points
+            .iter()
+            .copied()
+            .zip(window.iter().cloned())
+            .collect_vec()
+    };
 
     // We've already checked that commitments.random and opened_values.random are present if and only if ZK is enabled.
     let mut coms_to_verify = if let Some(random_commit) = &commitments.random {
@@ -128,13 +221,7 @@ where
     coms_to_verify.extend(vec![
         (
             commitments.trace.clone(),
-            vec![(
-                trace_domain,
-                vec![
-                    (zeta, opened_values.trace_local.clone()),
-                    (zeta_next, opened_values.trace_next.clone()),
-                ],
-            )],
+            vec![(trace_domain, window_openings(&opened_values.trace_window))],
         ),
         (
             commitments.quotient_chunks.clone(),
@@ -148,10 +235,56 @@ where
             .collect_vec(),
         ),
     ]);
+    // The preprocessed and then the permutation commitments are opened last, matching the
+    // prover's rounds.
+    if let (Some(p), Some(window)) = (preprocessed, &opened_values.preprocessed_window) {
+        coms_to_verify.push((
+            p.commitment.clone(),
+            vec![(trace_domain, window_openings(window))],
+        ));
+    }
+    if let (Some(commit), Some(window)) =
+        (&commitments.permutation, &opened_values.permutation_window)
+    {
+        coms_to_verify.push((
+            commit.clone(),
+            vec![(trace_domain, window_openings(window))],
+        ));
+    }
 
     pcs.verify(coms_to_verify, opening_proof, &mut challenger)
         .map_err(VerificationError::InvalidOpeningArgument)?;
 
+    verify_constraints(
+        air,
+        opened_values,
+        &permutation_challenges,
+        public_values,
+        init_trace_domain,
+        &quotient_chunks_domains,
+        zeta,
+        alpha,
+    )
+}
+
+/// Check that the constraints of `air`, evaluated on the openings at `zeta` and combined with
+/// `alpha`, agree with the opened quotient chunks. The shape of `opened_values` must already have
+/// been checked against the AIR.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn verify_constraints<SC, A, PcsErr>(
+    air: &A,
+    opened_values: &OpenedValues<SC::Challenge>,
+    permutation_challenges: &[SC::Challenge],
+    public_values: &Vec<Val<SC>>,
+    trace_domain: Domain<SC>,
+    quotient_chunks_domains: &[Domain<SC>],
+    zeta: SC::Challenge,
+    alpha: SC::Challenge,
+) -> Result<(), VerificationError<PcsErr>>
+where
+    SC: StarkGenericConfig,
+    A: for<'a> Air<VerifierConstraintFolder<'a, SC>>,
+{
     let zps = quotient_chunks_domains
         .iter()
         .enumerate()
@@ -170,35 +303,74 @@ where
         })
         .collect_vec();
 
+    // Recombine `DIMENSION` openings of base field polynomials into the opening of the extension
+    // field polynomial they are the coordinates of.
+    let recombine = |coeffs: &[SC::Challenge]| {
+        coeffs
+            .iter()
+            .enumerate()
+            .map(|(e_i, &c)| SC::Challenge::ith_basis_element(e_i).unwrap() * c)
+            .sum::<SC::Challenge>()
+    };
+
     let quotient = opened_values
         .quotient_chunks
         .iter()
         .enumerate()
-        .map(|(ch_i, ch)| {
-            // We checked in valid_shape the length of "ch" is equal to
-            // <SC::Challenge as BasedVectorSpace<Val<SC>>>::DIMENSION. Hence
-            // the unwrap() will never panic.
-            zps[ch_i]
-                * ch.iter()
-                    .enumerate()
-                    .map(|(e_i, &c)| SC::Challenge::ith_basis_element(e_i).unwrap() * c)
-                    .sum::<SC::Challenge>()
-        })
+        .map(|(ch_i, ch)| zps[ch_i] * recombine(ch))
         .sum::<SC::Challenge>();
 
-    let sels = init_trace_domain.selectors_at_point(zeta);
+    let window_size = opened_values.trace_window.len();
+    let window_points = window_points(trace_domain, zeta, window_size);
+    let sels = trace_domain.selectors_at_point(zeta);
+
+    // The selector of a transition window of `k + 2` rows is the product of `is_transition` at
+    // the first `k + 1` rows of the window.
+    let is_transition_windows = window_points[..window_size - 1]
+        .iter()
+        .scan(SC::Challenge::ONE, |selector, &point| {
+            *selector *= trace_domain.selectors_at_point(point).is_transition;
+            Some(*selector)
+        })
+        .collect();
+
+    // Stack the opened rows of a window into a matrix of the given width.
+    let stack = |window: Option<&Vec<Vec<SC::Challenge>>>, width: usize| {
+        RowMajorMatrix::new(window.map_or_else(Vec::new, |rows| rows.concat()), width)
+    };
+    let main = stack(Some(&opened_values.trace_window), air.width());
+    let preprocessed_width = opened_values
+        .preprocessed_window
+        .as_ref()
+        .map_or(0, |window| window[0].len());
+    let preprocessed = stack(
+        opened_values.preprocessed_window.as_ref(),
+        preprocessed_width,
+    );
 
-    let main = VerticalPair::new(
-        RowMajorMatrixView::new_row(&opened_values.trace_local),
-        RowMajorMatrixView::new_row(&opened_values.trace_next),
+    let permutation = RowMajorMatrix::new(
+        opened_values
+            .permutation_window
+            .as_ref()
+            .map_or_else(Vec::new, |window| {
+                window
+                    .concat()
+                    .chunks_exact(SC::Challenge::DIMENSION)
+                    .map(recombine)
+                    .collect()
+            }),
+        air.permutation_width(),
     );
 
     let mut folder = VerifierConstraintFolder {
-        main,
+        main: main.as_view(),
+        preprocessed: preprocessed.as_view(),
+        permutation: permutation.as_view(),
+        permutation_challenges,
         public_values,
         is_first_row: sels.is_first_row,
         is_last_row: sels.is_last_row,
-        is_transition: sels.is_transition,
+        is_transition_windows,
         alpha,
         accumulator: SC::Challenge::ZERO,
     };
@@ -214,9 +386,97 @@ where
     Ok(())
 }
 
+/// Check that `window` has `window_size` rows of `width` columns, reporting a row of another
+/// width with `width_mismatch`.
+pub(crate) fn check_window<T, PcsErr>(
+    window: &[Vec<T>],
+    window_size: usize,
+    width: usize,
+    width_mismatch: impl Fn(usize, usize) -> VerificationError<PcsErr>,
+) -> Result<(), VerificationError<PcsErr>> {
+    if window.len() != window_size {
+        return Err(VerificationError::WindowSizeMismatch {
+            expected: window_size,
+            actual: window.len(),
+        });
+    }
+    match window.iter().find(|row| row.len() != width) {
+        Some(row) => Err(width_mismatch(width, row.len())),
+        None => Ok(()),
+    }
+}
+
+/// Check that there are as many public values as `air` expects. The AIR reads its public values
+/// by index, so this is checked before it is evaluated.
+pub(crate) fn check_public_values<F, A: BaseAirWithPublicValues<F>, PcsErr>(
+    air: &A,
+    public_values: &[F],
+) -> Result<(), VerificationError<PcsErr>> {
+    let expected = air.num_public_values();
+    if public_values.len() != expected {
+        return Err(VerificationError::PublicValueCountMismatch {
+            expected,
+            actual: public_values.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Check that there are `quotient_degree` opened quotient chunks, each an extension field element
+/// flattened to its `dimension` base field coordinates.
+pub(crate) fn check_quotient_chunks<T, PcsErr>(
+    quotient_chunks: &[Vec<T>],
+    quotient_degree: usize,
+    dimension: usize,
+) -> Result<(), VerificationError<PcsErr>> {
+    if quotient_chunks.len() != quotient_degree {
+        return Err(VerificationError::QuotientChunkCountMismatch {
+            expected: quotient_degree,
+            actual: quotient_chunks.len(),
+        });
+    }
+    quotient_chunks
+        .iter()
+        .try_for_each(|chunk| check_extension_degree(chunk, dimension))
+}
+
+/// Check that an opened extension field element has its `dimension` base field coordinates.
+fn check_extension_degree<T, PcsErr>(
+    coordinates: &[T],
+    dimension: usize,
+) -> Result<(), VerificationError<PcsErr>> {
+    if coordinates.len() != dimension {
+        return Err(VerificationError::ExtensionDegreeMismatch {
+            expected: dimension,
+            actual: coordinates.len(),
+        });
+    }
+    Ok(())
+}
+
+/// The reason a proof was rejected. The mismatch variants give the value the verifier expected,
+/// from the AIR and the configuration, and the value found in the proof or the public values.
 #[derive(Debug)]
 pub enum VerificationError<PcsErr> {
+    /// A commitment or opening is present which should be absent or vice versa, or, for a batch
+    /// proof, the number of instances differs between its parts.
     InvalidProofShape,
+    /// The opened rows of the trace do not have the AIR's width.
+    TraceWidthMismatch { expected: usize, actual: usize },
+    /// The opened rows of the preprocessed trace do not have the width of its verifier key.
+    PreprocessedWidthMismatch { expected: usize, actual: usize },
+    /// The opened rows of the permutation trace do not have the AIR's permutation width,
+    /// flattened to the base field.
+    PermutationWidthMismatch { expected: usize, actual: usize },
+    /// The number of rows opened is not the AIR's window size.
+    WindowSizeMismatch { expected: usize, actual: usize },
+    /// The number of opened quotient chunks is not the quotient degree.
+    QuotientChunkCountMismatch { expected: usize, actual: usize },
+    /// An opened extension field element does not have the extension degree's number of base
+    /// field coordinates.
+    ExtensionDegreeMismatch { expected: usize, actual: usize },
+    /// The number of public values is not the number the AIR expects.
+    PublicValueCountMismatch { expected: usize, actual: usize },
     /// An error occurred while verifying the claimed openings.
     InvalidOpeningArgument(PcsErr),
     /// Out-of-domain evaluation mismatch, i.e. `constraints(zeta)` did not match
@@ -224,4 +484,82 @@ pub enum VerificationError<PcsErr> {
     OodEvaluationMismatch,
     /// The FRI batch randomization does not correspond to the ZK setting.
     RandomizationError,
+    /// The tuples sent over the buses of a batch proof do not balance the tuples received.
+    UnbalancedInteractions,
+}
+
+impl<PcsErr: fmt::Debug> fmt::Display for VerificationError<PcsErr> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidProofShape => write!(f, "invalid proof shape"),
+            Self::TraceWidthMismatch { expected, actual } => write!(
+                f,
+                "trace width mismatch: expected {expected} columns, found {actual}"
+            ),
+            Self::PreprocessedWidthMismatch { expected, actual } => write!(
+                f,
+                "preprocessed width mismatch: expected {expected} columns, found {actual}"
+            ),
+            Self::PermutationWidthMismatch { expected, actual } => write!(
+                f,
+                "permutation width mismatch: expected {expected} base field columns, found {actual}"
+            ),
+            Self::WindowSizeMismatch { expected, actual } => write!(
+                f,
+                "window size mismatch: expected {expected} rows, found {actual}"
+            ),
+            Self::QuotientChunkCountMismatch { expected, actual } => write!(
+                f,
+                "quotient chunk count mismatch: expected {expected} chunks, found {actual}"
+            ),
+            Self::ExtensionDegreeMismatch { expected, actual } => write!(
+                f,
+                "extension degree mismatch: expected {expected} coordinates, found {actual}"
+            ),
+            Self::PublicValueCountMismatch { expected, actual } => write!(
+                f,
+                "public value count mismatch: expected {expected} values, found {actual}"
+            ),
+            Self::InvalidOpeningArgument(err) => write!(f, "invalid opening argument: {err:?}"),
+            Self::OodEvaluationMismatch => write!(
+                f,
+                "the constraints at the out-of-domain point do not match the quotient"
+            ),
+            Self::RandomizationError => write!(
+                f,
+                "the random commitment does not match the zero-knowledge setting"
+            ),
+            Self::UnbalancedInteractions => {
+                write!(
+                    f,
+                    "the tuples sent over the buses do not balance those received"
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn test_extension_degree_mismatch() {
+        let chunks = [vec![0u32; 4], vec![0u32; 3]];
+        let err = check_quotient_chunks::<_, ()>(&chunks, 2, 4).unwrap_err();
+        assert!(matches!(
+            err,
+            VerificationError::ExtensionDegreeMismatch {
+                expected: 4,
+                actual: 3
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "extension degree mismatch: expected 4 coordinates, found 3"
+        );
+    }
 }
@@ -0,0 +1,296 @@
+use p3_air::{
+    Air, AirBuilder, BaseAir, BaseAirWithPublicValues, Interaction, InteractionAir,
+    PermutationAirBuilder, VirtualPairCol,
+};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{ExtensionField, Field, PrimeCharacteristicRing, PrimeField64};
+use p3_fri::{TwoAdicFriPcs, create_test_fri_params};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_uni_stark::{StarkConfig, StarkInstance, VerificationError, prove_batch, verify_batch};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+/// The bus over which values are sent to be range checked.
+const RANGE_BUS: usize = 0;
+
+/// The tables of a batch, all with two main columns.
+enum BatchAir {
+    /// Fibonacci pairs `(a, b)`, sending every `a` to be range checked.
+    Fibonacci,
+    /// The byte values and how often each is received.
+    Range,
+    /// Two columns which are permutations of each other, proven with a grand product as in
+    /// `permutation_air.rs`, sending every value of the first column to be range checked.
+    Permutation,
+}
+
+impl<F: Field> BaseAir<F> for BatchAir {
+    fn width(&self) -> usize {
+        2
+    }
+
+    fn num_permutation_challenges(&self) -> usize {
+        match self {
+            Self::Permutation => 1,
+            _ => 0,
+        }
+    }
+
+    fn permutation_width(&self) -> usize {
+        match self {
+            Self::Permutation => 1,
+            _ => 0,
+        }
+    }
+
+    fn permutation_trace<EF: ExtensionField<F>>(
+        &self,
+        main: &RowMajorMatrix<F>,
+        challenges: &[EF],
+    ) -> Option<RowMajorMatrix<EF>> {
+        let Self::Permutation = self else {
+            return None;
+        };
+        let beta = challenges[0];
+        let z = main
+            .rows()
+            .scan(EF::ONE, |z, mut row| {
+                let (a, b) = (row.next().unwrap(), row.next().unwrap());
+                let current = *z;
+                *z *= (beta - a) * (beta - b).inverse();
+                Some(current)
+            })
+            .collect();
+        Some(RowMajorMatrix::new_col(z))
+    }
+}
+
+impl<F: Field> BaseAirWithPublicValues<F> for BatchAir {}
+
+impl<F: Field> InteractionAir<F> for BatchAir {
+    fn sends(&self) -> Vec<Interaction<F>> {
+        match self {
+            Self::Fibonacci | Self::Permutation => vec![Interaction {
+                fields: vec![VirtualPairCol::single_main(0)],
+                count: VirtualPairCol::ONE,
+                bus: RANGE_BUS,
+            }],
+            Self::Range => vec![],
+        }
+    }
+
+    fn receives(&self) -> Vec<Interaction<F>> {
+        match self {
+            Self::Range => vec![Interaction {
+                fields: vec![VirtualPairCol::single_main(0)],
+                count: VirtualPairCol::single_main(1),
+                bus: RANGE_BUS,
+            }],
+            _ => vec![],
+        }
+    }
+}
+
+impl<AB: PermutationAirBuilder> Air<AB> for BatchAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, next) = (
+            main.row_slice(0).expect("Matrix is empty?"),
+            main.row_slice(1).expect("Matrix only has 1 row?"),
+        );
+        match self {
+            Self::Fibonacci => {
+                let mut when_transition = builder.when_transition();
+                when_transition.assert_eq(next[0].clone(), local[1].clone());
+                when_transition.assert_eq(next[1].clone(), local[0].clone() + local[1].clone());
+            }
+            Self::Range => {
+                builder.when_first_row().assert_zero(local[0].clone());
+                builder
+                    .when_transition()
+                    .assert_eq(next[0].clone(), local[0].clone() + AB::Expr::ONE);
+            }
+            Self::Permutation => {
+                let [a, b] = [0, 1].map(|i| AB::ExprEF::from(local[i].clone().into()));
+                let permutation = builder.permutation();
+                let (z, z_next): (AB::ExprEF, AB::ExprEF) = (
+                    permutation.row_slice(0).expect("Matrix is empty?")[0].into(),
+                    permutation.row_slice(1).expect("Matrix only has 1 row?")[0].into(),
+                );
+                let beta: AB::ExprEF = builder.permutation_randomness()[0].into();
+
+                let is_first_row: AB::ExprEF = builder.is_first_row().into();
+                let is_transition: AB::ExprEF = builder.is_transition().into();
+                let is_last_row: AB::ExprEF = builder.is_last_row().into();
+                builder.assert_zero_ext(is_first_row * (z.clone() - AB::ExprEF::ONE));
+                builder.assert_zero_ext(
+                    is_transition
+                        * (z_next * (beta.clone() - b.clone())
+                            - z.clone() * (beta.clone() - a.clone())),
+                );
+                builder.assert_zero_ext(is_last_row * (z * (beta.clone() - a) - (beta - b)));
+            }
+        }
+    }
+}
+
+fn fibonacci_trace(a: u32, b: u32, n: usize) -> RowMajorMatrix<Val> {
+    let values = (0..n)
+        .scan((a, b), |(a, b), _| {
+            let row = [Val::from_u32(*a), Val::from_u32(*b)];
+            (*a, *b) = (*b, *a + *b);
+            Some(row)
+        })
+        .flatten()
+        .collect();
+    RowMajorMatrix::new(values, 2)
+}
+
+fn permutation_trace(a: &[u32], b: &[u32]) -> RowMajorMatrix<Val> {
+    let values = a
+        .iter()
+        .zip(b)
+        .flat_map(|(&a, &b)| [Val::from_u32(a), Val::from_u32(b)])
+        .collect();
+    RowMajorMatrix::new(values, 2)
+}
+
+/// The range table receiving every byte among the first columns of `senders`.
+fn range_trace(senders: &[&RowMajorMatrix<Val>]) -> RowMajorMatrix<Val> {
+    let mut counts = [0; 256];
+    for trace in senders {
+        for row in trace.row_slices() {
+            if let Some(count) = counts.get_mut(row[0].as_canonical_u64() as usize) {
+                *count += 1;
+            }
+        }
+    }
+    let values = counts
+        .iter()
+        .enumerate()
+        .flat_map(|(value, &count)| [Val::from_usize(value), Val::from_u32(count)])
+        .collect();
+    RowMajorMatrix::new(values, 2)
+}
+
+type Val = BabyBear;
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type Challenge = BinomialExtensionField<Val, 4>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+type Dft = Radix2DitParallel<Val>;
+type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+fn config() -> MyConfig {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_params = create_test_fri_params(challenge_mmcs, 2);
+    let pcs = Pcs::new(Dft::default(), val_mmcs, fri_params);
+    MyConfig::new(pcs, Challenger::new(perm))
+}
+
+const VALUES: [u32; 8] = [3, 1, 4, 1, 5, 9, 2, 6];
+const PERMUTED: [u32; 8] = [9, 1, 6, 3, 1, 2, 5, 4];
+
+const AIRS: [&BatchAir; 3] = [
+    &BatchAir::Fibonacci,
+    &BatchAir::Permutation,
+    &BatchAir::Range,
+];
+
+/// Prove the Fibonacci table from `(a, b)` with 8 rows, the permutation table with 8 rows and the
+/// range table with 256 rows.
+fn prove_tables(config: &MyConfig, a: u32, b: u32) -> p3_uni_stark::BatchProof<MyConfig> {
+    let fibonacci = fibonacci_trace(a, b, 8);
+    let permutation = permutation_trace(&VALUES, &PERMUTED);
+    let range = range_trace(&[&fibonacci, &permutation]);
+    let instances = AIRS
+        .into_iter()
+        .zip([fibonacci, permutation, range])
+        .map(|(air, trace)| StarkInstance {
+            air,
+            trace,
+            public_values: vec![],
+        })
+        .collect();
+    prove_batch(config, instances)
+}
+
+#[test]
+fn test_batch() {
+    let config = config();
+    let proof = prove_tables(&config, 0, 1);
+    verify_batch(&config, &AIRS, &proof, &[vec![], vec![], vec![]]).expect("verification failed");
+}
+
+#[test]
+fn test_batch_value_out_of_range() {
+    let config = config();
+    // The Fibonacci table sends 0, 200, 200, 400, ..., and 400 is not a byte, so the range table
+    // cannot receive it. Each table still satisfies its own constraints.
+    let proof = prove_tables(&config, 0, 200);
+    assert!(matches!(
+        verify_batch(&config, &AIRS, &proof, &[vec![], vec![], vec![]]),
+        Err(VerificationError::UnbalancedInteractions)
+    ));
+}
+
+#[test]
+fn test_batch_wrong_airs() {
+    let config = config();
+    let proof = prove_tables(&config, 0, 1);
+    let airs = [
+        &BatchAir::Permutation,
+        &BatchAir::Fibonacci,
+        &BatchAir::Range,
+    ];
+    assert!(verify_batch(&config, &airs, &proof, &[vec![], vec![], vec![]]).is_err());
+    assert!(verify_batch(&config, &AIRS[..2], &proof, &[vec![], vec![]]).is_err());
+}
+
+#[test]
+fn test_two_table_bus() {
+    let config = config();
+    let airs = [&BatchAir::Fibonacci, &BatchAir::Range];
+    let prove = |range: RowMajorMatrix<Val>| {
+        let instances = airs
+            .into_iter()
+            .zip([fibonacci_trace(0, 1, 8), range])
+            .map(|(air, trace)| StarkInstance {
+                air,
+                trace,
+                public_values: vec![],
+            })
+            .collect();
+        prove_batch(&config, instances)
+    };
+    let range = range_trace(&[&fibonacci_trace(0, 1, 8)]);
+    let proof = prove(range.clone());
+    verify_batch(&config, &airs, &proof, &[vec![], vec![]]).expect("verification failed");
+
+    // Claim the range table receives 0 once more than the Fibonacci table sends it. Both tables
+    // still satisfy their own constraints, so only the bus can catch it.
+    let mut range = range;
+    range.values[1] += Val::ONE;
+    let proof = prove(range);
+    assert!(matches!(
+        verify_batch(&config, &airs, &proof, &[vec![], vec![]]),
+        Err(VerificationError::UnbalancedInteractions)
+    ));
+}
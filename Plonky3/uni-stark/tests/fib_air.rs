@@ -1,12 +1,12 @@
 use core::borrow::Borrow;
 
-use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, BaseAirWithPublicValues};
 use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
 use p3_challenger::{DuplexChallenger, HashChallenger, SerializingChallenger32};
 use p3_commit::ExtensionMmcs;
 use p3_dft::Radix2DitParallel;
 use p3_field::extension::BinomialExtensionField;
-use p3_field::{Field, PrimeCharacteristicRing, PrimeField64};
+use p3_field::{BasedVectorSpace, Field, PrimeCharacteristicRing, PrimeField64};
 use p3_fri::{HidingFriPcs, TwoAdicFriPcs, create_test_fri_params};
 use p3_keccak::{Keccak256Hash, KeccakF};
 use p3_matrix::Matrix;
@@ -15,7 +15,7 @@ use p3_merkle_tree::{MerkleTreeHidingMmcs, MerkleTreeMmcs};
 use p3_symmetric::{
     CompressionFunctionFromHasher, PaddingFreeSponge, SerializingHasher, TruncatedPermutation,
 };
-use p3_uni_stark::{StarkConfig, prove, verify};
+use p3_uni_stark::{StarkConfig, VerificationError, air_digest, encode_air, prove, verify};
 use rand::SeedableRng;
 use rand::rngs::SmallRng;
 
@@ -28,6 +28,12 @@ impl<F> BaseAir<F> for FibonacciAir {
     }
 }
 
+impl<F> BaseAirWithPublicValues<F> for FibonacciAir {
+    fn num_public_values(&self) -> usize {
+        3
+    }
+}
+
 impl<AB: AirBuilderWithPublicValues> Air<AB> for FibonacciAir {
     fn eval(&self, builder: &mut AB) {
         let main = builder.main();
@@ -62,6 +68,140 @@ impl<AB: AirBuilderWithPublicValues> Air<AB> for FibonacciAir {
     }
 }
 
+/// The constraints of [`FibonacciAir`] preceded by a trivial one. It accepts the same traces, and
+/// its constraints, combined with powers of `alpha`, give the same polynomial.
+pub struct PaddedFibonacciAir;
+
+impl<F> BaseAir<F> for PaddedFibonacciAir {
+    fn width(&self) -> usize {
+        NUM_FIBONACCI_COLS
+    }
+}
+
+impl<F> BaseAirWithPublicValues<F> for PaddedFibonacciAir {
+    fn num_public_values(&self) -> usize {
+        3
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for PaddedFibonacciAir {
+    fn eval(&self, builder: &mut AB) {
+        builder.assert_zero(AB::Expr::ZERO);
+        FibonacciAir {}.eval(builder);
+    }
+}
+
+/// An AIR with one more column than [`FibonacciAir`], whose proofs it must reject by their shape.
+pub struct WideAir;
+
+impl<F> BaseAir<F> for WideAir {
+    fn width(&self) -> usize {
+        NUM_FIBONACCI_COLS + 1
+    }
+}
+
+impl<F> BaseAirWithPublicValues<F> for WideAir {
+    fn num_public_values(&self) -> usize {
+        3
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for WideAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let a = builder.public_values()[0];
+        let local = main.row_slice(0).expect("Matrix is empty?");
+        builder.when_first_row().assert_eq(local[0].clone(), a);
+    }
+}
+
+/// [`FibonacciAir`] over a window of three rows, whose proofs it must reject by their shape.
+pub struct TallFibonacciAir;
+
+impl<F> BaseAir<F> for TallFibonacciAir {
+    fn width(&self) -> usize {
+        NUM_FIBONACCI_COLS
+    }
+
+    fn window_size(&self) -> usize {
+        3
+    }
+}
+
+impl<F> BaseAirWithPublicValues<F> for TallFibonacciAir {
+    fn num_public_values(&self) -> usize {
+        3
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for TallFibonacciAir {
+    fn eval(&self, builder: &mut AB) {
+        FibonacciAir {}.eval(builder);
+    }
+}
+
+/// [`FibonacciAir`] with a trivial cubic transition constraint, which needs more quotient chunks.
+pub struct CubicFibonacciAir;
+
+impl<F> BaseAir<F> for CubicFibonacciAir {
+    fn width(&self) -> usize {
+        NUM_FIBONACCI_COLS
+    }
+}
+
+impl<F> BaseAirWithPublicValues<F> for CubicFibonacciAir {
+    fn num_public_values(&self) -> usize {
+        3
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for CubicFibonacciAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let a: AB::Expr = main.row_slice(0).expect("Matrix is empty?")[0]
+            .clone()
+            .into();
+        builder
+            .when_transition()
+            .assert_zero(a.cube() - a.clone() * a.clone() * a);
+        FibonacciAir {}.eval(builder);
+    }
+}
+
+/// An AIR asserting that the square of its column is one, and that twice the square is two,
+/// building the square once and sharing it or building it for each constraint.
+pub struct SquareAir {
+    shared: bool,
+}
+
+impl<F> BaseAir<F> for SquareAir {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for SquareAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let x: AB::Expr = main.row_slice(0).expect("Matrix is empty?")[0]
+            .clone()
+            .into();
+        let shared = x.clone() * x.clone();
+        let square = || {
+            if self.shared {
+                shared.clone()
+            } else {
+                let x: AB::Expr = main.row_slice(0).expect("Matrix is empty?")[0]
+                    .clone()
+                    .into();
+                x.clone() * x
+            }
+        };
+        builder.assert_one(square());
+        builder.assert_eq(square().double(), AB::Expr::TWO);
+    }
+}
+
 pub fn generate_trace_rows<F: PrimeField64>(a: u64, b: u64, n: usize) -> RowMajorMatrix<F> {
     assert!(n.is_power_of_two());
 
@@ -185,6 +325,7 @@ fn test_zk() {
     let config = MyHidingConfig::new(pcs, challenger);
     let pis = vec![BabyBear::ZERO, BabyBear::ONE, BabyBear::from_u64(x)];
     let proof = prove(&config, &FibonacciAir {}, trace, &pis);
+    assert_eq!(proof.degree_bits(), 3);
     verify(&config, &FibonacciAir {}, &proof, &pis).expect("verification failed");
 }
 
@@ -201,7 +342,7 @@ fn test_public_value() {
 
 #[cfg(debug_assertions)]
 #[test]
-#[should_panic(expected = "assertion `left == right` failed: constraints had nonzero value")]
+#[should_panic(expected = "constraints had nonzero value")]
 fn test_incorrect_public_value() {
     let mut rng = SmallRng::seed_from_u64(1);
     let perm = Perm::new_from_rng_128(&mut rng);
@@ -222,3 +363,173 @@ fn test_incorrect_public_value() {
     ];
     prove(&config, &FibonacciAir {}, trace, &pis);
 }
+
+#[test]
+fn test_air_digest() {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let dft = Dft::default();
+    let fri_params = create_test_fri_params(challenge_mmcs, 2);
+    let trace = generate_trace_rows::<Val>(0, 1, 1 << 3);
+    let pcs = Pcs::new(dft, val_mmcs, fri_params);
+    let challenger = Challenger::new(perm);
+    let config = MyConfig::new(pcs, challenger);
+    let pis = vec![BabyBear::ZERO, BabyBear::ONE, BabyBear::from_u64(21)];
+
+    let digest = air_digest(&config, &FibonacciAir {}, 0, pis.len());
+    assert_eq!(digest, air_digest(&config, &FibonacciAir {}, 0, pis.len()));
+    assert_ne!(
+        digest,
+        air_digest(&config, &PaddedFibonacciAir, 0, pis.len())
+    );
+    assert_ne!(
+        digest,
+        air_digest(&config, &FibonacciAir {}, 0, pis.len() + 1)
+    );
+
+    // The encoding only depends on the constraints, not on how the AIR shares expressions.
+    assert_eq!(
+        encode_air::<Val, _>(&SquareAir { shared: true }, 0, 0),
+        encode_air::<Val, _>(&SquareAir { shared: false }, 0, 0)
+    );
+
+    // Without the digest in the transcript, the padded AIR would accept the proof.
+    let proof = prove(&config, &FibonacciAir {}, trace, &pis);
+    verify(&config, &FibonacciAir {}, &proof, &pis).expect("verification failed");
+    assert!(verify(&config, &PaddedFibonacciAir, &proof, &pis).is_err());
+}
+
+#[test]
+fn test_proof_accessors() {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let dft = Dft::default();
+    let fri_params = create_test_fri_params(challenge_mmcs, 2);
+    let num_queries = fri_params.num_queries;
+    let trace = generate_trace_rows::<Val>(0, 1, 1 << 3);
+    let pcs = Pcs::new(dft, val_mmcs, fri_params);
+    let challenger = Challenger::new(perm);
+    let config = MyConfig::new(pcs, challenger);
+    let pis = vec![BabyBear::ZERO, BabyBear::ONE, BabyBear::from_u64(21)];
+
+    let proof = prove(&config, &FibonacciAir {}, trace, &pis);
+    assert_eq!(proof.degree_bits(), 3);
+    assert!(proof.commitments().permutation().is_none());
+    assert!(proof.commitments().random().is_none());
+
+    let opened_values = proof.opened_values();
+    assert_eq!(opened_values.trace_window().len(), 2);
+    assert!(
+        opened_values
+            .trace_window()
+            .iter()
+            .all(|row| row.len() == NUM_FIBONACCI_COLS)
+    );
+    assert!(opened_values.preprocessed_window().is_none());
+    assert!(opened_values.permutation_window().is_none());
+    assert!(opened_values.random().is_none());
+    assert!(
+        opened_values
+            .quotient_chunks()
+            .iter()
+            .all(|chunk| chunk.len() == <Challenge as BasedVectorSpace<Val>>::DIMENSION)
+    );
+    assert_eq!(proof.opening_proof().query_proofs.len(), num_queries);
+}
+
+#[test]
+fn test_trace_width_mismatch() {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let dft = Dft::default();
+    let fri_params = create_test_fri_params(challenge_mmcs, 2);
+    let trace = generate_trace_rows::<Val>(0, 1, 1 << 3);
+    let pcs = Pcs::new(dft, val_mmcs, fri_params);
+    let challenger = Challenger::new(perm);
+    let config = MyConfig::new(pcs, challenger);
+    let pis = vec![BabyBear::ZERO, BabyBear::ONE, BabyBear::from_u64(21)];
+
+    let proof = prove(&config, &FibonacciAir {}, trace, &pis);
+    let err = verify(&config, &WideAir, &proof, &pis).unwrap_err();
+    assert!(matches!(
+        err,
+        VerificationError::TraceWidthMismatch {
+            expected: 3,
+            actual: 2
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "trace width mismatch: expected 3 columns, found 2"
+    );
+}
+
+#[test]
+fn test_proof_shape_mismatches() {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let dft = Dft::default();
+    let fri_params = create_test_fri_params(challenge_mmcs, 2);
+    let trace = generate_trace_rows::<Val>(0, 1, 1 << 3);
+    let pcs = Pcs::new(dft, val_mmcs, fri_params);
+    let challenger = Challenger::new(perm);
+    let config = MyConfig::new(pcs, challenger);
+    let pis = vec![BabyBear::ZERO, BabyBear::ONE, BabyBear::from_u64(21)];
+    let proof = prove(&config, &FibonacciAir {}, trace, &pis);
+
+    // Too few public values are rejected before the AIR reads them.
+    let err = verify(&config, &FibonacciAir {}, &proof, &pis[..2].to_vec()).unwrap_err();
+    assert!(matches!(
+        err,
+        VerificationError::PublicValueCountMismatch {
+            expected: 3,
+            actual: 2
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "public value count mismatch: expected 3 values, found 2"
+    );
+
+    let err = verify(&config, &TallFibonacciAir, &proof, &pis).unwrap_err();
+    assert!(matches!(
+        err,
+        VerificationError::WindowSizeMismatch {
+            expected: 3,
+            actual: 2
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "window size mismatch: expected 3 rows, found 2"
+    );
+
+    let err = verify(&config, &CubicFibonacciAir, &proof, &pis).unwrap_err();
+    assert!(matches!(
+        err,
+        VerificationError::QuotientChunkCountMismatch {
+            expected: 2,
+            actual: 1
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "quotient chunk count mismatch: expected 2 chunks, found 1"
+    );
+}
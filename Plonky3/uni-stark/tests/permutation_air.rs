@@ -0,0 +1,223 @@
+use p3_air::{Air, AirBuilder, BaseAir, BaseAirWithPublicValues, PermutationAirBuilder};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{ExtensionField, Field, PrimeCharacteristicRing};
+use p3_fri::{TwoAdicFriPcs, create_test_fri_params};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_uni_stark::{StarkConfig, VerificationError, prove, verify};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+/// Proves that the second main column is a permutation of the first with a grand product:
+/// `z` starts at 1 and accumulates `(beta - a) / (beta - b)` row by row, ending at 1.
+struct MultisetEqualityAir;
+
+impl<F: Field> BaseAir<F> for MultisetEqualityAir {
+    fn width(&self) -> usize {
+        2
+    }
+
+    fn num_permutation_challenges(&self) -> usize {
+        1
+    }
+
+    fn permutation_width(&self) -> usize {
+        1
+    }
+
+    fn permutation_trace<EF: ExtensionField<F>>(
+        &self,
+        main: &RowMajorMatrix<F>,
+        challenges: &[EF],
+    ) -> Option<RowMajorMatrix<EF>> {
+        let beta = challenges[0];
+        let z = main
+            .rows()
+            .scan(EF::ONE, |z, mut row| {
+                let (a, b) = (row.next().unwrap(), row.next().unwrap());
+                let current = *z;
+                *z *= (beta - a) * (beta - b).inverse();
+                Some(current)
+            })
+            .collect();
+        Some(RowMajorMatrix::new_col(z))
+    }
+}
+
+impl<F: Field> BaseAirWithPublicValues<F> for MultisetEqualityAir {}
+
+impl<AB: PermutationAirBuilder> Air<AB> for MultisetEqualityAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0).expect("Matrix is empty?");
+        let [a, b] = [0, 1].map(|i| AB::ExprEF::from(local[i].clone().into()));
+        let permutation = builder.permutation();
+        let (z, z_next): (AB::ExprEF, AB::ExprEF) = (
+            permutation.row_slice(0).expect("Matrix is empty?")[0].into(),
+            permutation.row_slice(1).expect("Matrix only has 1 row?")[0].into(),
+        );
+        let beta: AB::ExprEF = builder.permutation_randomness()[0].into();
+
+        let is_first_row: AB::ExprEF = builder.is_first_row().into();
+        let is_transition: AB::ExprEF = builder.is_transition().into();
+        let is_last_row: AB::ExprEF = builder.is_last_row().into();
+        builder.assert_zero_ext(is_first_row * (z.clone() - AB::ExprEF::ONE));
+        builder.assert_zero_ext(
+            is_transition
+                * (z_next * (beta.clone() - b.clone()) - z.clone() * (beta.clone() - a.clone())),
+        );
+        builder.assert_zero_ext(is_last_row * (z * (beta.clone() - a) - (beta - b)));
+    }
+}
+
+/// An AIR with the same main columns and no constraints or permutation columns.
+struct UnconstrainedAir;
+
+impl<F: Field> BaseAir<F> for UnconstrainedAir {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<F: Field> BaseAirWithPublicValues<F> for UnconstrainedAir {}
+
+impl<AB: AirBuilder> Air<AB> for UnconstrainedAir {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+/// [`MultisetEqualityAir`]'s main columns with two permutation columns and no constraints.
+struct WidePermutationAir;
+
+impl<F: Field> BaseAir<F> for WidePermutationAir {
+    fn width(&self) -> usize {
+        2
+    }
+
+    fn num_permutation_challenges(&self) -> usize {
+        1
+    }
+
+    fn permutation_width(&self) -> usize {
+        2
+    }
+}
+
+impl<F: Field> BaseAirWithPublicValues<F> for WidePermutationAir {}
+
+impl<AB: AirBuilder> Air<AB> for WidePermutationAir {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+fn trace(a: &[u32], b: &[u32]) -> RowMajorMatrix<Val> {
+    let values = a
+        .iter()
+        .zip(b)
+        .flat_map(|(&a, &b)| [Val::from_u32(a), Val::from_u32(b)])
+        .collect();
+    RowMajorMatrix::new(values, 2)
+}
+
+type Val = BabyBear;
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type Challenge = BinomialExtensionField<Val, 4>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+type Dft = Radix2DitParallel<Val>;
+type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+fn config() -> MyConfig {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_params = create_test_fri_params(challenge_mmcs, 2);
+    let pcs = Pcs::new(Dft::default(), val_mmcs, fri_params);
+    MyConfig::new(pcs, Challenger::new(perm))
+}
+
+const VALUES: [u32; 8] = [3, 1, 4, 1, 5, 9, 2, 6];
+const PERMUTED: [u32; 8] = [9, 1, 6, 3, 1, 2, 5, 4];
+
+#[test]
+fn test_permutation() {
+    let config = config();
+    let proof = prove(
+        &config,
+        &MultisetEqualityAir,
+        trace(&VALUES, &PERMUTED),
+        &vec![],
+    );
+    verify(&config, &MultisetEqualityAir, &proof, &vec![]).expect("verification failed");
+}
+
+#[test]
+fn test_permutation_columns_required() {
+    let config = config();
+    let proof = prove(
+        &config,
+        &MultisetEqualityAir,
+        trace(&VALUES, &PERMUTED),
+        &vec![],
+    );
+    // The proof's permutation round does not fit an AIR without one, and vice versa.
+    assert!(verify(&config, &UnconstrainedAir, &proof, &vec![]).is_err());
+    let proof = prove(
+        &config,
+        &UnconstrainedAir,
+        trace(&VALUES, &PERMUTED),
+        &vec![],
+    );
+    assert!(verify(&config, &MultisetEqualityAir, &proof, &vec![]).is_err());
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "constraints had nonzero value")]
+fn test_not_a_permutation() {
+    let config = config();
+    let mut not_permuted = PERMUTED;
+    not_permuted[0] = 7;
+    prove(
+        &config,
+        &MultisetEqualityAir,
+        trace(&VALUES, &not_permuted),
+        &vec![],
+    );
+}
+
+#[test]
+fn test_permutation_width_mismatch() {
+    let config = config();
+    let proof = prove(
+        &config,
+        &MultisetEqualityAir,
+        trace(&VALUES, &PERMUTED),
+        &vec![],
+    );
+    // Each extension field column is opened as its 4 base field coordinates.
+    let err = verify(&config, &WidePermutationAir, &proof, &vec![]).unwrap_err();
+    assert!(matches!(
+        err,
+        VerificationError::PermutationWidthMismatch {
+            expected: 8,
+            actual: 4
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "permutation width mismatch: expected 8 base field columns, found 4"
+    );
+}
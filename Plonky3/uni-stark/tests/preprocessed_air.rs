@@ -0,0 +1,220 @@
+use p3_air::{
+    Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, BaseAirWithPublicValues, PairBuilder,
+};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{Field, PrimeCharacteristicRing};
+use p3_fri::{TwoAdicFriPcs, create_test_fri_params};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_uni_stark::{
+    StarkConfig, VerificationError, prove_with_preprocessed, setup_preprocessed, verify,
+    verify_with_preprocessed,
+};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+/// Proves a running sum of a fixed column of constants: the constants are preprocessed, the
+/// running sum is the main trace and the total is the only public value.
+struct PrefixSumAir {
+    constants: Vec<u64>,
+}
+
+impl<F: Field> BaseAir<F> for PrefixSumAir {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        Some(RowMajorMatrix::new_col(
+            self.constants.iter().copied().map(F::from_u64).collect(),
+        ))
+    }
+}
+
+impl<F: Field> BaseAirWithPublicValues<F> for PrefixSumAir {
+    fn num_public_values(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: PairBuilder + AirBuilderWithPublicValues> Air<AB> for PrefixSumAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let preprocessed = builder.preprocessed();
+        let total = builder.public_values()[0];
+
+        let (local, next) = (
+            main.row_slice(0).expect("Matrix is empty?")[0].clone(),
+            main.row_slice(1).expect("Matrix only has 1 row?")[0].clone(),
+        );
+        let (constant, next_constant) = (
+            preprocessed.row_slice(0).expect("Matrix is empty?")[0].clone(),
+            preprocessed.row_slice(1).expect("Matrix only has 1 row?")[0].clone(),
+        );
+
+        builder.when_first_row().assert_eq(local.clone(), constant);
+        builder
+            .when_transition()
+            .assert_eq(next, local.clone() + next_constant);
+        builder.when_last_row().assert_eq(local, total);
+    }
+}
+
+/// An AIR with two preprocessed columns and no constraints, whose preprocessed key does not fit
+/// the proofs of [`PrefixSumAir`].
+struct TwoConstantsAir;
+
+impl<F: Field> BaseAir<F> for TwoConstantsAir {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        Some(RowMajorMatrix::new(F::zero_vec(2 * CONSTANTS.len()), 2))
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for TwoConstantsAir {
+    fn eval(&self, _builder: &mut AB) {}
+}
+
+fn prefix_sums(constants: &[u64]) -> RowMajorMatrix<Val> {
+    let sums = constants
+        .iter()
+        .scan(0, |sum, &c| {
+            *sum += c;
+            Some(Val::from_u64(*sum))
+        })
+        .collect();
+    RowMajorMatrix::new_col(sums)
+}
+
+type Val = BabyBear;
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type Challenge = BinomialExtensionField<Val, 4>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+type Dft = Radix2DitParallel<Val>;
+type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+fn config() -> MyConfig {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_params = create_test_fri_params(challenge_mmcs, 2);
+    let pcs = Pcs::new(Dft::default(), val_mmcs, fri_params);
+    MyConfig::new(pcs, Challenger::new(perm))
+}
+
+const CONSTANTS: [u64; 8] = [3, 1, 4, 1, 5, 9, 2, 6];
+
+#[test]
+fn test_preprocessed() {
+    let config = config();
+    let air = PrefixSumAir {
+        constants: CONSTANTS.to_vec(),
+    };
+    let (prover_data, verifier_key) = setup_preprocessed(&config, &air).unwrap();
+    assert_eq!((verifier_key.width(), verifier_key.degree_bits()), (1, 3));
+
+    let pis = vec![Val::from_u64(CONSTANTS.iter().sum())];
+    let trace = prefix_sums(&CONSTANTS);
+    let proof = prove_with_preprocessed(&config, &air, trace, &pis, Some(&prover_data));
+    verify_with_preprocessed(&config, &air, &proof, &pis, Some(&verifier_key))
+        .expect("verification failed");
+}
+
+#[test]
+fn test_wrong_preprocessed_key() {
+    let config = config();
+    let air = PrefixSumAir {
+        constants: CONSTANTS.to_vec(),
+    };
+    let (prover_data, _) = setup_preprocessed(&config, &air).unwrap();
+    let pis = vec![Val::from_u64(CONSTANTS.iter().sum())];
+    let proof = prove_with_preprocessed(
+        &config,
+        &air,
+        prefix_sums(&CONSTANTS),
+        &pis,
+        Some(&prover_data),
+    );
+
+    // Same total, different constants: the proof must not verify against them.
+    let mut other_constants = CONSTANTS;
+    other_constants.swap(0, 1);
+    let other_air = PrefixSumAir {
+        constants: other_constants.to_vec(),
+    };
+    let (_, other_key) = setup_preprocessed(&config, &other_air).unwrap();
+    assert!(verify_with_preprocessed(&config, &other_air, &proof, &pis, Some(&other_key)).is_err());
+
+    // Nor without any preprocessed key at all.
+    assert!(verify(&config, &air, &proof, &pis).is_err());
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "constraints had nonzero value on row 0")]
+fn test_trace_not_matching_preprocessed() {
+    let config = config();
+    let air = PrefixSumAir {
+        constants: CONSTANTS.to_vec(),
+    };
+    let (prover_data, _) = setup_preprocessed(&config, &air).unwrap();
+    let mut other_constants = CONSTANTS;
+    other_constants.swap(0, 1);
+    let pis = vec![Val::from_u64(CONSTANTS.iter().sum())];
+    prove_with_preprocessed(
+        &config,
+        &air,
+        prefix_sums(&other_constants),
+        &pis,
+        Some(&prover_data),
+    );
+}
+
+#[test]
+fn test_preprocessed_width_mismatch() {
+    let config = config();
+    let air = PrefixSumAir {
+        constants: CONSTANTS.to_vec(),
+    };
+    let (prover_data, _) = setup_preprocessed(&config, &air).unwrap();
+    let pis = vec![Val::from_u64(CONSTANTS.iter().sum())];
+    let proof = prove_with_preprocessed(
+        &config,
+        &air,
+        prefix_sums(&CONSTANTS),
+        &pis,
+        Some(&prover_data),
+    );
+
+    let (_, wide_key) = setup_preprocessed(&config, &TwoConstantsAir).unwrap();
+    let err = verify_with_preprocessed(&config, &air, &proof, &pis, Some(&wide_key)).unwrap_err();
+    assert!(matches!(
+        err,
+        VerificationError::PreprocessedWidthMismatch {
+            expected: 2,
+            actual: 1
+        }
+    ));
+    assert_eq!(
+        err.to_string(),
+        "preprocessed width mismatch: expected 2 columns, found 1"
+    );
+}
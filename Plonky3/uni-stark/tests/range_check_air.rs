@@ -0,0 +1,181 @@
+use p3_air::{
+    Air, BaseAir, BaseAirWithPublicValues, PairBuilder, PermutationAirBuilder, VirtualPairCol,
+};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{ExtensionField, Field, PrimeCharacteristicRing};
+use p3_fri::{TwoAdicFriPcs, create_test_fri_params};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_uni_stark::{
+    LogUp, RangeTable, StarkConfig, prove_with_preprocessed, setup_preprocessed,
+    verify_with_preprocessed,
+};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+/// Proves that every `x` is a 16-bit value by splitting it into bytes `lo` and `hi`, each looked
+/// up in a preprocessed byte table whose multiplicities are the last main column.
+struct U16Air {
+    height: usize,
+}
+
+const X: usize = 0;
+const LO: usize = 1;
+const HI: usize = 2;
+const MULTIPLICITY: usize = 3;
+
+impl U16Air {
+    fn logup<F: Field>(&self) -> LogUp<F> {
+        let table = RangeTable::BYTE;
+        LogUp {
+            sends: vec![
+                table.lookup(VirtualPairCol::single_main(LO), VirtualPairCol::ONE),
+                table.lookup(VirtualPairCol::single_main(HI), VirtualPairCol::ONE),
+            ],
+            receives: vec![table.table(0, MULTIPLICITY)],
+        }
+    }
+}
+
+impl<F: Field> BaseAir<F> for U16Air {
+    fn width(&self) -> usize {
+        4
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        Some(RowMajorMatrix::new_col(
+            RangeTable::BYTE.column(self.height),
+        ))
+    }
+
+    fn num_permutation_challenges(&self) -> usize {
+        LogUp::<F>::NUM_CHALLENGES
+    }
+
+    fn permutation_width(&self) -> usize {
+        self.logup::<F>().width()
+    }
+
+    fn permutation_trace<EF: ExtensionField<F>>(
+        &self,
+        main: &RowMajorMatrix<F>,
+        challenges: &[EF],
+    ) -> Option<RowMajorMatrix<EF>> {
+        let preprocessed = self.preprocessed_trace();
+        Some(
+            self.logup()
+                .generate_trace(preprocessed.as_ref(), main, challenges),
+        )
+    }
+}
+
+impl<F: Field> BaseAirWithPublicValues<F> for U16Air {}
+
+impl<AB> Air<AB> for U16Air
+where
+    AB: PermutationAirBuilder + PairBuilder,
+    AB::Var: Copy,
+{
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let preprocessed = builder.preprocessed();
+        let local = main.row_slice(0).expect("Matrix is empty?");
+        let table = preprocessed.row_slice(0).expect("Matrix is empty?");
+
+        builder.assert_eq(local[X], local[LO] + local[HI] * AB::Expr::from_u16(256));
+        self.logup()
+            .eval(builder, &table, &local, 0, 0, AB::ExprEF::ZERO);
+    }
+}
+
+/// The rows `(x, lo, hi)` with the multiplicities of the byte table appended.
+fn trace(rows: &[(u32, u32, u32)]) -> RowMajorMatrix<Val> {
+    let multiplicities: Vec<Val> = RangeTable::BYTE.multiplicities(
+        rows.len(),
+        rows.iter()
+            .flat_map(|&(_, lo, hi)| [u64::from(lo), u64::from(hi)]),
+    );
+    let values = rows
+        .iter()
+        .zip(multiplicities)
+        .flat_map(|(&(x, lo, hi), m)| [Val::from_u32(x), Val::from_u32(lo), Val::from_u32(hi), m])
+        .collect();
+    RowMajorMatrix::new(values, 4)
+}
+
+fn u16_rows(height: usize) -> Vec<(u32, u32, u32)> {
+    (0..height as u32)
+        .map(|i| {
+            let x = (i * 257 + 1000) & 0xFFFF;
+            (x, x & 0xFF, x >> 8)
+        })
+        .collect()
+}
+
+type Val = BabyBear;
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type Challenge = BinomialExtensionField<Val, 4>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+type Dft = Radix2DitParallel<Val>;
+type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+fn config() -> MyConfig {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_params = create_test_fri_params(challenge_mmcs, 2);
+    let pcs = Pcs::new(Dft::default(), val_mmcs, fri_params);
+    MyConfig::new(pcs, Challenger::new(perm))
+}
+
+const HEIGHT: usize = 256;
+
+#[test]
+fn test_range_check() {
+    let config = config();
+    let air = U16Air { height: HEIGHT };
+    let (prover_data, verifier_key) = setup_preprocessed(&config, &air).unwrap();
+    let proof = prove_with_preprocessed(
+        &config,
+        &air,
+        trace(&u16_rows(HEIGHT)),
+        &vec![],
+        Some(&prover_data),
+    );
+    verify_with_preprocessed(&config, &air, &proof, &vec![], Some(&verifier_key))
+        .expect("verification failed");
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "constraints had nonzero value")]
+fn test_value_out_of_range() {
+    let config = config();
+    let air = U16Air { height: HEIGHT };
+    let (prover_data, _) = setup_preprocessed(&config, &air).unwrap();
+    // 300 = 300 + 256 * 0 holds, but 300 is not a byte.
+    let mut rows = u16_rows(HEIGHT);
+    rows[5] = (300, 300, 0);
+    prove_with_preprocessed(&config, &air, trace(&rows), &vec![], Some(&prover_data));
+}
+
+#[test]
+#[should_panic(expected = "needs a trace of at least 256 rows")]
+fn test_table_too_tall() {
+    RangeTable::BYTE.column::<Val>(128);
+}
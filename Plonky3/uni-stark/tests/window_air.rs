@@ -0,0 +1,241 @@
+use core::fmt::Debug;
+use core::marker::PhantomData;
+
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, BaseAirWithPublicValues};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::{DuplexChallenger, HashChallenger, SerializingChallenger32};
+use p3_circle::CirclePcs;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{Field, PrimeCharacteristicRing};
+use p3_fri::{FriParameters, HidingFriPcs, TwoAdicFriPcs, create_test_fri_params_zk};
+use p3_keccak::Keccak256Hash;
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::{MerkleTreeHidingMmcs, MerkleTreeMmcs};
+use p3_mersenne_31::Mersenne31;
+use p3_symmetric::{
+    CompressionFunctionFromHasher, PaddingFreeSponge, SerializingHasher, TruncatedPermutation,
+};
+use p3_uni_stark::{StarkConfig, StarkGenericConfig, find_constraint_failures, prove, verify};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+/// The Tribonacci sequence in a single column, each value being the sum of the three before it,
+/// which needs a window of four rows. The public values are the first three values and the last.
+pub struct TribonacciAir;
+
+impl<F> BaseAir<F> for TribonacciAir {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn window_size(&self) -> usize {
+        4
+    }
+}
+
+impl<F> BaseAirWithPublicValues<F> for TribonacciAir {
+    fn num_public_values(&self) -> usize {
+        4
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for TribonacciAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let pis = builder.public_values();
+        let (first, last) = ([pis[0], pis[1], pis[2]], pis[3]);
+        let rows: [AB::Var; 4] =
+            core::array::from_fn(|k| main.row_slice(k).expect("Matrix has 4 rows")[0].clone());
+
+        builder.label("first values");
+        let mut when_first_row = builder.when_first_row();
+        for (row, value) in rows.iter().zip(first) {
+            when_first_row.assert_eq(row.clone(), value);
+        }
+
+        builder.label("tribonacci");
+        builder.when_transition_window(4).assert_eq(
+            rows[0].clone() + rows[1].clone() + rows[2].clone(),
+            rows[3].clone(),
+        );
+
+        builder.label("last value");
+        builder.when_last_row().assert_eq(rows[0].clone(), last);
+    }
+}
+
+/// The first `n` values of the Tribonacci sequence starting `0, 0, 1`, and the public values.
+fn generate_trace<F: Field>(n: usize) -> (RowMajorMatrix<F>, Vec<F>) {
+    let mut values = vec![F::ZERO, F::ZERO, F::ONE];
+    while values.len() < n {
+        let len = values.len();
+        values.push(values[len - 3] + values[len - 2] + values[len - 1]);
+    }
+    let public_values = vec![values[0], values[1], values[2], values[n - 1]];
+    (RowMajorMatrix::new_col(values), public_values)
+}
+
+fn do_test<SC: StarkGenericConfig>(config: SC, log_n: usize) -> Result<(), impl Debug> {
+    let (trace, public_values) = generate_trace(1 << log_n);
+
+    let proof = prove(&config, &TribonacciAir, trace, &public_values);
+
+    let serialized_proof = postcard::to_allocvec(&proof).expect("unable to serialize proof");
+    let deserialized_proof =
+        postcard::from_bytes(&serialized_proof).expect("unable to deserialize proof");
+
+    verify(&config, &TribonacciAir, &deserialized_proof, &public_values)
+}
+
+#[test]
+fn prove_bb_twoadic_window() -> Result<(), impl Debug> {
+    type Val = BabyBear;
+    type Challenge = BinomialExtensionField<Val, 4>;
+
+    type Perm = Poseidon2BabyBear<16>;
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+
+    type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+    let hash = MyHash::new(perm.clone());
+
+    type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+    let compress = MyCompress::new(perm.clone());
+
+    type ValMmcs =
+        MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+    let val_mmcs = ValMmcs::new(hash, compress);
+
+    type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+
+    type Dft = Radix2DitParallel<Val>;
+    let dft = Dft::default();
+
+    type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+
+    let fri_params = FriParameters {
+        log_blowup: 2,
+        log_final_poly_len: 3,
+        num_queries: 40,
+        proof_of_work_bits: 8,
+        mmcs: challenge_mmcs,
+    };
+    type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+    let pcs = Pcs::new(dft, val_mmcs, fri_params);
+    let challenger = Challenger::new(perm);
+
+    type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+    let config = MyConfig::new(pcs, challenger);
+
+    do_test(config, 6)
+}
+
+#[test]
+fn prove_bb_twoadic_window_zk() -> Result<(), impl Debug> {
+    type Val = BabyBear;
+    type Challenge = BinomialExtensionField<Val, 4>;
+
+    type Perm = Poseidon2BabyBear<16>;
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+
+    type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+    let hash = MyHash::new(perm.clone());
+
+    type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+    let compress = MyCompress::new(perm.clone());
+
+    type ValMmcs = MerkleTreeHidingMmcs<
+        <Val as Field>::Packing,
+        <Val as Field>::Packing,
+        MyHash,
+        MyCompress,
+        SmallRng,
+        8,
+        4,
+    >;
+    let val_mmcs = ValMmcs::new(hash, compress, rng);
+
+    type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+
+    type Dft = Radix2DitParallel<Val>;
+    let dft = Dft::default();
+
+    type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+
+    let fri_params = create_test_fri_params_zk(challenge_mmcs);
+    type HidingPcs = HidingFriPcs<Val, Dft, ValMmcs, ChallengeMmcs, SmallRng>;
+    let pcs = HidingPcs::new(dft, val_mmcs, fri_params, 4, SmallRng::seed_from_u64(1));
+    type MyConfig = StarkConfig<HidingPcs, Challenge, Challenger>;
+    let challenger = Challenger::new(perm);
+    let config = MyConfig::new(pcs, challenger);
+
+    do_test(config, 6)
+}
+
+#[test]
+fn prove_m31_circle_window() -> Result<(), impl Debug> {
+    type Val = Mersenne31;
+    type Challenge = BinomialExtensionField<Val, 3>;
+
+    type ByteHash = Keccak256Hash;
+    type FieldHash = SerializingHasher<ByteHash>;
+    let byte_hash = ByteHash {};
+    let field_hash = FieldHash::new(byte_hash);
+
+    type MyCompress = CompressionFunctionFromHasher<ByteHash, 2, 32>;
+    let compress = MyCompress::new(byte_hash);
+
+    type ValMmcs = MerkleTreeMmcs<Val, u8, FieldHash, MyCompress, 32>;
+    let val_mmcs = ValMmcs::new(field_hash, compress);
+
+    type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+
+    type Challenger = SerializingChallenger32<Val, HashChallenger<u8, ByteHash, 32>>;
+
+    let fri_params = FriParameters {
+        log_blowup: 2,
+        log_final_poly_len: 0,
+        num_queries: 40,
+        proof_of_work_bits: 8,
+        mmcs: challenge_mmcs,
+    };
+
+    type Pcs = CirclePcs<Val, ValMmcs, ChallengeMmcs>;
+    let pcs = Pcs {
+        mmcs: val_mmcs,
+        fri_params,
+        _phantom: PhantomData,
+    };
+    let challenger = Challenger::from_hasher(vec![], byte_hash);
+
+    type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+    let config = MyConfig::new(pcs, challenger);
+
+    do_test(config, 6)
+}
+
+#[test]
+fn test_window_failures() {
+    let (mut trace, public_values) = generate_trace::<BabyBear>(16);
+    assert!(find_constraint_failures(&TribonacciAir, &trace, &public_values).is_ok());
+
+    // The last value is only the last row of the window starting 3 rows before it.
+    trace.values[15] += BabyBear::ONE;
+    let report = find_constraint_failures(&TribonacciAir, &trace, &public_values);
+    assert_eq!(report.failing_rows("tribonacci"), [12]);
+    assert_eq!(report.failing_rows("last value"), [15]);
+
+    // A value in the middle is in the windows of the 4 rows up to it.
+    let (mut trace, _) = generate_trace::<BabyBear>(16);
+    trace.values[8] += BabyBear::ONE;
+    let report = find_constraint_failures(&TribonacciAir, &trace, &public_values);
+    assert_eq!(report.failing_rows("tribonacci"), [5, 6, 7, 8]);
+    assert!(report.failing_rows("last value").is_empty());
+}
@@ -0,0 +1,173 @@
+//! The universal adder: the `ADD`-only predecessor of [`AluChip`](crate::AluChip).
+
+use core::borrow::{Borrow, BorrowMut};
+use core::mem::size_of;
+
+use p3_air::{Air, AirBuilder, BaseAir, BaseAirWithPublicValues};
+use p3_field::{PrimeCharacteristicRing, PrimeField64};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::{Instruction, NUM_REGISTERS, Opcode};
+
+/// An AIR for a four register machine whose only instruction is `ADD`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AdderChip;
+
+/// Columns for the adder chip: 4 register values and 4*3 one-hot register selectors.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AdderRow<T> {
+    // Register values.
+    pub r0: T,
+    pub r1: T,
+    pub r2: T,
+    pub r3: T,
+    // Destination selectors (one-hot).
+    pub dest_0: T,
+    pub dest_1: T,
+    pub dest_2: T,
+    pub dest_3: T,
+    // Source1 selectors (one-hot).
+    pub src1_0: T,
+    pub src1_1: T,
+    pub src1_2: T,
+    pub src1_3: T,
+    // Source2 selectors (one-hot).
+    pub src2_0: T,
+    pub src2_1: T,
+    pub src2_2: T,
+    pub src2_3: T,
+}
+
+impl<T> AdderRow<T> {
+    pub const fn regs(&self) -> [&T; 4] {
+        [&self.r0, &self.r1, &self.r2, &self.r3]
+    }
+
+    pub const fn dest(&self) -> [&T; 4] {
+        [&self.dest_0, &self.dest_1, &self.dest_2, &self.dest_3]
+    }
+
+    pub const fn src1(&self) -> [&T; 4] {
+        [&self.src1_0, &self.src1_1, &self.src1_2, &self.src1_3]
+    }
+
+    pub const fn src2(&self) -> [&T; 4] {
+        [&self.src2_0, &self.src2_1, &self.src2_2, &self.src2_3]
+    }
+}
+
+/// Number of columns in the adder trace.
+pub const NUM_ADDER_COLS: usize = size_of::<AdderRow<u8>>();
+
+impl<T> Borrow<AdderRow<T>> for [T] {
+    fn borrow(&self) -> &AdderRow<T> {
+        debug_assert_eq!(self.len(), NUM_ADDER_COLS);
+        let (prefix, shorts, suffix) = unsafe { self.align_to::<AdderRow<T>>() };
+        debug_assert!(prefix.is_empty(), "Alignment should match");
+        debug_assert!(suffix.is_empty(), "Alignment should match");
+        debug_assert_eq!(shorts.len(), 1);
+        &shorts[0]
+    }
+}
+
+impl<T> BorrowMut<AdderRow<T>> for [T] {
+    fn borrow_mut(&mut self) -> &mut AdderRow<T> {
+        debug_assert_eq!(self.len(), NUM_ADDER_COLS);
+        let (prefix, shorts, suffix) = unsafe { self.align_to_mut::<AdderRow<T>>() };
+        debug_assert!(prefix.is_empty(), "Alignment should match");
+        debug_assert!(suffix.is_empty(), "Alignment should match");
+        debug_assert_eq!(shorts.len(), 1);
+        &mut shorts[0]
+    }
+}
+
+impl<F> BaseAir<F> for AdderChip {
+    fn width(&self) -> usize {
+        NUM_ADDER_COLS
+    }
+}
+
+impl<F> BaseAirWithPublicValues<F> for AdderChip {}
+
+impl<AB: AirBuilder> Air<AB> for AdderChip {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, next) = (
+            main.row_slice(0).expect("Matrix is empty?"),
+            main.row_slice(1).expect("Matrix only has 1 row?"),
+        );
+        let local: &AdderRow<AB::Var> = (*local).borrow();
+        let next: &AdderRow<AB::Var> = (*next).borrow();
+
+        // Every selector is boolean and each group of selectors is one-hot.
+        for group in [local.dest(), local.src1(), local.src2()] {
+            builder.assert_bools(group.map(|s| s.clone()));
+            builder.assert_one(group.into_iter().cloned().map(Into::into).sum::<AB::Expr>());
+        }
+
+        let regs = local.regs();
+        let read = |selectors: [&AB::Var; 4]| -> AB::Expr {
+            regs.iter()
+                .zip(selectors)
+                .map(|(&r, s)| r.clone() * s.clone())
+                .sum()
+        };
+        let add_result = read(local.src1()) + read(local.src2());
+
+        // The destination register takes the sum and every other register is unchanged.
+        let mut when_transition = builder.when_transition();
+        for ((reg, dest), next_reg) in regs.into_iter().zip(local.dest()).zip(next.regs()) {
+            let expected_next =
+                reg.clone() * (AB::Expr::ONE - dest.clone()) + add_result.clone() * dest.clone();
+            when_transition.assert_eq(next_reg.clone(), expected_next);
+        }
+    }
+}
+
+impl AdderChip {
+    /// Generate the execution trace of an `ADD`-only `program` starting from `initial_regs`.
+    ///
+    /// The adder has no `NOP`, so the trace is padded by repeating the last row. The repeated rows
+    /// only satisfy the transition constraint if the last instruction leaves the registers
+    /// unchanged, so in general only programs whose length is a power of two are provable.
+    ///
+    /// # Panics
+    /// Panics if the program is empty or contains an instruction other than `ADD`.
+    pub fn generate_trace<F: PrimeField64>(
+        program: &[Instruction],
+        initial_regs: [F; NUM_REGISTERS],
+    ) -> RowMajorMatrix<F> {
+        let n = program.len();
+        assert!(n > 0, "Program cannot be empty");
+        let trace_len = n.next_power_of_two();
+
+        let mut trace =
+            RowMajorMatrix::new(F::zero_vec(trace_len * NUM_ADDER_COLS), NUM_ADDER_COLS);
+
+        let mut regs = initial_regs;
+        for (inst, row) in program.iter().zip(trace.rows_mut()) {
+            assert_eq!(inst.op, Opcode::ADD, "The adder only supports ADD");
+            let row: &mut AdderRow<F> = row.borrow_mut();
+            [row.r0, row.r1, row.r2, row.r3] = regs;
+            [row.dest_0, row.dest_1, row.dest_2, row.dest_3] = one_hot(inst.dest);
+            [row.src1_0, row.src1_1, row.src1_2, row.src1_3] = one_hot(inst.src1);
+            [row.src2_0, row.src2_1, row.src2_2, row.src2_3] = one_hot(inst.src2);
+            regs[inst.dest] = regs[inst.src1] + regs[inst.src2];
+        }
+
+        // Pad by repeating the last row.
+        let last_row = trace.values[(n - 1) * NUM_ADDER_COLS..n * NUM_ADDER_COLS].to_vec();
+        for row in trace.rows_mut().skip(n) {
+            row.copy_from_slice(&last_row);
+        }
+
+        trace
+    }
+}
+
+fn one_hot<F: PrimeCharacteristicRing>(index: usize) -> [F; NUM_REGISTERS] {
+    assert!(index < NUM_REGISTERS, "Invalid register: {index}");
+    core::array::from_fn(|i| F::from_bool(i == index))
+}
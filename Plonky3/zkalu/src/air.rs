@@ -0,0 +1,486 @@
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+
+use p3_air::utils::{add2, pack_bits_le};
+use p3_air::{
+    Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, BaseAirWithPublicValues,
+    PermutationAirBuilder,
+};
+use p3_field::{ExtensionField, Field, PrimeCharacteristicRing};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::NUM_REGISTERS;
+use crate::columns::{AluRow, NUM_LIMBS, num_alu_cols, u32_to_limbs};
+use crate::decoder::eval_decoder;
+use crate::memory::{
+    NUM_MEMORY_CHALLENGES, NUM_MEMORY_PERMUTATION_COLS, eval_memory, eval_memory_permutation,
+    generate_memory_permutation,
+};
+
+/// Number of public values for the default [`NUM_REGISTERS`] registers: the initial registers
+/// followed by the final registers, each as 16-bit limbs.
+pub const NUM_ALU_PUBLIC_VALUES: usize = 2 * NUM_REGISTERS * NUM_LIMBS;
+
+/// An AIR for a machine with `NUM_REGS` 32-bit registers supporting `ADD`, `SUB`, `MUL`, the
+/// bitwise operations `AND`, `OR`, `XOR` and `NOT`, the shifts `SLL`, `SRL` and `SRA`, the
+/// comparisons `EQ`, `LT` and `SLT`, the unsigned divisions `DIVU` and `REMU`, the immediate and
+/// move instructions `ADDI`, `SLLI`, `SRLI`, `SRAI`, `LOADI` and `MOV`, the branches `JMP`, `BEQ`,
+/// `BNE`, `BLT`, `BGE`, `BLTU` and `BGEU`, the memory instructions `LOAD` and `STORE`, and `NOP`.
+///
+/// Row `i` of the trace holds the register state before the `i`-th executed instruction, the
+/// instruction itself and its `pc`. The transition constraint from row `i` to row `i + 1` applies
+/// that instruction and moves the `pc` to the branch target if a branch is taken, or to `pc + 1`
+/// otherwise.
+///
+/// Arithmetic wraps modulo `2^32`. Register limbs are not range checked directly: the initial
+/// registers are public and every value written to a register is the range checked `result`.
+/// The `MUL` constraints assume the field characteristic is larger than `2^27`.
+///
+/// Memory is checked offline: the trace also holds a memory table of every access sorted by
+/// address, tied to the instructions by a permutation argument over two extension field columns.
+/// See [`MemoryRow`](crate::MemoryRow).
+///
+/// The public values are the registers before the first instruction and after the last one,
+/// see [`AluChip::public_values`]. Each row's instruction word is decoded into its selectors, but
+/// the words are part of the witness, so a proof binds no program at all: it only shows that some
+/// sequence of instructions maps the initial registers to the final ones.
+/// [`RomAluChip`](crate::RomAluChip) additionally fixes the program.
+///
+/// The register count defaults to [`NUM_REGISTERS`]. As a const parameter cannot be inferred from
+/// a unit value, pass the chip itself as `AluChip::<N>`; the associated functions infer `N` from
+/// their register arrays.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AluChip<const NUM_REGS: usize = NUM_REGISTERS>;
+
+impl<const NUM_REGS: usize> AluChip<NUM_REGS> {
+    /// Lay out the public values expected by the AIR: `initial_regs` followed by `final_regs`.
+    pub fn public_values<F: PrimeCharacteristicRing>(
+        initial_regs: &[u32; NUM_REGS],
+        final_regs: &[u32; NUM_REGS],
+    ) -> Vec<F> {
+        initial_regs
+            .iter()
+            .chain(final_regs)
+            .flat_map(|&reg| u32_to_limbs(reg))
+            .collect()
+    }
+}
+
+impl<F, const NUM_REGS: usize> BaseAir<F> for AluChip<NUM_REGS> {
+    fn width(&self) -> usize {
+        num_alu_cols::<NUM_REGS>()
+    }
+
+    fn num_permutation_challenges(&self) -> usize {
+        NUM_MEMORY_CHALLENGES
+    }
+
+    fn permutation_width(&self) -> usize {
+        NUM_MEMORY_PERMUTATION_COLS
+    }
+
+    fn permutation_trace<EF: ExtensionField<F>>(
+        &self,
+        main: &RowMajorMatrix<F>,
+        challenges: &[EF],
+    ) -> Option<RowMajorMatrix<EF>>
+    where
+        F: Field,
+    {
+        Some(generate_memory_permutation::<F, EF, NUM_REGS>(
+            main, challenges,
+        ))
+    }
+}
+
+impl<F, const NUM_REGS: usize> BaseAirWithPublicValues<F> for AluChip<NUM_REGS> {
+    fn num_public_values(&self) -> usize {
+        2 * NUM_REGS * NUM_LIMBS
+    }
+}
+
+impl<AB: PermutationAirBuilder + AirBuilderWithPublicValues, const NUM_REGS: usize> Air<AB>
+    for AluChip<NUM_REGS>
+{
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, next) = (
+            main.row_slice(0).expect("Matrix is empty?"),
+            main.row_slice(1).expect("Matrix only has 1 row?"),
+        );
+        let local: &AluRow<AB::Var, NUM_REGS> = (*local).borrow();
+        let next: &AluRow<AB::Var, NUM_REGS> = (*next).borrow();
+
+        // The selectors are one-hot, or all zero for the destination of an instruction writing no
+        // register, and decode the instruction word.
+        eval_decoder(builder, local);
+
+        // The source operands are dot products of the register limbs with the selectors, except
+        // that ADDI, the immediate shifts and LOADI take the immediate as their second operand.
+        // Reading it through src2_val range checks it along with the other operands.
+        builder.label("operands");
+        let regs = &local.regs;
+        let uses_imm = local.op_addi.clone()
+            + local.op_slli.clone()
+            + local.op_srli.clone()
+            + local.op_srai.clone()
+            + local.op_loadi.clone();
+        let read = |selectors: &[AB::Var; NUM_REGS], limb: usize| -> AB::Expr {
+            regs.iter()
+                .zip(selectors)
+                .map(|(r, s)| r[limb].clone() * s.clone())
+                .sum()
+        };
+        for limb in 0..NUM_LIMBS {
+            builder.assert_eq(local.src1_val[limb].clone(), read(&local.src1, limb));
+            let src2_reg = read(&local.src2, limb);
+            builder.assert_eq(
+                local.src2_val[limb].clone(),
+                src2_reg.clone() + (local.imm[limb].clone() - src2_reg) * uses_imm.clone(),
+            );
+        }
+
+        // Each operand, the result and the division witnesses are the packing of their bits, which
+        // range checks the limbs.
+        builder.label("range checks");
+        let words = [
+            (&local.src1_val, &local.src1_bits),
+            (&local.src2_val, &local.src2_bits),
+            (&local.result, &local.result_bits),
+            (&local.quotient, &local.quotient_bits),
+            (&local.remainder, &local.remainder_bits),
+        ];
+        for (limbs, bits) in words {
+            builder.assert_bools(bits.clone());
+            for (limb, limb_bits) in limbs.iter().zip(bits.chunks_exact(16)) {
+                builder.assert_eq(limb.clone(), pack_bits_le(limb_bits.iter().cloned()));
+            }
+        }
+        builder.assert_bools(local.mul_carry_lo.clone());
+        builder.assert_bools(local.mul_carry_hi.clone());
+        builder.assert_bools(local.diff_bits.clone());
+        builder.assert_bools(local.borrow.clone());
+        for (limb, limb_bits) in local.diff.iter().zip(local.diff_bits.chunks_exact(16)) {
+            builder.assert_eq(limb.clone(), pack_bits_le(limb_bits.iter().cloned()));
+        }
+
+        // ADD and ADDI: result = src1 + src2 mod 2^32.
+        builder.label("add");
+        let src2_val = local.src2_val.clone().map(Into::into);
+        add2(
+            &mut builder.when(local.op_add.clone() + local.op_addi.clone()),
+            &local.result,
+            &local.src1_val,
+            &src2_val,
+        );
+
+        // SUB: src1 = result + src2 mod 2^32.
+        builder.label("sub");
+        add2(
+            &mut builder.when(local.op_sub.clone()),
+            &local.src1_val,
+            &local.result,
+            &src2_val,
+        );
+
+        // LOAD and STORE: the address src1 + imm mod 2^32 is held in diff, which is range checked.
+        // imm is not, but a prover free to choose imm can reach any address anyway, and a ROM
+        // only holds 16-bit immediate limbs.
+        builder.label("memory address");
+        let imm = local.imm.clone().map(Into::into);
+        add2(
+            &mut builder.when(local.op_load.clone() + local.op_store.clone()),
+            &local.diff,
+            &local.src1_val,
+            &imm,
+        );
+
+        // LOADI: result = imm. MOV: result = src1. STORE: result = src2, the value written. The
+        // value a LOAD reads is checked by the memory argument.
+        builder.label("moves");
+        for limb in 0..NUM_LIMBS {
+            builder
+                .when(local.op_loadi.clone())
+                .assert_eq(local.result[limb].clone(), local.src2_val[limb].clone());
+            builder
+                .when(local.op_mov.clone())
+                .assert_eq(local.result[limb].clone(), local.src1_val[limb].clone());
+            builder
+                .when(local.op_store.clone())
+                .assert_eq(local.result[limb].clone(), local.src2_val[limb].clone());
+        }
+
+        // MUL, DIVU and REMU multiply words byte by byte: conv(x, y, k) is the sum of the products
+        // of the bytes of x and y whose indices add up to k. Every partial sum below fits in the
+        // field, so with range checked carries the equations hold over the integers.
+        let bytes = |bits: &[AB::Var; 32]| -> [AB::Expr; 4] {
+            core::array::from_fn(|i| pack_bits_le(bits[8 * i..8 * (i + 1)].iter().cloned()))
+        };
+        let conv = |x: &[AB::Expr; 4], y: &[AB::Expr; 4], k: usize| -> AB::Expr {
+            (k.saturating_sub(3)..=k.min(3))
+                .map(|i| x[i].clone() * y[k - i].clone())
+                .sum::<AB::Expr>()
+        };
+        let src1_bytes = bytes(&local.src1_bits);
+        let src2_bytes = bytes(&local.src2_bits);
+        let quotient_bytes = bytes(&local.quotient_bits);
+        let carry_lo: AB::Expr = pack_bits_le(local.mul_carry_lo.iter().cloned());
+        let carry_hi: AB::Expr = pack_bits_le(local.mul_carry_hi.iter().cloned());
+
+        // MUL: the low 32 bits of the product of the operands.
+        builder.label("mul");
+        let product = |k| conv(&src1_bytes, &src2_bytes, k);
+        let mut when_mul = builder.when(local.op_mul.clone());
+        when_mul.assert_eq(
+            product(0) + product(1).mul_2exp_u64(8),
+            local.result[0].clone() + carry_lo.clone().mul_2exp_u64(16),
+        );
+        when_mul.assert_eq(
+            product(2) + product(3).mul_2exp_u64(8) + carry_lo.clone(),
+            local.result[1].clone() + carry_hi.clone().mul_2exp_u64(16),
+        );
+
+        // DIVU/REMU: src1 = quotient * src2 + remainder over the integers.
+        builder.label("division");
+        let is_div = local.op_divu.clone() + local.op_remu.clone();
+        let product = |k| conv(&quotient_bytes, &src2_bytes, k);
+        let mut when_div = builder.when(is_div.clone());
+        when_div.assert_eq(
+            product(0) + product(1).mul_2exp_u64(8) + local.remainder[0].clone(),
+            local.src1_val[0].clone() + carry_lo.clone().mul_2exp_u64(16),
+        );
+        when_div.assert_eq(
+            product(2) + product(3).mul_2exp_u64(8) + carry_lo + local.remainder[1].clone(),
+            local.src1_val[1].clone() + carry_hi.clone().mul_2exp_u64(16),
+        );
+        // Nothing may overflow past 2^32. Each term below is a nonnegative integer too small to
+        // wrap around the field, so each one vanishes.
+        when_div.assert_zero(product(4) + product(5).mul_2exp_u64(8) + carry_hi);
+        when_div.assert_zero(product(6));
+
+        // Bitwise operations act on each bit of the operands independently:
+        // result_i * is_bitwise = (a_i & b_i) * op_and + (a_i | b_i) * op_or
+        //                       + (a_i ^ b_i) * op_xor + !a_i * op_not
+        builder.label("bitwise");
+        let is_bitwise = local.op_and.clone()
+            + local.op_or.clone()
+            + local.op_xor.clone()
+            + local.op_not.clone();
+        for ((a, b), r) in local
+            .src1_bits
+            .iter()
+            .zip(&local.src2_bits)
+            .zip(&local.result_bits)
+        {
+            let (a, b): (AB::Expr, AB::Expr) = (a.clone().into(), b.clone().into());
+            let and = a.clone() * b.clone();
+            let xor = a.xor(&b);
+            let or = xor.clone() + and.clone();
+            let not = a.andn(&AB::Expr::ONE);
+            builder.assert_eq(
+                r.clone() * is_bitwise.clone(),
+                and * local.op_and.clone()
+                    + or * local.op_or.clone()
+                    + xor * local.op_xor.clone()
+                    + not * local.op_not.clone(),
+            );
+        }
+
+        // Shifts move each bit of src1 by the shift amount, the low 5 bits of src2, which the
+        // shift columns encode one-hot. Each result bit is the sum over the shift amounts of the
+        // src1 bit it comes from, where the bits shifted in are zeros, or copies of the sign bit
+        // for SRA.
+        builder.label("shift");
+        let op_sll = local.op_sll.clone() + local.op_slli.clone();
+        let op_srl = local.op_srl.clone() + local.op_srli.clone();
+        let op_sra = local.op_sra.clone() + local.op_srai.clone();
+        let is_shift = op_sll.clone() + op_srl.clone() + op_sra.clone();
+        let shift = &local.shift;
+        builder.assert_bools(shift.clone());
+        builder.assert_eq(
+            shift.iter().cloned().map(Into::into).sum::<AB::Expr>(),
+            is_shift.clone(),
+        );
+        let shift_amount = shift
+            .iter()
+            .enumerate()
+            .map(|(s, bit)| bit.clone() * AB::F::from_usize(s))
+            .sum::<AB::Expr>();
+        builder.when(is_shift.clone()).assert_eq(
+            shift_amount,
+            pack_bits_le(local.src2_bits[..5].iter().cloned()),
+        );
+        let a = &local.src1_bits;
+        for (i, r) in local.result_bits.iter().enumerate() {
+            let left = (0..=i)
+                .map(|s| shift[s].clone() * a[i - s].clone())
+                .sum::<AB::Expr>();
+            let right = (0..32 - i)
+                .map(|s| shift[s].clone() * a[i + s].clone())
+                .sum::<AB::Expr>();
+            let sign = (32 - i..32)
+                .map(|s| shift[s].clone().into())
+                .sum::<AB::Expr>()
+                * a[31].clone();
+            builder.assert_eq(
+                r.clone() * is_shift.clone(),
+                left * op_sll.clone()
+                    + right.clone() * op_srl.clone()
+                    + (right + sign) * op_sra.clone(),
+            );
+        }
+
+        // Comparisons and conditional branches compute diff = a - b mod 2^32 with limb borrows,
+        // where a and b are the operands, with their sign bits flipped for SLT, BLT and BGE so
+        // that a signed comparison becomes an unsigned one. The final borrow is set exactly when
+        // a < b.
+        builder.label("comparison");
+        let is_eq = local.op_eq.clone() + local.op_beq.clone() + local.op_bne.clone();
+        let is_less = local.op_lt.clone()
+            + local.op_slt.clone()
+            + local.op_blt.clone()
+            + local.op_bge.clone()
+            + local.op_bltu.clone()
+            + local.op_bgeu.clone();
+        let is_cmp = is_eq.clone() + is_less.clone();
+        let is_signed = local.op_slt.clone() + local.op_blt.clone() + local.op_bge.clone();
+        let flip = |val: &[AB::Var; NUM_LIMBS], sign: &AB::Var| -> AB::Expr {
+            // The high limb with its top bit flipped: val[1] + 2^15 * (1 - 2 * sign).
+            let sign: AB::Expr = sign.clone().into();
+            val[1].clone() + (AB::Expr::ONE - sign.double()).mul_2exp_u64(15) * is_signed.clone()
+        };
+        let two_16 = AB::F::from_u32(1 << 16);
+        let a_hi = flip(&local.src1_val, &local.src1_bits[31]);
+        let b_hi = flip(&local.src2_val, &local.src2_bits[31]);
+        let mut when_cmp = builder.when(is_cmp);
+        when_cmp.assert_zero(
+            local.src1_val[0].clone() - local.src2_val[0].clone() - local.diff[0].clone()
+                + local.borrow[0].clone() * two_16,
+        );
+        when_cmp.assert_zero(
+            a_hi - b_hi - local.diff[1].clone() - local.borrow[0].clone()
+                + local.borrow[1].clone() * two_16,
+        );
+        // The result of a comparison is a single bit.
+        when_cmp.assert_zero(local.result[1].clone());
+
+        // LT, SLT and the order branches: result = final borrow. Branches write no register, so
+        // for them result is only the less-than flag.
+        builder.label("less than");
+        builder
+            .when(is_less)
+            .assert_eq(local.result[0].clone(), local.borrow[1].clone());
+
+        // EQ, BEQ and BNE: diff[0] + diff[1] is a sum of two 16-bit limbs, so it vanishes exactly
+        // when diff = 0. With diff_inv as a witness, result = 1 - sum * diff_inv and
+        // result * sum = 0 force result = 1 if sum = 0 and result = 0 otherwise. Branches write
+        // no register, so for them result is only the equality flag.
+        builder.label("equality");
+        let diff_sum = local.diff[0].clone() + local.diff[1].clone();
+        let mut when_eq = builder.when(is_eq);
+        when_eq.assert_eq(
+            local.result[0].clone(),
+            AB::Expr::ONE - diff_sum.clone() * local.diff_inv.clone(),
+        );
+        when_eq.assert_zero(local.result[0].clone() * diff_sum);
+
+        // DIVU/REMU: remainder < src2 unless src2 = 0, checked by computing remainder - src2 with
+        // the comparison columns and requiring a final borrow. div_by_zero is set exactly when
+        // src2 = 0, by the same argument as for EQ, and then the quotient is all ones. The
+        // remainder is already pinned to src1 by src1 = quotient * 0 + remainder.
+        builder.label("division remainder");
+        let src2_sum = local.src2_val[0].clone() + local.src2_val[1].clone();
+        let mut when_div = builder.when(is_div.clone());
+        when_div.assert_zero(
+            local.remainder[0].clone() - local.src2_val[0].clone() - local.diff[0].clone()
+                + local.borrow[0].clone() * two_16,
+        );
+        when_div.assert_zero(
+            local.remainder[1].clone()
+                - local.src2_val[1].clone()
+                - local.diff[1].clone()
+                - local.borrow[0].clone()
+                + local.borrow[1].clone() * two_16,
+        );
+        when_div.assert_eq(
+            local.div_by_zero.clone(),
+            AB::Expr::ONE - src2_sum.clone() * local.src2_inv.clone(),
+        );
+        when_div.assert_zero(local.div_by_zero.clone() * src2_sum);
+        builder
+            .when(is_div.clone() * (AB::Expr::ONE - local.div_by_zero.clone()))
+            .assert_one(local.borrow[1].clone());
+        let mut when_div_by_zero = builder.when(is_div * local.div_by_zero.clone());
+        for limb in local.quotient.clone() {
+            when_div_by_zero.assert_eq(limb, AB::Expr::from_u32(0xFFFF));
+        }
+
+        // DIVU writes the quotient and REMU the remainder.
+        builder.label("division result");
+        for limb in 0..NUM_LIMBS {
+            builder
+                .when(local.op_divu.clone())
+                .assert_eq(local.result[limb].clone(), local.quotient[limb].clone());
+            builder
+                .when(local.op_remu.clone())
+                .assert_eq(local.result[limb].clone(), local.remainder[limb].clone());
+        }
+
+        // The destination register takes the result and every other register is unchanged.
+        let expected_next: [[AB::Expr; NUM_LIMBS]; NUM_REGS] = core::array::from_fn(|i| {
+            let dest = local.dest[i].clone();
+            core::array::from_fn(|limb| {
+                regs[i][limb].clone() * (AB::Expr::ONE - dest.clone())
+                    + local.result[limb].clone() * dest.clone()
+            })
+        });
+
+        let public_values = builder.public_values();
+        let (initial_regs, final_regs) = public_values.split_at(NUM_REGS * NUM_LIMBS);
+        let initial_regs: Vec<AB::Expr> = initial_regs.iter().map(|&v| v.into()).collect();
+        let final_regs: Vec<AB::Expr> = final_regs.iter().map(|&v| v.into()).collect();
+
+        // The first row starts from the claimed initial registers at the first instruction.
+        builder.label("initial state");
+        let mut when_first_row = builder.when_first_row();
+        when_first_row.assert_zero(local.pc.clone());
+        when_first_row.assert_zero(local.clk.clone());
+        for (reg, initial) in regs.iter().flatten().zip(initial_regs) {
+            when_first_row.assert_eq(reg.clone(), initial);
+        }
+
+        // Each row runs the instruction after the previous one: the branch target imm if a branch
+        // is taken and pc + 1 otherwise. BEQ, BLT and BLTU are taken when their result flag is
+        // set, and BNE, BGE and BGEU when it is not.
+        builder.label("transition");
+        let flag = local.result[0].clone();
+        let taken = local.op_jmp.clone()
+            + (local.op_beq.clone() + local.op_blt.clone() + local.op_bltu.clone()) * flag.clone()
+            + (local.op_bne.clone() + local.op_bge.clone() + local.op_bgeu.clone())
+                * (AB::Expr::ONE - flag);
+        let target = local.imm[0].clone() + local.imm[1].clone() * two_16;
+        let pc_plus_one = local.pc.clone() + AB::Expr::ONE;
+        let mut when_transition = builder.when_transition();
+        when_transition.assert_eq(next.clk.clone(), local.clk.clone() + AB::Expr::ONE);
+        when_transition.assert_eq(
+            next.pc.clone(),
+            pc_plus_one.clone() + taken * (target - pc_plus_one),
+        );
+        let next_regs = next.regs.iter().flatten();
+        for (next_reg, expected) in next_regs.zip(expected_next.clone().into_iter().flatten()) {
+            when_transition.assert_eq(next_reg.clone(), expected);
+        }
+
+        // The last row has no successor, so its instruction is applied directly to the claimed
+        // final registers.
+        builder.label("final registers");
+        let mut when_last_row = builder.when_last_row();
+        for (expected, final_reg) in expected_next.into_iter().flatten().zip(final_regs) {
+            when_last_row.assert_eq(expected, final_reg);
+        }
+
+        eval_memory(builder, &local.mem, &next.mem);
+        eval_memory_permutation(builder, local);
+    }
+}
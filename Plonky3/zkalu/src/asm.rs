@@ -0,0 +1,399 @@
+//! A text assembly format for ALU programs.
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{Instruction, Opcode};
+
+/// An error in assembly source, at a 1-based line and column.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: AsmErrorKind,
+}
+
+/// The kinds of [`AsmError`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AsmErrorKind {
+    /// The mnemonic names no opcode.
+    UnknownMnemonic(String),
+    /// The instruction takes `expected` operands but was given `found`.
+    WrongOperandCount { expected: usize, found: usize },
+    /// The operand is not a register such as `r1`.
+    ExpectedRegister,
+    /// The operand is not an immediate fitting in 32 bits.
+    InvalidImmediate,
+    /// The operand is not a memory operand such as `8(r1)`.
+    ExpectedMemoryOperand,
+    /// The label is not an identifier.
+    InvalidLabel,
+    /// The label is defined twice.
+    DuplicateLabel(String),
+    /// The branch target is neither an instruction index nor a defined label.
+    UnknownLabel(String),
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.kind
+        )
+    }
+}
+
+impl fmt::Display for AsmErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownMnemonic(name) => write!(f, "unknown mnemonic `{name}`"),
+            Self::WrongOperandCount { expected, found } => {
+                write!(f, "expected {expected} operands, found {found}")
+            }
+            Self::ExpectedRegister => write!(f, "expected a register"),
+            Self::InvalidImmediate => write!(f, "expected a 32-bit immediate"),
+            Self::ExpectedMemoryOperand => write!(f, "expected a memory operand `offset(rs)`"),
+            Self::InvalidLabel => write!(f, "invalid label"),
+            Self::DuplicateLabel(name) => write!(f, "label `{name}` is already defined"),
+            Self::UnknownLabel(name) => write!(f, "unknown label `{name}`"),
+        }
+    }
+}
+
+impl core::error::Error for AsmError {}
+
+/// A piece of a source line, with the 1-based column it starts at.
+#[derive(Clone, Copy)]
+struct Token<'a> {
+    text: &'a str,
+    column: usize,
+}
+
+impl<'a> Token<'a> {
+    /// The token for `text`, a subslice of `line`, with surrounding whitespace trimmed.
+    fn new(line: &str, text: &'a str) -> Self {
+        let text = text.trim();
+        let offset = text.as_ptr() as usize - line.as_ptr() as usize;
+        Self {
+            text,
+            column: line[..offset].chars().count() + 1,
+        }
+    }
+}
+
+/// Parse `source` into a program.
+///
+/// Each line holds at most one instruction, optionally preceded by labels such as `loop:`.
+/// Comments run from `;` or `#` to the end of the line, and mnemonics are case-insensitive:
+///
+/// ```text
+///         loadi r1, 10        ; r1 = 10
+/// loop:   add r0, r0, r1      ; r0 = r0 + r1
+///         addi r1, r1, -1     ; r1 = r1 - 1
+///         bne r1, r2, loop    ; repeat while r1 != r2
+///         store r0, 8(r2)     ; mem[r2 + 8] = r0
+///         load r3, 8(r2)      ; r3 = mem[r2 + 8]
+/// ```
+///
+/// | Syntax | Instruction |
+/// |---|---|
+/// | `add rd, rs1, rs2`, likewise `sub`, `mul`, `and`, `or`, `xor`, `sll`, `srl`, `sra`, `eq`, `lt`, `slt`, `divu`, `remu` | `rd = rs1 op rs2` |
+/// | `not rd, rs` | `rd = !rs` |
+/// | `addi rd, rs, imm`, likewise `slli`, `srli`, `srai` | `rd = rs op imm` |
+/// | `loadi rd, imm` | `rd = imm` |
+/// | `mov rd, rs` | `rd = rs` |
+/// | `jmp target` | `pc = target` |
+/// | `beq rs1, rs2, target`, likewise `bne`, `blt`, `bge`, `bltu`, `bgeu` | `pc = target` if `rs1 == rs2` |
+/// | `load rd, imm(rs)` | `rd = mem[rs + imm]` |
+/// | `store rs2, imm(rs1)` | `mem[rs1 + imm] = rs2` |
+/// | `nop` | |
+/// | `.inst op, rd, rs1, rs2, imm` | the instruction `op` with every field given |
+///
+/// The `.inst` form writes any instruction, including one whose unused fields are set, which
+/// the other forms cannot express.
+///
+/// Immediates are decimal or `0x` hexadecimal, and negative immediates stand for their two's
+/// complement. A branch target is a label or an instruction index, and a label after the last
+/// instruction stands for the end of the program.
+pub fn assemble(source: &str) -> Result<Vec<Instruction>, AsmError> {
+    let mut program = Vec::new();
+    let mut labels = BTreeMap::new();
+    // Branches to labels, resolved once every label is known.
+    let mut fixups = Vec::new();
+
+    for (line_idx, line) in source.lines().enumerate() {
+        let line_number = line_idx + 1;
+        let error = |token: Token<'_>, kind| AsmError {
+            line: line_number,
+            column: token.column,
+            kind,
+        };
+        let mut code = line.find([';', '#']).map_or(line, |i| &line[..i]);
+
+        while let Some(colon) = code.find(':') {
+            let label = Token::new(line, &code[..colon]);
+            if !is_identifier(label.text) {
+                return Err(error(label, AsmErrorKind::InvalidLabel));
+            }
+            if labels.insert(label.text, program.len()).is_some() {
+                let kind = AsmErrorKind::DuplicateLabel(label.text.to_string());
+                return Err(error(label, kind));
+            }
+            code = &code[colon + 1..];
+        }
+
+        let code = code.trim();
+        if code.is_empty() {
+            continue;
+        }
+        let (mnemonic, operands) = code.split_once(char::is_whitespace).unwrap_or((code, ""));
+        let mnemonic = Token::new(line, mnemonic);
+        let operands: Vec<Token<'_>> = if operands.trim().is_empty() {
+            Vec::new()
+        } else {
+            operands.split(',').map(|op| Token::new(line, op)).collect()
+        };
+
+        let check_count = |expected: usize| {
+            if operands.len() == expected {
+                return Ok(());
+            }
+            let kind = AsmErrorKind::WrongOperandCount {
+                expected,
+                found: operands.len(),
+            };
+            Err(error(mnemonic, kind))
+        };
+        let reg = |i: usize| parse_register(operands[i].text).map_err(|k| error(operands[i], k));
+        let imm = |i: usize| parse_immediate(operands[i].text).map_err(|k| error(operands[i], k));
+        let mem = |i: usize| parse_memory(operands[i].text).map_err(|k| error(operands[i], k));
+
+        if mnemonic.text.eq_ignore_ascii_case(RAW_DIRECTIVE) {
+            check_count(5)?;
+            let op = parse_opcode(operands[0].text).map_err(|k| error(operands[0], k))?;
+            program.push(Instruction {
+                op,
+                dest: reg(1)?,
+                src1: reg(2)?,
+                src2: reg(3)?,
+                imm: imm(4)?,
+            });
+            continue;
+        }
+
+        let op = parse_opcode(mnemonic.text).map_err(|k| error(mnemonic, k))?;
+        check_count(operand_count(op))?;
+
+        let mut target = |i: usize| -> Result<u32, AsmError> {
+            let operand = operands[i];
+            if operand.text.starts_with(|c: char| c.is_ascii_digit()) {
+                imm(i)
+            } else if is_identifier(operand.text) {
+                fixups.push((program.len(), line_number, operand));
+                Ok(0)
+            } else {
+                let kind = AsmErrorKind::UnknownLabel(operand.text.to_string());
+                Err(error(operand, kind))
+            }
+        };
+
+        let inst = match op {
+            Opcode::NOT => Instruction::not(reg(0)?, reg(1)?),
+            Opcode::ADDI | Opcode::SLLI | Opcode::SRLI | Opcode::SRAI => {
+                Instruction::with_imm(op, reg(0)?, reg(1)?, imm(2)?)
+            }
+            Opcode::LOADI => Instruction::loadi(reg(0)?, imm(1)?),
+            Opcode::MOV => Instruction::mov(reg(0)?, reg(1)?),
+            Opcode::JMP => Instruction::jmp(target(0)?),
+            op if op.is_branch() => Instruction::branch(op, reg(0)?, reg(1)?, target(2)?),
+            Opcode::LOAD => {
+                let (offset, base) = mem(1)?;
+                Instruction::load(reg(0)?, base, offset)
+            }
+            Opcode::STORE => {
+                let (offset, base) = mem(1)?;
+                Instruction::store(base, reg(0)?, offset)
+            }
+            Opcode::NOP => Instruction::nop(),
+            op => Instruction::new(op, reg(0)?, reg(1)?, reg(2)?),
+        };
+        program.push(inst);
+    }
+
+    for (pc, line, operand) in fixups {
+        let target = labels.get(operand.text).ok_or_else(|| AsmError {
+            line,
+            column: operand.column,
+            kind: AsmErrorKind::UnknownLabel(operand.text.to_string()),
+        })?;
+        program[pc].imm = *target as u32;
+    }
+    Ok(program)
+}
+
+/// The directive writing an instruction with every field given.
+const RAW_DIRECTIVE: &str = ".inst";
+
+/// The number of operands `op` takes in assembly.
+const fn operand_count(op: Opcode) -> usize {
+    match op {
+        Opcode::NOP => 0,
+        Opcode::JMP => 1,
+        Opcode::NOT | Opcode::LOADI | Opcode::MOV | Opcode::LOAD | Opcode::STORE => 2,
+        _ => 3,
+    }
+}
+
+fn is_identifier(text: &str) -> bool {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == '.')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+fn parse_opcode(text: &str) -> Result<Opcode, AsmErrorKind> {
+    Opcode::ALL
+        .into_iter()
+        .find(|op| op.mnemonic().eq_ignore_ascii_case(text))
+        .ok_or_else(|| AsmErrorKind::UnknownMnemonic(text.to_string()))
+}
+
+fn parse_register(text: &str) -> Result<usize, AsmErrorKind> {
+    text.strip_prefix(['r', 'R'])
+        .filter(|index| !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit()))
+        .and_then(|index| index.parse().ok())
+        .ok_or(AsmErrorKind::ExpectedRegister)
+}
+
+fn parse_immediate(text: &str) -> Result<u32, AsmErrorKind> {
+    let (negative, magnitude) = match text.strip_prefix('-') {
+        Some(magnitude) => (true, magnitude),
+        None => (false, text),
+    };
+    let magnitude = match magnitude
+        .strip_prefix("0x")
+        .or_else(|| magnitude.strip_prefix("0X"))
+    {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => magnitude.parse(),
+    }
+    .map_err(|_| AsmErrorKind::InvalidImmediate)?;
+    match negative {
+        false => Ok(magnitude),
+        true if magnitude <= 1 << 31 => Ok(magnitude.wrapping_neg()),
+        true => Err(AsmErrorKind::InvalidImmediate),
+    }
+}
+
+/// Parse `offset(rs)` into the offset and the register. The offset may be omitted.
+fn parse_memory(text: &str) -> Result<(u32, usize), AsmErrorKind> {
+    let (offset, base) = text
+        .strip_suffix(')')
+        .and_then(|text| text.split_once('('))
+        .ok_or(AsmErrorKind::ExpectedMemoryOperand)?;
+    let offset = match offset.trim() {
+        "" => 0,
+        offset => parse_immediate(offset)?,
+    };
+    Ok((offset, parse_register(base.trim())?))
+}
+
+/// The instruction [`assemble`] produces from the mnemonic form of `inst`, which leaves out the
+/// fields `inst.op` does not use.
+fn canonical(inst: &Instruction) -> Instruction {
+    let (op, d, s1, s2, imm) = (inst.op, inst.dest, inst.src1, inst.src2, inst.imm);
+    match op {
+        Opcode::NOT => Instruction::not(d, s1),
+        Opcode::ADDI | Opcode::SLLI | Opcode::SRLI | Opcode::SRAI => {
+            Instruction::with_imm(op, d, s1, imm)
+        }
+        Opcode::LOADI => Instruction::loadi(d, imm),
+        Opcode::MOV => Instruction::mov(d, s1),
+        Opcode::JMP => Instruction::jmp(imm),
+        op if op.is_branch() => Instruction::branch(op, s1, s2, imm),
+        Opcode::LOAD => Instruction::load(d, s1, imm),
+        Opcode::STORE => Instruction::store(s1, s2, imm),
+        Opcode::NOP => Instruction::nop(),
+        op => Instruction::new(op, d, s1, s2),
+    }
+}
+
+/// Write `inst` in assembly, with `target` as its branch target. An instruction with unused
+/// fields set is written with [`RAW_DIRECTIVE`], so that it assembles back to itself.
+fn write_instruction(
+    f: &mut impl fmt::Write,
+    inst: &Instruction,
+    target: impl fmt::Display,
+) -> fmt::Result {
+    let (d, s1, s2) = (inst.dest, inst.src1, inst.src2);
+    // Offsets read better signed, e.g. `addi r1, r1, -1`.
+    let offset = inst.imm as i32;
+    let mnemonic = inst.op.mnemonic();
+    if *inst != canonical(inst) {
+        return write!(
+            f,
+            "{RAW_DIRECTIVE} {mnemonic}, r{d}, r{s1}, r{s2}, {}",
+            inst.imm
+        );
+    }
+    match inst.op {
+        Opcode::NOT | Opcode::MOV => write!(f, "{mnemonic} r{d}, r{s1}"),
+        Opcode::ADDI | Opcode::SLLI | Opcode::SRLI | Opcode::SRAI => {
+            write!(f, "{mnemonic} r{d}, r{s1}, {offset}")
+        }
+        Opcode::LOADI => write!(f, "{mnemonic} r{d}, {}", inst.imm),
+        Opcode::JMP => write!(f, "{mnemonic} {target}"),
+        op if op.is_branch() => write!(f, "{mnemonic} r{s1}, r{s2}, {target}"),
+        Opcode::LOAD => write!(f, "{mnemonic} r{d}, {offset}(r{s1})"),
+        Opcode::STORE => write!(f, "{mnemonic} r{s2}, {offset}(r{s1})"),
+        Opcode::NOP => write!(f, "{mnemonic}"),
+        _ => write!(f, "{mnemonic} r{d}, r{s1}, r{s2}"),
+    }
+}
+
+/// Writes the instruction in the syntax of [`assemble`], with a branch target as an instruction
+/// index.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_instruction(f, self, self.imm)
+    }
+}
+
+/// Write `program` in the syntax of [`assemble`], one indented instruction per line. Branch
+/// targets within the program, or at its end, get labels `L<pc>` on lines of their own.
+pub fn disassemble(program: &[Instruction]) -> String {
+    let is_label = |pc: usize| pc <= program.len();
+    let mut targets: Vec<usize> = program
+        .iter()
+        .filter(|inst| inst.op.is_branch())
+        .map(|inst| inst.imm as usize)
+        .filter(|&pc| is_label(pc))
+        .collect();
+    targets.sort_unstable();
+    targets.dedup();
+
+    let mut text = String::new();
+    let mut targets = targets.into_iter().peekable();
+    for pc in 0..=program.len() {
+        if targets.next_if_eq(&pc).is_some() {
+            text += &format!("L{pc}:\n");
+        }
+        if let Some(inst) = program.get(pc) {
+            let target = inst.imm as usize;
+            let target = if is_label(target) {
+                format!("L{target}")
+            } else {
+                target.to_string()
+            };
+            text += "    ";
+            write_instruction(&mut text, inst, target).expect("writing to a String cannot fail");
+            text += "\n";
+        }
+    }
+    text
+}
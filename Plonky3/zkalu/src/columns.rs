@@ -0,0 +1,291 @@
+use core::borrow::{Borrow, BorrowMut};
+use core::mem::size_of;
+
+use p3_field::PrimeCharacteristicRing;
+
+use crate::{NUM_OPCODES, NUM_REGISTERS};
+
+/// Number of 16-bit limbs in a 32-bit word.
+pub const NUM_LIMBS: usize = 2;
+
+/// Split a 32-bit word into little-endian 16-bit limbs.
+pub fn u32_to_limbs<F: PrimeCharacteristicRing>(val: u32) -> [F; NUM_LIMBS] {
+    [F::from_u16(val as u16), F::from_u16((val >> 16) as u16)]
+}
+
+/// Number of bits in the carry out of the low limb of a `MUL`. The carry is at most 509, or 510
+/// for the `q * src2 + r` of a division.
+pub const MUL_CARRY_LO_BITS: usize = 9;
+
+/// Number of bits in the carry out of the high limb of a `MUL`. The carry is at most 1019.
+pub const MUL_CARRY_HI_BITS: usize = 10;
+
+/// Number of bits in the range check ordering consecutive rows of the memory table. The
+/// increases it bounds are below `2^16` for an address limb and below the trace height for a clock.
+pub const MEMORY_DELTA_BITS: usize = 24;
+
+/// Columns for the ALU chip with `NUM_REGS` registers. Each row holds the register state before
+/// an instruction is applied together with the program counter, the instruction word and the
+/// one-hot selectors it decodes to.
+///
+/// A `NOP`, `STORE` or branch row has all destination selectors zero, so no register is written.
+///
+/// Every 32-bit word is stored as little-endian 16-bit limbs. `src1_val` and `src2_val` hold the
+/// source operands (`src2_val` is `imm` for the opcodes taking an immediate) and `result` the
+/// value computed by the instruction; their bit decompositions range check the result, give `MUL`
+/// access to the bytes of its operands and let the bitwise and shift operations act bit by bit.
+///
+/// `mem` is an independent row of the memory table, which lists the `LOAD`s and `STORE`s of the
+/// whole trace sorted by address; see [`MemoryRow`].
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct AluRow<T, const NUM_REGS: usize = NUM_REGISTERS> {
+    // Register values.
+    pub regs: [[T; NUM_LIMBS]; NUM_REGS],
+    // Program counter: the index of this row's instruction in the program.
+    pub pc: T,
+    // Clock: the index of this row in the trace, timestamping its memory access.
+    pub clk: T,
+    // The instruction word, see `Instruction::encode`, which the selectors below decode.
+    pub inst: T,
+    // Destination selectors (one-hot).
+    pub dest: [T; NUM_REGS],
+    // Source1 selectors (one-hot).
+    pub src1: [T; NUM_REGS],
+    // Source2 selectors (one-hot).
+    pub src2: [T; NUM_REGS],
+    // Operation selectors (one-hot).
+    pub op_add: T,
+    pub op_sub: T,
+    pub op_mul: T,
+    pub op_and: T,
+    pub op_or: T,
+    pub op_xor: T,
+    pub op_not: T,
+    pub op_sll: T,
+    pub op_srl: T,
+    pub op_sra: T,
+    pub op_eq: T,
+    pub op_lt: T,
+    pub op_slt: T,
+    pub op_divu: T,
+    pub op_remu: T,
+    pub op_addi: T,
+    pub op_slli: T,
+    pub op_srli: T,
+    pub op_srai: T,
+    pub op_loadi: T,
+    pub op_mov: T,
+    pub op_jmp: T,
+    pub op_beq: T,
+    pub op_bne: T,
+    pub op_blt: T,
+    pub op_bge: T,
+    pub op_bltu: T,
+    pub op_bgeu: T,
+    pub op_load: T,
+    pub op_store: T,
+    pub op_nop: T,
+    // The instruction's immediate operand, or the target pc of a branch.
+    pub imm: [T; NUM_LIMBS],
+    // Operands and result.
+    pub src1_val: [T; NUM_LIMBS],
+    pub src2_val: [T; NUM_LIMBS],
+    pub result: [T; NUM_LIMBS],
+    // Little-endian bit decompositions of the operands and result.
+    pub src1_bits: [T; 32],
+    pub src2_bits: [T; 32],
+    pub result_bits: [T; 32],
+    // Little-endian bits of the carries out of each limb of a `MUL`, or of `q * src2 + r` for
+    // `DIVU` and `REMU`.
+    pub mul_carry_lo: [T; MUL_CARRY_LO_BITS],
+    pub mul_carry_hi: [T; MUL_CARRY_HI_BITS],
+    // Comparison witnesses: `src1 - src2 mod 2^32` (with the sign bits flipped for `SLT`, `BLT`
+    // and `BGE`), its bits, the borrow out of each limb and the inverse of `diff[0] + diff[1]` if
+    // nonzero. The conditional branches use them to compare their operands, and `LOAD` and
+    // `STORE` hold their address `src1 + imm` in `diff`.
+    pub diff: [T; NUM_LIMBS],
+    pub diff_bits: [T; 32],
+    pub borrow: [T; NUM_LIMBS],
+    pub diff_inv: T,
+    // Division witnesses: the quotient and remainder with their bits, the inverse of
+    // `src2_val[0] + src2_val[1]` if nonzero and a flag set when dividing by zero.
+    pub quotient: [T; NUM_LIMBS],
+    pub quotient_bits: [T; 32],
+    pub remainder: [T; NUM_LIMBS],
+    pub remainder_bits: [T; 32],
+    pub src2_inv: T,
+    pub div_by_zero: T,
+    // Shift witness: the shift amount of a shift, one-hot.
+    pub shift: [T; 32],
+    // How often the trace fetches the ROM row with this row's index, for `RomAluChip`.
+    pub rom_multiplicity: T,
+    // A row of the memory table.
+    pub mem: MemoryRow<T>,
+}
+
+/// Number of columns in the ALU trace for `NUM_REGS` registers: one per field of [`AluRow`], so
+/// the registers and their selectors grow with `NUM_REGS` while the decoded instruction, the
+/// operands, the witnesses of each operation and the memory table row are fixed.
+pub const fn num_alu_cols<const NUM_REGS: usize>() -> usize {
+    size_of::<AluRow<u8, NUM_REGS>>()
+}
+
+/// Number of columns in the ALU trace for the default [`NUM_REGISTERS`] registers.
+pub const NUM_ALU_COLS: usize = num_alu_cols::<NUM_REGISTERS>();
+
+impl<T: Clone, const NUM_REGS: usize> AluRow<T, NUM_REGS> {
+    /// The operation selectors, indexed by [`Opcode`](crate::Opcode).
+    pub fn ops(&self) -> [T; NUM_OPCODES] {
+        [
+            self.op_add.clone(),
+            self.op_sub.clone(),
+            self.op_mul.clone(),
+            self.op_and.clone(),
+            self.op_or.clone(),
+            self.op_xor.clone(),
+            self.op_not.clone(),
+            self.op_sll.clone(),
+            self.op_srl.clone(),
+            self.op_sra.clone(),
+            self.op_eq.clone(),
+            self.op_lt.clone(),
+            self.op_slt.clone(),
+            self.op_divu.clone(),
+            self.op_remu.clone(),
+            self.op_addi.clone(),
+            self.op_slli.clone(),
+            self.op_srli.clone(),
+            self.op_srai.clone(),
+            self.op_loadi.clone(),
+            self.op_mov.clone(),
+            self.op_jmp.clone(),
+            self.op_beq.clone(),
+            self.op_bne.clone(),
+            self.op_blt.clone(),
+            self.op_bge.clone(),
+            self.op_bltu.clone(),
+            self.op_bgeu.clone(),
+            self.op_load.clone(),
+            self.op_store.clone(),
+            self.op_nop.clone(),
+        ]
+    }
+}
+
+impl<T, const NUM_REGS: usize> AluRow<T, NUM_REGS> {
+    /// Mutable references to the operation selectors, indexed by [`Opcode`](crate::Opcode).
+    pub fn ops_mut(&mut self) -> [&mut T; NUM_OPCODES] {
+        [
+            &mut self.op_add,
+            &mut self.op_sub,
+            &mut self.op_mul,
+            &mut self.op_and,
+            &mut self.op_or,
+            &mut self.op_xor,
+            &mut self.op_not,
+            &mut self.op_sll,
+            &mut self.op_srl,
+            &mut self.op_sra,
+            &mut self.op_eq,
+            &mut self.op_lt,
+            &mut self.op_slt,
+            &mut self.op_divu,
+            &mut self.op_remu,
+            &mut self.op_addi,
+            &mut self.op_slli,
+            &mut self.op_srli,
+            &mut self.op_srai,
+            &mut self.op_loadi,
+            &mut self.op_mov,
+            &mut self.op_jmp,
+            &mut self.op_beq,
+            &mut self.op_bne,
+            &mut self.op_blt,
+            &mut self.op_bge,
+            &mut self.op_bltu,
+            &mut self.op_bgeu,
+            &mut self.op_load,
+            &mut self.op_store,
+            &mut self.op_nop,
+        ]
+    }
+}
+
+impl<T, const NUM_REGS: usize> Borrow<AluRow<T, NUM_REGS>> for [T] {
+    fn borrow(&self) -> &AluRow<T, NUM_REGS> {
+        debug_assert_eq!(self.len(), num_alu_cols::<NUM_REGS>());
+        let (prefix, shorts, suffix) = unsafe { self.align_to::<AluRow<T, NUM_REGS>>() };
+        debug_assert!(prefix.is_empty(), "Alignment should match");
+        debug_assert!(suffix.is_empty(), "Alignment should match");
+        debug_assert_eq!(shorts.len(), 1);
+        &shorts[0]
+    }
+}
+
+impl<T, const NUM_REGS: usize> BorrowMut<AluRow<T, NUM_REGS>> for [T] {
+    fn borrow_mut(&mut self) -> &mut AluRow<T, NUM_REGS> {
+        debug_assert_eq!(self.len(), num_alu_cols::<NUM_REGS>());
+        let (prefix, shorts, suffix) = unsafe { self.align_to_mut::<AluRow<T, NUM_REGS>>() };
+        debug_assert!(prefix.is_empty(), "Alignment should match");
+        debug_assert!(suffix.is_empty(), "Alignment should match");
+        debug_assert_eq!(shorts.len(), 1);
+        &mut shorts[0]
+    }
+}
+
+/// Columns of the memory table: one memory access per row, sorted by address and then by clock,
+/// followed by padding rows with `is_real = 0`.
+///
+/// `same_addr_hi` and `same_addr` record whether the access has the same high address limb, or
+/// the same address, as the one on the previous row. `delta_bits` range check the amount by which
+/// the access increases `(addr[1], addr[0], clk)` over the previous one, minus one: the increase of
+/// `clk` within an address, of `addr[0]` within a high limb and of `addr[1]` otherwise.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct MemoryRow<T> {
+    pub addr: [T; NUM_LIMBS],
+    pub clk: T,
+    pub value: [T; NUM_LIMBS],
+    pub is_write: T,
+    pub is_real: T,
+    pub same_addr_hi: T,
+    pub same_addr: T,
+    pub delta_bits: [T; MEMORY_DELTA_BITS],
+}
+
+/// Columns of the program ROM: an instruction's address together with its encoding, see
+/// [`Instruction::encode`](crate::Instruction::encode). The trace row executing the instruction
+/// holds the same instruction word and immediate, and decodes the word into its selectors.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct RomRow<T> {
+    pub pc: T,
+    pub inst: T,
+    pub imm: [T; NUM_LIMBS],
+}
+
+/// Number of columns in the program ROM.
+pub const NUM_ROM_COLS: usize = size_of::<RomRow<u8>>();
+
+impl<T> Borrow<RomRow<T>> for [T] {
+    fn borrow(&self) -> &RomRow<T> {
+        debug_assert_eq!(self.len(), NUM_ROM_COLS);
+        let (prefix, shorts, suffix) = unsafe { self.align_to::<RomRow<T>>() };
+        debug_assert!(prefix.is_empty(), "Alignment should match");
+        debug_assert!(suffix.is_empty(), "Alignment should match");
+        debug_assert_eq!(shorts.len(), 1);
+        &shorts[0]
+    }
+}
+
+impl<T> BorrowMut<RomRow<T>> for [T] {
+    fn borrow_mut(&mut self) -> &mut RomRow<T> {
+        debug_assert_eq!(self.len(), NUM_ROM_COLS);
+        let (prefix, shorts, suffix) = unsafe { self.align_to_mut::<RomRow<T>>() };
+        debug_assert!(prefix.is_empty(), "Alignment should match");
+        debug_assert!(suffix.is_empty(), "Alignment should match");
+        debug_assert_eq!(shorts.len(), 1);
+        &mut shorts[0]
+    }
+}
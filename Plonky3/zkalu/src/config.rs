@@ -0,0 +1,56 @@
+//! A `StarkConfig` preset over BabyBear using Poseidon2 for hashing and Fiat-Shamir.
+
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::Field;
+use p3_field::extension::BinomialExtensionField;
+use p3_fri::{FriParameters, TwoAdicFriPcs, create_benchmark_fri_params, create_test_fri_params};
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_uni_stark::StarkConfig;
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+pub type Val = BabyBear;
+pub type Challenge = BinomialExtensionField<Val, 4>;
+pub type Perm = Poseidon2BabyBear<16>;
+pub type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+pub type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+pub type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+pub type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+pub type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+pub type Dft = Radix2DitParallel<Val>;
+pub type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+pub type AluConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+/// The seed from which the Poseidon2 round constants are derived.
+///
+/// Prover and verifier must agree on the permutation, so this is fixed rather than random.
+pub const PERM_SEED: u64 = 42;
+
+/// A configuration with FRI parameters suitable for benchmarking and production-like use.
+pub fn alu_config() -> AluConfig {
+    make_config(create_benchmark_fri_params)
+}
+
+/// A cheap configuration with minimal FRI parameters, for tests and demos only.
+pub fn alu_test_config() -> AluConfig {
+    make_config(|mmcs| create_test_fri_params(mmcs, 0))
+}
+
+fn make_config(
+    fri_params: impl FnOnce(ChallengeMmcs) -> FriParameters<ChallengeMmcs>,
+) -> AluConfig {
+    let mut rng = SmallRng::seed_from_u64(PERM_SEED);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let pcs = Pcs::new(Dft::default(), val_mmcs, fri_params(challenge_mmcs));
+    let challenger = Challenger::new(perm);
+    AluConfig::new(pcs, challenger)
+}
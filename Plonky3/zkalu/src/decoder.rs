@@ -0,0 +1,98 @@
+//! The instruction decoder: the selector columns of a row are the decoding of its instruction word.
+//!
+//! Each row holds the instruction word of [`Instruction::encode`] in `inst`. Given one-hot
+//! selectors, `sum_i i * op_i` is the index of the selected opcode and `sum_r r * dest_r` that of
+//! the selected register, so the word is recomposed from the selectors as
+//! `sum_i i * op_i + 2^5 * sum_r r * dest_r + 2^10 * sum_r r * src1_r + 2^15 * sum_r r * src2_r`.
+//! Every field is below `2^5`, so the word determines the selectors. The constraints of the
+//! instruction itself only read the selectors, and fixing the word, as the ROM of a
+//! [`RomAluChip`](crate::RomAluChip) does, fixes the instruction.
+
+use p3_air::AirBuilder;
+use p3_field::{Field, PrimeCharacteristicRing};
+
+use crate::columns::{AluRow, u32_to_limbs};
+use crate::generation::one_hot;
+use crate::{FIELD_BITS, FIELD_SHIFTS, Instruction};
+
+/// Fill the instruction word, selector and immediate columns of `row` for `inst`, decoding the
+/// selectors from the word.
+pub(crate) fn generate_decoded_row<F: Field, const NUM_REGS: usize>(
+    row: &mut AluRow<F, NUM_REGS>,
+    inst: &Instruction,
+) {
+    let words = inst.encode();
+    let decoded = Instruction::decode(words).expect("an encoded instruction always decodes");
+    row.inst = F::from_u32(words[0]);
+    // Opcodes which write no register encode dest as zero and have no destination selector.
+    if decoded.op.writes_register() {
+        row.dest = one_hot(decoded.dest);
+    }
+    row.src1 = one_hot(decoded.src1);
+    row.src2 = one_hot(decoded.src2);
+    for (i, op) in row.ops_mut().into_iter().enumerate() {
+        *op = F::from_bool(i == decoded.op as usize);
+    }
+    row.imm = u32_to_limbs(decoded.imm);
+}
+
+pub(crate) fn eval_decoder<AB: AirBuilder, const NUM_REGS: usize>(
+    builder: &mut AB,
+    local: &AluRow<AB::Var, NUM_REGS>,
+) {
+    const {
+        assert!(
+            NUM_REGS <= 1 << FIELD_BITS,
+            "An instruction word cannot name more than 32 registers"
+        );
+    }
+
+    // Every selector is boolean and the operation and source selectors are one-hot.
+    builder.label("opcode one-hot");
+    let ops = local.ops();
+    builder.assert_bools(ops.clone());
+    builder.assert_one(ops.iter().cloned().map(Into::into).sum::<AB::Expr>());
+    builder.label("source one-hot");
+    for group in [&local.src1, &local.src2] {
+        builder.assert_bools(group.clone());
+        builder.assert_one(group.iter().cloned().map(Into::into).sum::<AB::Expr>());
+    }
+
+    // The destination selectors are one-hot unless this is a NOP, a STORE or a branch, in which
+    // case they are all zero and every register keeps its value.
+    builder.label("dest one-hot");
+    let is_branch = local.op_jmp.clone()
+        + local.op_beq.clone()
+        + local.op_bne.clone()
+        + local.op_blt.clone()
+        + local.op_bge.clone()
+        + local.op_bltu.clone()
+        + local.op_bgeu.clone();
+    builder.assert_bools(local.dest.clone());
+    builder.assert_eq(
+        local.dest.iter().cloned().map(Into::into).sum::<AB::Expr>(),
+        AB::Expr::ONE - local.op_nop.clone() - local.op_store.clone() - is_branch,
+    );
+
+    // The instruction word is the recomposition of its fields from the selectors.
+    builder.label("instruction word");
+    let index = |selectors: &[AB::Var]| -> AB::Expr {
+        selectors
+            .iter()
+            .enumerate()
+            .map(|(i, s)| s.clone() * AB::F::from_usize(i))
+            .sum()
+    };
+    let fields = [
+        index(&ops),
+        index(&local.dest),
+        index(&local.src1),
+        index(&local.src2),
+    ];
+    let word = fields
+        .into_iter()
+        .zip(FIELD_SHIFTS)
+        .map(|(field, shift)| field.mul_2exp_u64(shift as u64))
+        .sum::<AB::Expr>();
+    builder.assert_eq(local.inst.clone(), word);
+}
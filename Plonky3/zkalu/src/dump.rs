@@ -0,0 +1,179 @@
+//! Exporting ALU traces for debugging: named columns, CSV, JSON and a per-row pretty-printer.
+//!
+//! Columns are named after the fields of [`AluRow`]: a scalar column keeps its field name, the
+//! two limbs of a word get the suffixes `_lo` and `_hi` (`r0_lo`, `imm_hi`), the columns of any
+//! other array are numbered (`dest_0`, `src1_bits_31`), the operation selectors are named after
+//! their opcode (`op_add`) and the memory table columns are prefixed with `mem_`.
+//!
+//! Each exported row also holds its instruction, decoded from the row's own instruction word and
+//! immediate columns rather than taken from the program, so a tampered row shows what it claims
+//! to execute.
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::{Borrow, BorrowMut};
+use core::fmt::Write;
+
+use p3_field::PrimeField64;
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::columns::{AluRow, NUM_LIMBS, num_alu_cols};
+use crate::{AluChip, Instruction, Opcode};
+
+fn name_word(limbs: &mut [String; NUM_LIMBS], name: &str) {
+    let [lo, hi] = limbs;
+    *lo = format!("{name}_lo");
+    *hi = format!("{name}_hi");
+}
+
+fn name_array(columns: &mut [String], name: &str) {
+    for (i, column) in columns.iter_mut().enumerate() {
+        *column = format!("{name}_{i}");
+    }
+}
+
+/// The instruction encoded by the `inst` and `imm` columns of `row`, or `None` if they are not
+/// the encoding of an instruction.
+fn row_instruction<F: PrimeField64, const NUM_REGS: usize>(
+    row: &AluRow<F, NUM_REGS>,
+) -> Option<Instruction> {
+    let word = u32::try_from(row.inst.as_canonical_u64()).ok()?;
+    let [lo, hi] = row
+        .imm
+        .map(|limb| u16::try_from(limb.as_canonical_u64()).ok());
+    let imm = (hi? as u32) << 16 | lo? as u32;
+    Instruction::decode([word, imm])
+}
+
+impl<const NUM_REGS: usize> AluChip<NUM_REGS> {
+    /// The names of the trace columns, in column order.
+    pub fn column_names() -> Vec<String> {
+        let mut names = vec![String::new(); num_alu_cols::<NUM_REGS>()];
+        let row: &mut AluRow<String, NUM_REGS> = names.as_mut_slice().borrow_mut();
+        for (r, limbs) in row.regs.iter_mut().enumerate() {
+            name_word(limbs, &format!("r{r}"));
+        }
+        row.pc = "pc".to_string();
+        row.clk = "clk".to_string();
+        row.inst = "inst".to_string();
+        name_array(&mut row.dest, "dest");
+        name_array(&mut row.src1, "src1");
+        name_array(&mut row.src2, "src2");
+        for (op, column) in Opcode::ALL.into_iter().zip(row.ops_mut()) {
+            *column = format!("op_{}", op.mnemonic());
+        }
+        name_word(&mut row.imm, "imm");
+        name_word(&mut row.src1_val, "src1_val");
+        name_word(&mut row.src2_val, "src2_val");
+        name_word(&mut row.result, "result");
+        name_array(&mut row.src1_bits, "src1_bits");
+        name_array(&mut row.src2_bits, "src2_bits");
+        name_array(&mut row.result_bits, "result_bits");
+        name_array(&mut row.mul_carry_lo, "mul_carry_lo");
+        name_array(&mut row.mul_carry_hi, "mul_carry_hi");
+        name_word(&mut row.diff, "diff");
+        name_array(&mut row.diff_bits, "diff_bits");
+        name_word(&mut row.borrow, "borrow");
+        row.diff_inv = "diff_inv".to_string();
+        name_word(&mut row.quotient, "quotient");
+        name_array(&mut row.quotient_bits, "quotient_bits");
+        name_word(&mut row.remainder, "remainder");
+        name_array(&mut row.remainder_bits, "remainder_bits");
+        row.src2_inv = "src2_inv".to_string();
+        row.div_by_zero = "div_by_zero".to_string();
+        name_array(&mut row.shift, "shift");
+        row.rom_multiplicity = "rom_multiplicity".to_string();
+        let mem = &mut row.mem;
+        name_word(&mut mem.addr, "mem_addr");
+        mem.clk = "mem_clk".to_string();
+        name_word(&mut mem.value, "mem_value");
+        mem.is_write = "mem_is_write".to_string();
+        mem.is_real = "mem_is_real".to_string();
+        mem.same_addr_hi = "mem_same_addr_hi".to_string();
+        mem.same_addr = "mem_same_addr".to_string();
+        name_array(&mut mem.delta_bits, "mem_delta_bits");
+        names
+    }
+
+    /// Write `trace` as CSV: a header of `row`, `instruction` and the column names, then one line
+    /// per row with the canonical value of every column. The instruction is left empty on rows
+    /// whose columns do not encode one.
+    pub fn trace_to_csv<F: PrimeField64>(trace: &RowMajorMatrix<F>) -> String {
+        let mut csv = String::from("row,instruction");
+        for name in Self::column_names() {
+            csv.push(',');
+            csv.push_str(&name);
+        }
+        csv.push('\n');
+        for (i, values) in trace.row_slices().enumerate() {
+            let row: &AluRow<F, NUM_REGS> = values.borrow();
+            let inst = row_instruction(row).map_or_else(String::new, |inst| format!("\"{inst}\""));
+            write!(csv, "{i},{inst}").unwrap();
+            for value in values {
+                write!(csv, ",{}", value.as_canonical_u64()).unwrap();
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Write `trace` as a JSON array with one object per row, holding `row`, `instruction` and
+    /// the canonical value of every column by name. The instruction is `null` on rows whose
+    /// columns do not encode one.
+    pub fn trace_to_json<F: PrimeField64>(trace: &RowMajorMatrix<F>) -> String {
+        let names = Self::column_names();
+        let mut json = String::from("[");
+        for (i, values) in trace.row_slices().enumerate() {
+            let row: &AluRow<F, NUM_REGS> = values.borrow();
+            let inst =
+                row_instruction(row).map_or_else(|| "null".into(), |inst| format!("\"{inst}\""));
+            let separator = if i == 0 { "" } else { "," };
+            write!(
+                json,
+                "{separator}\n  {{\"row\": {i}, \"instruction\": {inst}"
+            )
+            .unwrap();
+            for (name, value) in names.iter().zip(values) {
+                write!(json, ", \"{name}\": {}", value.as_canonical_u64()).unwrap();
+            }
+            json.push('}');
+        }
+        json.push_str("\n]\n");
+        json
+    }
+
+    /// Describe row `i` of `trace` for debugging: its instruction, registers and pc, followed by
+    /// every other nonzero column, one per line.
+    pub fn format_row<F: PrimeField64>(trace: &RowMajorMatrix<F>, i: usize) -> String {
+        let values = trace.row_slice(i).expect("row index out of bounds");
+        let row: &AluRow<F, NUM_REGS> = (*values).borrow();
+        let inst = row_instruction(row)
+            .map_or_else(|| "<invalid instruction>".into(), |inst| inst.to_string());
+        let regs = row
+            .regs
+            .iter()
+            .enumerate()
+            .map(|(r, [lo, hi])| {
+                format!(
+                    "r{r}={}",
+                    hi.as_canonical_u64() << 16 | lo.as_canonical_u64()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mut text = format!(
+            "row {i}: pc={} {inst} | {regs}\n",
+            row.pc.as_canonical_u64()
+        );
+        let skip = 2 * NUM_REGS + 1;
+        for (name, value) in Self::column_names().iter().zip(values.iter()).skip(skip) {
+            if !value.is_zero() {
+                writeln!(text, "  {name} = {}", value.as_canonical_u64()).unwrap();
+            }
+        }
+        text
+    }
+}
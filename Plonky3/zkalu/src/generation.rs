@@ -0,0 +1,267 @@
+use alloc::vec::Vec;
+use core::borrow::BorrowMut;
+use core::fmt;
+
+use p3_air::utils::u32_to_bits_le;
+use p3_field::{PrimeCharacteristicRing, PrimeField64};
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::columns::{AluRow, num_alu_cols, u32_to_limbs};
+use crate::decoder::generate_decoded_row;
+use crate::memory::{MemoryAccess, generate_memory_rows};
+use crate::{AluChip, Instruction, Memory, Opcode};
+
+/// The number of instructions a program may execute before it is assumed not to halt.
+pub const MAX_STEPS: usize = 1 << 22;
+
+/// The error of running a program which executes more than [`MAX_STEPS`] instructions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DidNotHalt;
+
+impl fmt::Display for DidNotHalt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "program did not halt within {MAX_STEPS} steps")
+    }
+}
+
+impl core::error::Error for DidNotHalt {}
+
+/// Run `program` from `initial_regs` and return the register state once it halts.
+///
+/// Execution starts at pc 0 with every memory word zero and halts when the pc leaves the
+/// program, either by running off its end or by branching to a target past it.
+///
+/// # Panics
+/// Panics if the program executes more than [`MAX_STEPS`] instructions.
+pub fn execute<const NUM_REGS: usize>(
+    program: &[Instruction],
+    initial_regs: [u32; NUM_REGS],
+) -> [u32; NUM_REGS] {
+    try_execute(program, initial_regs).unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// Run `program` as [`execute`] does, failing with [`DidNotHalt`] rather than panicking if it
+/// executes more than [`MAX_STEPS`] instructions.
+pub fn try_execute<const NUM_REGS: usize>(
+    program: &[Instruction],
+    initial_regs: [u32; NUM_REGS],
+) -> Result<[u32; NUM_REGS], DidNotHalt> {
+    Ok(run(program, initial_regs, |_, _, _, _| {})?.0)
+}
+
+/// Run `program` as [`execute`] does, calling `visit` with the pc, the instruction and the
+/// registers before and after each step. Returns the final registers and the pc the program
+/// halted at, or [`DidNotHalt`] once it has executed [`MAX_STEPS`] instructions.
+fn run<const NUM_REGS: usize>(
+    program: &[Instruction],
+    initial_regs: [u32; NUM_REGS],
+    mut visit: impl FnMut(usize, &Instruction, [u32; NUM_REGS], [u32; NUM_REGS]),
+) -> Result<([u32; NUM_REGS], usize), DidNotHalt> {
+    let (mut regs, mut pc) = (initial_regs, 0);
+    let mut memory = Memory::new();
+    let mut steps = 0;
+    while let Some(inst) = program.get(pc) {
+        if steps == MAX_STEPS {
+            return Err(DidNotHalt);
+        }
+        let (prev_regs, next_pc) = (regs, inst.next_pc(pc, &regs));
+        inst.step(&mut regs, &mut memory);
+        visit(pc, inst, prev_regs, regs);
+        pc = next_pc;
+        steps += 1;
+    }
+    Ok((regs, pc))
+}
+
+impl<const NUM_REGS: usize> AluChip<NUM_REGS> {
+    /// Generate the execution trace of `program` starting from `initial_regs`.
+    ///
+    /// The trace has one row per executed instruction, padded to a power of two with `NOP` rows
+    /// which hold the final register state and continue counting up from the pc the program
+    /// halted at. The memory table columns hold the `LOAD`s and `STORE`s of the trace sorted by
+    /// address.
+    pub fn generate_trace<F: PrimeField64>(
+        program: &[Instruction],
+        initial_regs: [u32; NUM_REGS],
+    ) -> RowMajorMatrix<F> {
+        Self::generate_trace_and_final_regs(program, initial_regs).0
+    }
+
+    /// Generate the trace as [`Self::generate_trace`] does, together with the final registers.
+    pub(crate) fn generate_trace_and_final_regs<F: PrimeField64>(
+        program: &[Instruction],
+        initial_regs: [u32; NUM_REGS],
+    ) -> (RowMajorMatrix<F>, [u32; NUM_REGS]) {
+        Self::generate_padded_trace(program, initial_regs, Instruction::nop(), 0, 1)
+            .unwrap_or_else(|e| panic!("{e}"))
+    }
+
+    /// Generate the trace as [`Self::generate_trace`] does, with at least `min_padding` padding
+    /// rows and `min_height` rows in all, together with the final registers. The padding rows
+    /// execute `padding` from the pc the program halted at. Fails if the program does not halt.
+    pub(crate) fn generate_padded_trace<F: PrimeField64>(
+        program: &[Instruction],
+        initial_regs: [u32; NUM_REGS],
+        padding: Instruction,
+        min_padding: usize,
+        min_height: usize,
+    ) -> Result<(RowMajorMatrix<F>, [u32; NUM_REGS]), DidNotHalt> {
+        assert!(!program.is_empty(), "Program cannot be empty");
+        for inst in program {
+            assert!(inst.dest < NUM_REGS, "Invalid dest register: {}", inst.dest);
+            assert!(inst.src1 < NUM_REGS, "Invalid src1 register: {}", inst.src1);
+            assert!(inst.src2 < NUM_REGS, "Invalid src2 register: {}", inst.src2);
+        }
+
+        let mut steps = Vec::new();
+        let (final_regs, halt_pc) = run(program, initial_regs, |pc, &inst, regs, next_regs| {
+            steps.push((pc, inst, regs, next_regs));
+        })?;
+        let trace_len = (steps.len() + min_padding)
+            .max(min_height)
+            .next_power_of_two();
+        let padding =
+            core::iter::successors(Some(halt_pc), |&pc| Some(padding.next_pc(pc, &final_regs)))
+                .map(|pc| (pc, padding, final_regs, final_regs));
+
+        let width = num_alu_cols::<NUM_REGS>();
+        let mut trace = RowMajorMatrix::new(F::zero_vec(trace_len * width), width);
+        let mut accesses = Vec::new();
+        let rows = steps.into_iter().chain(padding).zip(trace.rows_mut());
+        for (clk, ((pc, inst, regs, next_regs), row)) in rows.enumerate() {
+            let row: &mut AluRow<F, NUM_REGS> = row.borrow_mut();
+            row.pc = F::from_usize(pc);
+            row.clk = F::from_usize(clk);
+            generate_row(row, &inst, regs, next_regs);
+            if inst.op.accesses_memory() {
+                accesses.push(MemoryAccess {
+                    addr: inst.mem_addr(&regs),
+                    clk,
+                    value: result(&inst, regs, next_regs),
+                    is_write: inst.op == Opcode::STORE,
+                });
+            }
+        }
+        generate_memory_rows::<F, NUM_REGS>(&mut trace, accesses);
+
+        Ok((trace, final_regs))
+    }
+}
+
+/// The value of the result columns on the row executing `inst` from `regs` to `next_regs`. For a
+/// `LOAD` or `STORE` this is the word read or written.
+fn result<const NUM_REGS: usize>(
+    inst: &Instruction,
+    regs: [u32; NUM_REGS],
+    next_regs: [u32; NUM_REGS],
+) -> u32 {
+    match inst.op {
+        Opcode::NOP | Opcode::JMP => 0,
+        // A conditional branch records whether its operands are equal, or whether the first is
+        // less than the second.
+        Opcode::BEQ | Opcode::BNE => (regs[inst.src1] == inst.src2_val(&regs)) as u32,
+        Opcode::BLT | Opcode::BGE => {
+            ((regs[inst.src1] as i32) < (inst.src2_val(&regs) as i32)) as u32
+        }
+        Opcode::BLTU | Opcode::BGEU => (regs[inst.src1] < inst.src2_val(&regs)) as u32,
+        Opcode::STORE => inst.src2_val(&regs),
+        _ => next_regs[inst.dest],
+    }
+}
+
+fn generate_row<F: PrimeField64, const NUM_REGS: usize>(
+    row: &mut AluRow<F, NUM_REGS>,
+    inst: &Instruction,
+    regs: [u32; NUM_REGS],
+    next_regs: [u32; NUM_REGS],
+) {
+    row.regs = regs.map(u32_to_limbs);
+    generate_decoded_row(row, inst);
+
+    let (src1, src2) = (regs[inst.src1], inst.src2_val(&regs));
+    let result = result(inst, regs, next_regs);
+    row.src1_val = u32_to_limbs(src1);
+    row.src2_val = u32_to_limbs(src2);
+    row.result = u32_to_limbs(result);
+    row.src1_bits = u32_to_bits_le(src1);
+    row.src2_bits = u32_to_bits_le(src2);
+    row.result_bits = u32_to_bits_le(result);
+
+    match inst.op {
+        Opcode::MUL => {
+            let [carry_lo, carry_hi] = mul_carries(src1, src2, 0);
+            row.mul_carry_lo = bits_le(carry_lo);
+            row.mul_carry_hi = bits_le(carry_hi);
+        }
+        Opcode::EQ
+        | Opcode::LT
+        | Opcode::SLT
+        | Opcode::BEQ
+        | Opcode::BNE
+        | Opcode::BLT
+        | Opcode::BGE
+        | Opcode::BLTU
+        | Opcode::BGEU => {
+            // Signed comparisons compare the operands with their sign bits flipped as unsigned
+            // integers.
+            let signed = matches!(inst.op, Opcode::SLT | Opcode::BLT | Opcode::BGE);
+            let flip = if signed { 1 << 31 } else { 0 };
+            generate_diff(row, src1 ^ flip, src2 ^ flip);
+            row.diff_inv = (row.diff[0] + row.diff[1]).try_inverse().unwrap_or(F::ZERO);
+        }
+        Opcode::LOAD | Opcode::STORE => {
+            let addr = inst.mem_addr(&regs);
+            row.diff = u32_to_limbs(addr);
+            row.diff_bits = u32_to_bits_le(addr);
+        }
+        Opcode::DIVU | Opcode::REMU => {
+            let quotient = src1.checked_div(src2).unwrap_or(u32::MAX);
+            let remainder = src1.checked_rem(src2).unwrap_or(src1);
+            row.quotient = u32_to_limbs(quotient);
+            row.quotient_bits = u32_to_bits_le(quotient);
+            row.remainder = u32_to_limbs(remainder);
+            row.remainder_bits = u32_to_bits_le(remainder);
+            // q * src2 + r never exceeds 32 bits, so the carry out of the high limb is zero.
+            let [carry_lo, carry_hi] = mul_carries(quotient, src2, remainder);
+            row.mul_carry_lo = bits_le(carry_lo);
+            row.mul_carry_hi = bits_le(carry_hi);
+            generate_diff(row, remainder, src2);
+            row.src2_inv = (row.src2_val[0] + row.src2_val[1])
+                .try_inverse()
+                .unwrap_or(F::ZERO);
+            row.div_by_zero = F::from_bool(src2 == 0);
+        }
+        op if op.is_shift() => row.shift = one_hot((src2 & 31) as usize),
+        _ => {}
+    }
+}
+
+/// The carries out of each 16-bit limb of the low 32 bits of `a * b + c`, computed byte by byte.
+fn mul_carries(a: u32, b: u32, c: u32) -> [u32; 2] {
+    let (a, b) = (a.to_le_bytes(), b.to_le_bytes());
+    let conv = |k: usize| (0..=k).map(|i| a[i] as u32 * b[k - i] as u32).sum::<u32>();
+    let carry_lo = (conv(0) + (conv(1) << 8) + (c & 0xFFFF)) >> 16;
+    let carry_hi = (conv(2) + (conv(3) << 8) + carry_lo + (c >> 16)) >> 16;
+    [carry_lo, carry_hi]
+}
+
+/// Fill in `a - b mod 2^32` and the borrow out of each limb.
+fn generate_diff<F: PrimeField64, const NUM_REGS: usize>(
+    row: &mut AluRow<F, NUM_REGS>,
+    a: u32,
+    b: u32,
+) {
+    let diff = a.wrapping_sub(b);
+    row.diff = u32_to_limbs(diff);
+    row.diff_bits = u32_to_bits_le(diff);
+    row.borrow = [F::from_bool((a as u16) < (b as u16)), F::from_bool(a < b)];
+}
+
+pub(crate) fn one_hot<F: PrimeCharacteristicRing, const N: usize>(index: usize) -> [F; N] {
+    core::array::from_fn(|i| F::from_bool(i == index))
+}
+
+pub(crate) fn bits_le<F: PrimeCharacteristicRing, const N: usize>(val: u32) -> [F; N] {
+    debug_assert!(val >> N == 0, "{val} does not fit in {N} bits");
+    core::array::from_fn(|i| F::from_bool(val >> i & 1 == 1))
+}
@@ -0,0 +1,532 @@
+use crate::Memory;
+
+/// The default number of general-purpose 32-bit registers, `r0..r3`.
+pub const NUM_REGISTERS: usize = 4;
+
+/// The number of opcodes, and so of operation selector columns.
+pub const NUM_OPCODES: usize = 31;
+
+/// The width of the opcode field and of each register field of an instruction word, see
+/// [`Instruction::encode`]. An encoded instruction can only name the registers `r0..r31`.
+pub const FIELD_BITS: usize = 5;
+
+/// The bit offsets of the opcode, `dest`, `src1` and `src2` fields of an instruction word.
+pub const FIELD_SHIFTS: [usize; 4] = [0, FIELD_BITS, 2 * FIELD_BITS, 3 * FIELD_BITS];
+
+/// Operation types supported by the ALU. The operation selector columns follow the declaration
+/// order, so `op as usize` is the index of the selector for `op`.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Opcode {
+    /// `dest = src1 + src2 mod 2^32`
+    ADD,
+    /// `dest = src1 - src2 mod 2^32`
+    SUB,
+    /// `dest = src1 * src2 mod 2^32`
+    MUL,
+    /// `dest = src1 & src2`
+    AND,
+    /// `dest = src1 | src2`
+    OR,
+    /// `dest = src1 ^ src2`
+    XOR,
+    /// `dest = !src1`. `src2` is ignored.
+    NOT,
+    /// `dest = src1 << (src2 & 31)`
+    SLL,
+    /// `dest = src1 >> (src2 & 31)`, shifting in zeros.
+    SRL,
+    /// `dest = src1 >> (src2 & 31)`, shifting in copies of the sign bit.
+    SRA,
+    /// `dest = 1` if `src1 == src2`, otherwise `0`.
+    EQ,
+    /// `dest = 1` if `src1 < src2` as unsigned integers, otherwise `0`.
+    LT,
+    /// `dest = 1` if `src1 < src2` as signed (two's complement) integers, otherwise `0`.
+    SLT,
+    /// `dest = src1 / src2` as unsigned integers, or `2^32 - 1` if `src2 = 0`.
+    DIVU,
+    /// `dest = src1 % src2` as unsigned integers, or `src1` if `src2 = 0`.
+    REMU,
+    /// `dest = src1 + imm mod 2^32`
+    ADDI,
+    /// `dest = src1 << (imm & 31)`
+    SLLI,
+    /// `dest = src1 >> (imm & 31)`, shifting in zeros.
+    SRLI,
+    /// `dest = src1 >> (imm & 31)`, shifting in copies of the sign bit.
+    SRAI,
+    /// `dest = imm`
+    LOADI,
+    /// `dest = src1`
+    MOV,
+    /// `pc = imm`. No register is written.
+    JMP,
+    /// `pc = imm` if `src1 == src2`, otherwise `pc + 1`. No register is written.
+    BEQ,
+    /// `pc = imm` if `src1 != src2`, otherwise `pc + 1`. No register is written.
+    BNE,
+    /// `pc = imm` if `src1 < src2` as signed integers, otherwise `pc + 1`. No register is
+    /// written.
+    BLT,
+    /// `pc = imm` if `src1 >= src2` as signed integers, otherwise `pc + 1`. No register is
+    /// written.
+    BGE,
+    /// `pc = imm` if `src1 < src2` as unsigned integers, otherwise `pc + 1`. No register is
+    /// written.
+    BLTU,
+    /// `pc = imm` if `src1 >= src2` as unsigned integers, otherwise `pc + 1`. No register is
+    /// written.
+    BGEU,
+    /// `dest = mem[src1 + imm mod 2^32]`
+    LOAD,
+    /// `mem[src1 + imm mod 2^32] = src2`. No register is written.
+    STORE,
+    /// Leave every register unchanged. Used to pad traces to a power of two.
+    NOP,
+}
+
+impl Opcode {
+    /// Every opcode, in declaration order.
+    pub const ALL: [Self; NUM_OPCODES] = [
+        Self::ADD,
+        Self::SUB,
+        Self::MUL,
+        Self::AND,
+        Self::OR,
+        Self::XOR,
+        Self::NOT,
+        Self::SLL,
+        Self::SRL,
+        Self::SRA,
+        Self::EQ,
+        Self::LT,
+        Self::SLT,
+        Self::DIVU,
+        Self::REMU,
+        Self::ADDI,
+        Self::SLLI,
+        Self::SRLI,
+        Self::SRAI,
+        Self::LOADI,
+        Self::MOV,
+        Self::JMP,
+        Self::BEQ,
+        Self::BNE,
+        Self::BLT,
+        Self::BGE,
+        Self::BLTU,
+        Self::BGEU,
+        Self::LOAD,
+        Self::STORE,
+        Self::NOP,
+    ];
+
+    /// The name of the opcode in assembly, see [`assemble`](crate::assemble).
+    pub const fn mnemonic(self) -> &'static str {
+        match self {
+            Self::ADD => "add",
+            Self::SUB => "sub",
+            Self::MUL => "mul",
+            Self::AND => "and",
+            Self::OR => "or",
+            Self::XOR => "xor",
+            Self::NOT => "not",
+            Self::SLL => "sll",
+            Self::SRL => "srl",
+            Self::SRA => "sra",
+            Self::EQ => "eq",
+            Self::LT => "lt",
+            Self::SLT => "slt",
+            Self::DIVU => "divu",
+            Self::REMU => "remu",
+            Self::ADDI => "addi",
+            Self::SLLI => "slli",
+            Self::SRLI => "srli",
+            Self::SRAI => "srai",
+            Self::LOADI => "loadi",
+            Self::MOV => "mov",
+            Self::JMP => "jmp",
+            Self::BEQ => "beq",
+            Self::BNE => "bne",
+            Self::BLT => "blt",
+            Self::BGE => "bge",
+            Self::BLTU => "bltu",
+            Self::BGEU => "bgeu",
+            Self::LOAD => "load",
+            Self::STORE => "store",
+            Self::NOP => "nop",
+        }
+    }
+
+    /// Whether the second operand is the instruction's immediate rather than `src2`.
+    pub const fn uses_imm(self) -> bool {
+        matches!(
+            self,
+            Self::ADDI | Self::SLLI | Self::SRLI | Self::SRAI | Self::LOADI
+        )
+    }
+
+    /// Whether the instruction shifts `src1` by its second operand.
+    pub const fn is_shift(self) -> bool {
+        matches!(
+            self,
+            Self::SLL | Self::SRL | Self::SRA | Self::SLLI | Self::SRLI | Self::SRAI
+        )
+    }
+
+    /// Whether the instruction is a conditional branch on the order of its operands.
+    pub const fn is_order_branch(self) -> bool {
+        matches!(self, Self::BLT | Self::BGE | Self::BLTU | Self::BGEU)
+    }
+
+    /// Whether the instruction may set the pc to its immediate instead of `pc + 1`.
+    pub const fn is_branch(self) -> bool {
+        matches!(self, Self::JMP | Self::BEQ | Self::BNE) || self.is_order_branch()
+    }
+
+    /// Whether the instruction reads or writes memory.
+    pub const fn accesses_memory(self) -> bool {
+        matches!(self, Self::LOAD | Self::STORE)
+    }
+
+    /// Whether the instruction writes its destination register. `NOP`, `STORE` and branches
+    /// write none.
+    pub const fn writes_register(self) -> bool {
+        !matches!(self, Self::NOP | Self::STORE) && !self.is_branch()
+    }
+}
+
+/// A single ALU instruction: `dest = src1 <op> src2`, or `dest = src1 <op> imm` for the
+/// opcodes which take an immediate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Instruction {
+    /// Operation type.
+    pub op: Opcode,
+    /// Destination register index, below the register count of the chip.
+    pub dest: usize,
+    /// First source register index, below the register count of the chip.
+    pub src1: usize,
+    /// Second source register index, below the register count of the chip.
+    pub src2: usize,
+    /// Immediate operand, used in place of `src2` when [`Opcode::uses_imm`] holds, the target pc
+    /// of a branch or the offset added to `src1` to form a memory address.
+    pub imm: u32,
+}
+
+impl Instruction {
+    pub const fn new(op: Opcode, dest: usize, src1: usize, src2: usize) -> Self {
+        Self {
+            op,
+            dest,
+            src1,
+            src2,
+            imm: 0,
+        }
+    }
+
+    /// An instruction with an immediate operand. `src2` is set to `r0` and ignored.
+    pub const fn with_imm(op: Opcode, dest: usize, src1: usize, imm: u32) -> Self {
+        Self {
+            op,
+            dest,
+            src1,
+            src2: 0,
+            imm,
+        }
+    }
+
+    /// Shorthand for `Instruction::new(Opcode::ADD, dest, src1, src2)`.
+    pub const fn add(dest: usize, src1: usize, src2: usize) -> Self {
+        Self::new(Opcode::ADD, dest, src1, src2)
+    }
+
+    /// Shorthand for `Instruction::new(Opcode::SUB, dest, src1, src2)`.
+    pub const fn sub(dest: usize, src1: usize, src2: usize) -> Self {
+        Self::new(Opcode::SUB, dest, src1, src2)
+    }
+
+    /// Shorthand for `Instruction::new(Opcode::MUL, dest, src1, src2)`.
+    pub const fn mul(dest: usize, src1: usize, src2: usize) -> Self {
+        Self::new(Opcode::MUL, dest, src1, src2)
+    }
+
+    /// Shorthand for `Instruction::new(Opcode::AND, dest, src1, src2)`.
+    pub const fn and(dest: usize, src1: usize, src2: usize) -> Self {
+        Self::new(Opcode::AND, dest, src1, src2)
+    }
+
+    /// Shorthand for `Instruction::new(Opcode::OR, dest, src1, src2)`.
+    pub const fn or(dest: usize, src1: usize, src2: usize) -> Self {
+        Self::new(Opcode::OR, dest, src1, src2)
+    }
+
+    /// Shorthand for `Instruction::new(Opcode::XOR, dest, src1, src2)`.
+    pub const fn xor(dest: usize, src1: usize, src2: usize) -> Self {
+        Self::new(Opcode::XOR, dest, src1, src2)
+    }
+
+    /// Shorthand for `Instruction::new(Opcode::NOT, dest, src, src)`.
+    pub const fn not(dest: usize, src: usize) -> Self {
+        Self::new(Opcode::NOT, dest, src, src)
+    }
+
+    /// Shorthand for `Instruction::new(Opcode::SLL, dest, src1, src2)`.
+    pub const fn sll(dest: usize, src1: usize, src2: usize) -> Self {
+        Self::new(Opcode::SLL, dest, src1, src2)
+    }
+
+    /// Shorthand for `Instruction::new(Opcode::SRL, dest, src1, src2)`.
+    pub const fn srl(dest: usize, src1: usize, src2: usize) -> Self {
+        Self::new(Opcode::SRL, dest, src1, src2)
+    }
+
+    /// Shorthand for `Instruction::new(Opcode::SRA, dest, src1, src2)`.
+    pub const fn sra(dest: usize, src1: usize, src2: usize) -> Self {
+        Self::new(Opcode::SRA, dest, src1, src2)
+    }
+
+    /// Shorthand for `Instruction::new(Opcode::EQ, dest, src1, src2)`.
+    pub const fn eq(dest: usize, src1: usize, src2: usize) -> Self {
+        Self::new(Opcode::EQ, dest, src1, src2)
+    }
+
+    /// Shorthand for `Instruction::new(Opcode::LT, dest, src1, src2)`.
+    pub const fn lt(dest: usize, src1: usize, src2: usize) -> Self {
+        Self::new(Opcode::LT, dest, src1, src2)
+    }
+
+    /// Shorthand for `Instruction::new(Opcode::SLT, dest, src1, src2)`.
+    pub const fn slt(dest: usize, src1: usize, src2: usize) -> Self {
+        Self::new(Opcode::SLT, dest, src1, src2)
+    }
+
+    /// Shorthand for `Instruction::new(Opcode::DIVU, dest, src1, src2)`.
+    pub const fn divu(dest: usize, src1: usize, src2: usize) -> Self {
+        Self::new(Opcode::DIVU, dest, src1, src2)
+    }
+
+    /// Shorthand for `Instruction::new(Opcode::REMU, dest, src1, src2)`.
+    pub const fn remu(dest: usize, src1: usize, src2: usize) -> Self {
+        Self::new(Opcode::REMU, dest, src1, src2)
+    }
+
+    /// Shorthand for `Instruction::with_imm(Opcode::ADDI, dest, src, imm)`.
+    pub const fn addi(dest: usize, src: usize, imm: u32) -> Self {
+        Self::with_imm(Opcode::ADDI, dest, src, imm)
+    }
+
+    /// Shorthand for `Instruction::with_imm(Opcode::SLLI, dest, src, shamt)`.
+    pub const fn slli(dest: usize, src: usize, shamt: u32) -> Self {
+        Self::with_imm(Opcode::SLLI, dest, src, shamt)
+    }
+
+    /// Shorthand for `Instruction::with_imm(Opcode::SRLI, dest, src, shamt)`.
+    pub const fn srli(dest: usize, src: usize, shamt: u32) -> Self {
+        Self::with_imm(Opcode::SRLI, dest, src, shamt)
+    }
+
+    /// Shorthand for `Instruction::with_imm(Opcode::SRAI, dest, src, shamt)`.
+    pub const fn srai(dest: usize, src: usize, shamt: u32) -> Self {
+        Self::with_imm(Opcode::SRAI, dest, src, shamt)
+    }
+
+    /// Shorthand for `Instruction::with_imm(Opcode::LOADI, dest, 0, imm)`.
+    pub const fn loadi(dest: usize, imm: u32) -> Self {
+        Self::with_imm(Opcode::LOADI, dest, 0, imm)
+    }
+
+    /// Shorthand for `Instruction::new(Opcode::MOV, dest, src, 0)`.
+    pub const fn mov(dest: usize, src: usize) -> Self {
+        Self::new(Opcode::MOV, dest, src, 0)
+    }
+
+    /// An unconditional jump to instruction `target`.
+    pub const fn jmp(target: u32) -> Self {
+        Self::with_imm(Opcode::JMP, 0, 0, target)
+    }
+
+    /// Jump to instruction `target` if `src1 == src2`.
+    pub const fn beq(src1: usize, src2: usize, target: u32) -> Self {
+        Self::branch(Opcode::BEQ, src1, src2, target)
+    }
+
+    /// Jump to instruction `target` if `src1 != src2`.
+    pub const fn bne(src1: usize, src2: usize, target: u32) -> Self {
+        Self::branch(Opcode::BNE, src1, src2, target)
+    }
+
+    /// Jump to instruction `target` if `src1 < src2` as signed integers.
+    pub const fn blt(src1: usize, src2: usize, target: u32) -> Self {
+        Self::branch(Opcode::BLT, src1, src2, target)
+    }
+
+    /// Jump to instruction `target` if `src1 >= src2` as signed integers.
+    pub const fn bge(src1: usize, src2: usize, target: u32) -> Self {
+        Self::branch(Opcode::BGE, src1, src2, target)
+    }
+
+    /// Jump to instruction `target` if `src1 < src2` as unsigned integers.
+    pub const fn bltu(src1: usize, src2: usize, target: u32) -> Self {
+        Self::branch(Opcode::BLTU, src1, src2, target)
+    }
+
+    /// Jump to instruction `target` if `src1 >= src2` as unsigned integers.
+    pub const fn bgeu(src1: usize, src2: usize, target: u32) -> Self {
+        Self::branch(Opcode::BGEU, src1, src2, target)
+    }
+
+    /// The conditional branch `op` comparing `src1` and `src2`, jumping to instruction `target`.
+    pub const fn branch(op: Opcode, src1: usize, src2: usize, target: u32) -> Self {
+        Self {
+            op,
+            dest: 0,
+            src1,
+            src2,
+            imm: target,
+        }
+    }
+
+    /// Load the word at address `base + offset` into `dest`.
+    pub const fn load(dest: usize, base: usize, offset: u32) -> Self {
+        Self::with_imm(Opcode::LOAD, dest, base, offset)
+    }
+
+    /// Store `src` to the word at address `base + offset`.
+    pub const fn store(base: usize, src: usize, offset: u32) -> Self {
+        Self {
+            op: Opcode::STORE,
+            dest: 0,
+            src1: base,
+            src2: src,
+            imm: offset,
+        }
+    }
+
+    /// An instruction which leaves every register unchanged.
+    pub const fn nop() -> Self {
+        Self::new(Opcode::NOP, 0, 0, 0)
+    }
+
+    /// Encode the instruction as two 32-bit words: the instruction word, holding the opcode
+    /// and the `dest`, `src1` and `src2` fields at [`FIELD_SHIFTS`], followed by `imm`. The
+    /// immediate takes a whole word, so it cannot share one with the other fields.
+    ///
+    /// `dest` is encoded as zero for opcodes which write no register. The instruction word is
+    /// below `2^20`, so it fits in a single element of any field used for the ALU.
+    ///
+    /// # Panics
+    /// Panics if a register field is `r32` or above.
+    pub const fn encode(&self) -> [u32; 2] {
+        let dest = if self.op.writes_register() {
+            self.dest
+        } else {
+            0
+        };
+        let fields = [self.op as usize, dest, self.src1, self.src2];
+        let mut word = 0;
+        let mut i = 0;
+        while i < fields.len() {
+            assert!(
+                fields[i] < 1 << FIELD_BITS,
+                "Register does not fit in an instruction word"
+            );
+            word |= (fields[i] as u32) << FIELD_SHIFTS[i];
+            i += 1;
+        }
+        [word, self.imm]
+    }
+
+    /// Decode the words produced by [`Self::encode`]. Returns `None` if the opcode field is not
+    /// an opcode or any bit above the `src2` field is set.
+    pub const fn decode(words: [u32; 2]) -> Option<Self> {
+        let [word, imm] = words;
+        if word >> (FIELD_SHIFTS[3] + FIELD_BITS) != 0 {
+            return None;
+        }
+        let mask = (1 << FIELD_BITS) - 1;
+        let op = (word & mask) as usize;
+        if op >= NUM_OPCODES {
+            return None;
+        }
+        Some(Self {
+            op: Opcode::ALL[op],
+            dest: (word >> FIELD_SHIFTS[1] & mask) as usize,
+            src1: (word >> FIELD_SHIFTS[2] & mask) as usize,
+            src2: (word >> FIELD_SHIFTS[3] & mask) as usize,
+            imm,
+        })
+    }
+
+    /// Execute this instruction on `regs` and `memory`. Branches leave them unchanged; see
+    /// [`Self::next_pc`].
+    pub fn step<const NUM_REGS: usize>(&self, regs: &mut [u32; NUM_REGS], memory: &mut Memory) {
+        let (src1, src2) = (regs[self.src1], self.src2_val(regs));
+        regs[self.dest] = match self.op {
+            Opcode::ADD => src1.wrapping_add(src2),
+            Opcode::SUB => src1.wrapping_sub(src2),
+            Opcode::MUL => src1.wrapping_mul(src2),
+            Opcode::AND => src1 & src2,
+            Opcode::OR => src1 | src2,
+            Opcode::XOR => src1 ^ src2,
+            Opcode::NOT => !src1,
+            Opcode::SLL | Opcode::SLLI => src1 << (src2 & 31),
+            Opcode::SRL | Opcode::SRLI => src1 >> (src2 & 31),
+            Opcode::SRA | Opcode::SRAI => ((src1 as i32) >> (src2 & 31)) as u32,
+            Opcode::EQ => (src1 == src2) as u32,
+            Opcode::LT => (src1 < src2) as u32,
+            Opcode::SLT => ((src1 as i32) < (src2 as i32)) as u32,
+            // Division by zero follows RISC-V: the quotient is all ones and the remainder is the
+            // dividend.
+            Opcode::DIVU => src1.checked_div(src2).unwrap_or(u32::MAX),
+            Opcode::REMU => src1.checked_rem(src2).unwrap_or(src1),
+            Opcode::ADDI => src1.wrapping_add(src2),
+            Opcode::LOADI => src2,
+            Opcode::MOV => src1,
+            // Memory is zero-initialized.
+            Opcode::LOAD => memory.get(&self.mem_addr(regs)).copied().unwrap_or(0),
+            Opcode::STORE => {
+                memory.insert(self.mem_addr(regs), src2);
+                return;
+            }
+            Opcode::JMP
+            | Opcode::BEQ
+            | Opcode::BNE
+            | Opcode::BLT
+            | Opcode::BGE
+            | Opcode::BLTU
+            | Opcode::BGEU
+            | Opcode::NOP => return,
+        };
+    }
+
+    /// The pc of the instruction executed after this one, which is at `pc`, given the registers
+    /// before it executes.
+    pub const fn next_pc<const NUM_REGS: usize>(&self, pc: usize, regs: &[u32; NUM_REGS]) -> usize {
+        let taken = match self.op {
+            Opcode::JMP => true,
+            Opcode::BEQ => regs[self.src1] == regs[self.src2],
+            Opcode::BNE => regs[self.src1] != regs[self.src2],
+            Opcode::BLT => (regs[self.src1] as i32) < (regs[self.src2] as i32),
+            Opcode::BGE => (regs[self.src1] as i32) >= (regs[self.src2] as i32),
+            Opcode::BLTU => regs[self.src1] < regs[self.src2],
+            Opcode::BGEU => regs[self.src1] >= regs[self.src2],
+            _ => false,
+        };
+        if taken { self.imm as usize } else { pc + 1 }
+    }
+
+    /// The address accessed by a `LOAD` or `STORE`: `src1 + imm mod 2^32`.
+    pub const fn mem_addr<const NUM_REGS: usize>(&self, regs: &[u32; NUM_REGS]) -> u32 {
+        regs[self.src1].wrapping_add(self.imm)
+    }
+
+    /// The second operand: the immediate if the opcode takes one, otherwise `regs[src2]`.
+    pub const fn src2_val<const NUM_REGS: usize>(&self, regs: &[u32; NUM_REGS]) -> u32 {
+        if self.op.uses_imm() {
+            self.imm
+        } else {
+            regs[self.src2]
+        }
+    }
+}
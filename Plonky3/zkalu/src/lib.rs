@@ -0,0 +1,45 @@
+//! A zero-knowledge arithmetic logic unit.
+//!
+//! [`AluChip`] is an AIR for a small register machine. A program is a list of [`Instruction`]s;
+//! [`AluChip::generate_trace`] executes it from pc 0, following branches, and records one row
+//! per executed instruction, and [`prove_program`] / [`verify_program`] wrap `p3_uni_stark` for
+//! the common case. `LOAD` and `STORE` access a zero-initialized [`Memory`], whose consistency
+//! is proven with a permutation argument against a sorted memory table.
+//! [`RomAluChip`] is the same machine with the program committed as a preprocessed ROM, which
+//! every row looks up its instruction in by pc, proven with [`prove_rom_program`] /
+//! [`verify_rom_program`].
+//!
+//! Programs can also be written as text: [`assemble`] parses assembly and [`disassemble`]
+//! prints a program back as assembly. The [`riscv`] module loads and runs RV32IM programs and
+//! translates the subset the ALU supports into ALU programs.
+//!
+//! For debugging, [`AluChip::trace_to_csv`] and [`AluChip::trace_to_json`] export a trace with
+//! named columns and the instruction of each row, and [`AluChip::format_row`] prints one row.
+
+#![no_std]
+
+extern crate alloc;
+
+mod adder;
+mod air;
+mod asm;
+mod columns;
+pub mod config;
+mod decoder;
+mod dump;
+mod generation;
+mod instruction;
+mod memory;
+mod prover;
+pub mod riscv;
+mod rom;
+
+pub use adder::*;
+pub use air::*;
+pub use asm::*;
+pub use columns::*;
+pub use generation::*;
+pub use instruction::*;
+pub use memory::*;
+pub use prover::*;
+pub use rom::*;
@@ -0,0 +1,257 @@
+//! The memory chip: offline memory checking for `LOAD` and `STORE`.
+//!
+//! Each row of the ALU trace makes at most one memory access, at address `src1 + imm` and
+//! timestamped by the row's clock. The [`MemoryRow`] columns of the same trace list these accesses
+//! a second time, sorted by address and then by clock. In that order every access to an address
+//! directly follows the previous one, so checking that a read returns the last value written, or
+//! zero for the first access to an address, is a constraint between adjacent rows.
+//!
+//! A grand product over two permutation columns then shows that the sorted accesses are a
+//! permutation of the accesses made by the instructions. With challenges `beta` and `gamma`, an
+//! access `(addr, clk, value, is_write)` has the fingerprint
+//! `beta - (addr[0] + gamma * addr[1] + gamma^2 * clk + gamma^3 * value[0] + gamma^4 * value[1]
+//! + gamma^5 * is_write)`, and a row without an access contributes 1. The column `ratio` holds the
+//! fingerprint of the row's instruction divided by that of its memory table row, and `z` is the
+//! running product of `ratio` over the previous rows, which must reach 1 after the last row.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::borrow::{Borrow, BorrowMut};
+
+use p3_air::utils::pack_bits_le;
+use p3_air::{AirBuilder, ExtensionBuilder, PermutationAirBuilder};
+use p3_field::{
+    ExtensionField, Field, PrimeCharacteristicRing, PrimeField64, batch_multiplicative_inverse,
+};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::columns::{AluRow, MemoryRow, u32_to_limbs};
+use crate::generation::bits_le;
+
+/// Word-addressed memory mapping 32-bit addresses to 32-bit words. Absent addresses hold zero.
+pub type Memory = BTreeMap<u32, u32>;
+
+/// The number of challenges drawn for the memory permutation argument: `beta` and `gamma`.
+pub const NUM_MEMORY_CHALLENGES: usize = 2;
+
+/// The number of permutation columns of the memory argument: `z` and `ratio`.
+pub const NUM_MEMORY_PERMUTATION_COLS: usize = 2;
+
+/// A memory access made by the instruction on row `clk` of the trace. Accesses are ordered by
+/// address and then by clock.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct MemoryAccess {
+    pub addr: u32,
+    pub clk: usize,
+    pub value: u32,
+    pub is_write: bool,
+}
+
+/// Fill the memory table columns of `trace` with `accesses`, sorted, followed by padding rows.
+pub(crate) fn generate_memory_rows<F: PrimeField64, const NUM_REGS: usize>(
+    trace: &mut RowMajorMatrix<F>,
+    mut accesses: Vec<MemoryAccess>,
+) {
+    accesses.sort_unstable();
+    let mut prev: Option<MemoryAccess> = None;
+    for (access, row) in accesses.into_iter().zip(trace.rows_mut()) {
+        let row: &mut AluRow<F, NUM_REGS> = row.borrow_mut();
+        let mem = &mut row.mem;
+        mem.addr = u32_to_limbs(access.addr);
+        mem.clk = F::from_usize(access.clk);
+        mem.value = u32_to_limbs(access.value);
+        mem.is_write = F::from_bool(access.is_write);
+        mem.is_real = F::ONE;
+        if let Some(prev) = prev {
+            let same_addr_hi = prev.addr >> 16 == access.addr >> 16;
+            let same_addr = prev.addr == access.addr;
+            let delta = if same_addr {
+                (access.clk - prev.clk - 1) as u32
+            } else if same_addr_hi {
+                (access.addr & 0xFFFF) - (prev.addr & 0xFFFF) - 1
+            } else {
+                (access.addr >> 16) - (prev.addr >> 16) - 1
+            };
+            mem.same_addr_hi = F::from_bool(same_addr_hi);
+            mem.same_addr = F::from_bool(same_addr);
+            mem.delta_bits = bits_le(delta);
+        }
+        prev = Some(access);
+    }
+}
+
+/// Constrain the memory table: its rows are sorted and every read returns the last value written
+/// to its address, or zero if there is none.
+pub(crate) fn eval_memory<AB: AirBuilder>(
+    builder: &mut AB,
+    local: &MemoryRow<AB::Var>,
+    next: &MemoryRow<AB::Var>,
+) {
+    builder.label("memory table");
+    builder.assert_bools([
+        local.is_write.clone(),
+        local.is_real.clone(),
+        local.same_addr_hi.clone(),
+        local.same_addr.clone(),
+    ]);
+    builder.assert_bools(local.delta_bits.clone());
+    builder
+        .when(local.same_addr.clone())
+        .assert_one(local.same_addr_hi.clone());
+
+    // The first access to memory is the first access to its address, so a read returns zero.
+    let mut when_first_read =
+        builder.when(builder.is_first_row() * (AB::Expr::ONE - local.is_write.clone()));
+    for limb in local.value.clone() {
+        when_first_read.assert_zero(limb);
+    }
+
+    let mut when_transition = builder.when_transition();
+    // Padding rows come after every real access.
+    when_transition.assert_zero((AB::Expr::ONE - local.is_real.clone()) * next.is_real.clone());
+
+    // A real access increases (addr[1], addr[0], clk) lexicographically: within an address the
+    // clock increases, within a high limb the low limb increases and otherwise the high limb
+    // does. The flags cannot lie: claiming a differing limb is equal fails the equality, and
+    // claiming an equal limb differs fails the range check of its increase.
+    when_transition
+        .when(next.same_addr_hi.clone())
+        .assert_eq(next.addr[1].clone(), local.addr[1].clone());
+    when_transition
+        .when(next.same_addr.clone())
+        .assert_eq(next.addr[0].clone(), local.addr[0].clone());
+    let increase = |next: &AB::Var, local: &AB::Var| -> AB::Expr {
+        next.clone() - local.clone() - AB::Expr::ONE
+    };
+    let delta = increase(&next.clk, &local.clk) * next.same_addr.clone()
+        + increase(&next.addr[0], &local.addr[0])
+            * (next.same_addr_hi.clone() - next.same_addr.clone())
+        + increase(&next.addr[1], &local.addr[1]) * (AB::Expr::ONE - next.same_addr_hi.clone());
+    when_transition
+        .when(next.is_real.clone())
+        .assert_eq(delta, pack_bits_le(next.delta_bits.iter().cloned()));
+
+    // A read returns the previous value at the same address, or zero at a new address.
+    let mut when_next_read = when_transition.when(AB::Expr::ONE - next.is_write.clone());
+    for (next_limb, limb) in next.value.iter().zip(&local.value) {
+        when_next_read.assert_eq(next_limb.clone(), limb.clone() * next.same_addr.clone());
+    }
+}
+
+/// Constrain the permutation columns: the accesses made by the instructions, at address `diff`
+/// with value `result`, are a permutation of the memory table.
+pub(crate) fn eval_memory_permutation<AB: PermutationAirBuilder, const NUM_REGS: usize>(
+    builder: &mut AB,
+    local: &AluRow<AB::Var, NUM_REGS>,
+) {
+    let permutation = builder.permutation();
+    let (perm_local, perm_next) = (
+        permutation.row_slice(0).expect("Matrix is empty?"),
+        permutation.row_slice(1).expect("Matrix only has 1 row?"),
+    );
+    let (z, ratio, z_next): (AB::ExprEF, AB::ExprEF, AB::ExprEF) = (
+        perm_local[0].into(),
+        perm_local[1].into(),
+        perm_next[0].into(),
+    );
+    let randomness = builder.permutation_randomness();
+    let (beta, gamma): (AB::ExprEF, AB::ExprEF) = (randomness[0].into(), randomness[1].into());
+    builder.label("memory permutation");
+
+    let factor = |selector: AB::Expr, fields: [AB::Expr; 6]| -> AB::ExprEF {
+        let fingerprint = fields
+            .into_iter()
+            .rev()
+            .fold(AB::ExprEF::ZERO, |acc, field| acc * gamma.clone() + field);
+        (beta.clone() - fingerprint) * selector.clone() + (AB::Expr::ONE - selector)
+    };
+    let cpu = factor(
+        local.op_load.clone() + local.op_store.clone(),
+        [
+            local.diff[0].clone().into(),
+            local.diff[1].clone().into(),
+            local.clk.clone().into(),
+            local.result[0].clone().into(),
+            local.result[1].clone().into(),
+            local.op_store.clone().into(),
+        ],
+    );
+    let mem = &local.mem;
+    let mem = factor(
+        mem.is_real.clone().into(),
+        [
+            mem.addr[0].clone().into(),
+            mem.addr[1].clone().into(),
+            mem.clk.clone().into(),
+            mem.value[0].clone().into(),
+            mem.value[1].clone().into(),
+            mem.is_write.clone().into(),
+        ],
+    );
+
+    builder.assert_eq_ext(ratio.clone() * mem, cpu);
+    builder.when_first_row().assert_one_ext(z.clone());
+    builder
+        .when_transition()
+        .assert_eq_ext(z_next, z.clone() * ratio.clone());
+    builder.when_last_row().assert_one_ext(z * ratio);
+}
+
+/// Generate the permutation columns `[z, ratio]` of `main` under the challenges `[beta, gamma]`.
+pub(crate) fn generate_memory_permutation<
+    F: Field,
+    EF: ExtensionField<F>,
+    const NUM_REGS: usize,
+>(
+    main: &RowMajorMatrix<F>,
+    challenges: &[EF],
+) -> RowMajorMatrix<EF> {
+    let (beta, gamma) = (challenges[0], challenges[1]);
+    let factor = |selector: F, fields: [F; 6]| -> EF {
+        let fingerprint = fields
+            .into_iter()
+            .rev()
+            .fold(EF::ZERO, |acc, field| acc * gamma + field);
+        (beta - fingerprint) * selector + (F::ONE - selector)
+    };
+    let (cpu, mem): (Vec<EF>, Vec<EF>) = main
+        .row_slices()
+        .map(|row| {
+            let row: &AluRow<F, NUM_REGS> = row.borrow();
+            let cpu = factor(
+                row.op_load + row.op_store,
+                [
+                    row.diff[0],
+                    row.diff[1],
+                    row.clk,
+                    row.result[0],
+                    row.result[1],
+                    row.op_store,
+                ],
+            );
+            let mem = &row.mem;
+            let mem = factor(
+                mem.is_real,
+                [
+                    mem.addr[0],
+                    mem.addr[1],
+                    mem.clk,
+                    mem.value[0],
+                    mem.value[1],
+                    mem.is_write,
+                ],
+            );
+            (cpu, mem)
+        })
+        .unzip();
+
+    let mut values = Vec::with_capacity(2 * cpu.len());
+    let mut z = EF::ONE;
+    for (cpu, mem_inv) in cpu.into_iter().zip(batch_multiplicative_inverse(&mem)) {
+        let ratio = cpu * mem_inv;
+        values.extend([z, ratio]);
+        z *= ratio;
+    }
+    RowMajorMatrix::new(values, NUM_MEMORY_PERMUTATION_COLS)
+}
@@ -0,0 +1,111 @@
+use p3_field::PrimeField64;
+use p3_matrix::Matrix;
+use p3_uni_stark::{
+    PcsError, Proof, StarkGenericConfig, Val, VerificationError, prove, prove_with_preprocessed,
+    setup_preprocessed, verify, verify_with_preprocessed,
+};
+
+use crate::{AluChip, DidNotHalt, Instruction, MAX_STEPS, RomAluChip};
+
+/// Execute `program` from `initial_regs` and prove the resulting trace satisfies [`AluChip`].
+///
+/// Returns the proof together with the final register state it attests to.
+pub fn prove_program<SC, const NUM_REGS: usize>(
+    config: &SC,
+    program: &[Instruction],
+    initial_regs: [u32; NUM_REGS],
+) -> (Proof<SC>, [u32; NUM_REGS])
+where
+    SC: StarkGenericConfig,
+    Val<SC>: PrimeField64,
+{
+    let (trace, final_regs) = AluChip::generate_trace_and_final_regs(program, initial_regs);
+    let public_values = AluChip::public_values(&initial_regs, &final_regs);
+    let proof = prove(config, &AluChip::<NUM_REGS>, trace, &public_values);
+    (proof, final_regs)
+}
+
+/// Verify a proof produced by [`prove_program`], checking that the program run from
+/// `initial_regs` ended in `final_regs`.
+pub fn verify_program<SC, const NUM_REGS: usize>(
+    config: &SC,
+    proof: &Proof<SC>,
+    initial_regs: &[u32; NUM_REGS],
+    final_regs: &[u32; NUM_REGS],
+) -> Result<(), VerificationError<PcsError<SC>>>
+where
+    SC: StarkGenericConfig,
+{
+    let public_values = AluChip::public_values(initial_regs, final_regs);
+    verify(config, &AluChip::<NUM_REGS>, proof, &public_values)
+}
+
+/// Execute the program of `chip` from `initial_regs` and prove the resulting trace satisfies
+/// [`RomAluChip`], committing to the program's ROM along the way.
+///
+/// Returns the proof together with the final register state it attests to. Callers proving the
+/// same program repeatedly can instead commit to the ROM once with [`setup_preprocessed`] and
+/// call [`prove_with_preprocessed`] directly.
+///
+/// # Panics
+/// Panics if the program executes more than [`MAX_STEPS`] instructions.
+pub fn prove_rom_program<SC, const NUM_REGS: usize>(
+    config: &SC,
+    chip: &RomAluChip<NUM_REGS>,
+    initial_regs: [u32; NUM_REGS],
+) -> (Proof<SC>, [u32; NUM_REGS])
+where
+    SC: StarkGenericConfig,
+    Val<SC>: PrimeField64,
+{
+    try_prove_rom_program(config, chip, initial_regs).unwrap_or_else(|e| panic!("{e}"))
+}
+
+/// Prove the program of `chip` as [`prove_rom_program`] does, failing with [`DidNotHalt`]
+/// rather than panicking if it does not halt.
+pub fn try_prove_rom_program<SC, const NUM_REGS: usize>(
+    config: &SC,
+    chip: &RomAluChip<NUM_REGS>,
+    initial_regs: [u32; NUM_REGS],
+) -> Result<(Proof<SC>, [u32; NUM_REGS]), DidNotHalt>
+where
+    SC: StarkGenericConfig,
+    Val<SC>: PrimeField64,
+{
+    let (trace, final_regs) = chip.try_generate_trace(initial_regs)?;
+    // A run longer than the ROM is proven against a taller one, which the verifier rebuilds at
+    // the height of the proof.
+    let chip = chip.clone().with_height(trace.height());
+    let (rom_data, _) = setup_preprocessed(config, &chip).expect("RomAluChip always has a ROM");
+    let public_values = AluChip::public_values(&initial_regs, &final_regs);
+    let proof = prove_with_preprocessed(config, &chip, trace, &public_values, Some(&rom_data));
+    Ok((proof, final_regs))
+}
+
+/// Verify a proof produced by [`prove_rom_program`], checking that the program of `chip` run
+/// from `initial_regs` ended in `final_regs`.
+///
+/// The ROM commitment is recomputed from `chip` at the height of the proof, so the proof is only
+/// accepted for that program. A proof taller than any run of at most [`MAX_STEPS`] steps needs is
+/// rejected without building its ROM.
+pub fn verify_rom_program<SC, const NUM_REGS: usize>(
+    config: &SC,
+    chip: &RomAluChip<NUM_REGS>,
+    proof: &Proof<SC>,
+    initial_regs: &[u32; NUM_REGS],
+    final_regs: &[u32; NUM_REGS],
+) -> Result<(), VerificationError<PcsError<SC>>>
+where
+    SC: StarkGenericConfig,
+    Val<SC>: PrimeField64,
+{
+    let max_height = MAX_STEPS.max(chip.height());
+    let degree_bits = proof.degree_bits();
+    if degree_bits > max_height.trailing_zeros() as usize || 1 << degree_bits < chip.height() {
+        return Err(VerificationError::InvalidProofShape);
+    }
+    let chip = chip.clone().with_height(1 << degree_bits);
+    let (_, rom_key) = setup_preprocessed(config, &chip).expect("RomAluChip always has a ROM");
+    let public_values = AluChip::public_values(initial_regs, final_regs);
+    verify_with_preprocessed(config, &chip, proof, &public_values, Some(&rom_key))
+}
@@ -0,0 +1,393 @@
+use core::fmt;
+
+/// The register-register and register-immediate operations of RV32IM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RiscvOp {
+    Add,
+    Sub,
+    Sll,
+    Slt,
+    Sltu,
+    Xor,
+    Srl,
+    Sra,
+    Or,
+    And,
+    Mul,
+    Mulh,
+    Mulhsu,
+    Mulhu,
+    Div,
+    Divu,
+    Rem,
+    Remu,
+}
+
+impl RiscvOp {
+    /// The register-register mnemonic, e.g. `add`. The immediate form appends `i`.
+    pub const fn mnemonic(self) -> &'static str {
+        match self {
+            Self::Add => "add",
+            Self::Sub => "sub",
+            Self::Sll => "sll",
+            Self::Slt => "slt",
+            Self::Sltu => "sltu",
+            Self::Xor => "xor",
+            Self::Srl => "srl",
+            Self::Sra => "sra",
+            Self::Or => "or",
+            Self::And => "and",
+            Self::Mul => "mul",
+            Self::Mulh => "mulh",
+            Self::Mulhsu => "mulhsu",
+            Self::Mulhu => "mulhu",
+            Self::Div => "div",
+            Self::Divu => "divu",
+            Self::Rem => "rem",
+            Self::Remu => "remu",
+        }
+    }
+
+    /// Apply the operation to `a` and `b`. Shifts use the low 5 bits of `b`, and division by zero
+    /// and signed overflow follow the RISC-V specification.
+    pub const fn eval(self, a: u32, b: u32) -> u32 {
+        let (sa, sb) = (a as i32, b as i32);
+        match self {
+            Self::Add => a.wrapping_add(b),
+            Self::Sub => a.wrapping_sub(b),
+            Self::Sll => a << (b & 31),
+            Self::Slt => (sa < sb) as u32,
+            Self::Sltu => (a < b) as u32,
+            Self::Xor => a ^ b,
+            Self::Srl => a >> (b & 31),
+            Self::Sra => (sa >> (b & 31)) as u32,
+            Self::Or => a | b,
+            Self::And => a & b,
+            Self::Mul => a.wrapping_mul(b),
+            Self::Mulh => ((sa as i64 * sb as i64) >> 32) as u32,
+            Self::Mulhsu => ((sa as i64 * b as i64) >> 32) as u32,
+            Self::Mulhu => ((a as u64 * b as u64) >> 32) as u32,
+            Self::Div => match sb {
+                0 => u32::MAX,
+                _ => sa.wrapping_div(sb) as u32,
+            },
+            Self::Divu => match b {
+                0 => u32::MAX,
+                _ => a / b,
+            },
+            Self::Rem => match sb {
+                0 => a,
+                _ => sa.wrapping_rem(sb) as u32,
+            },
+            Self::Remu => match b {
+                0 => a,
+                _ => a % b,
+            },
+        }
+    }
+}
+
+/// The comparisons of the conditional branches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BranchCondition {
+    Eq,
+    Ne,
+    Lt,
+    Ge,
+    Ltu,
+    Geu,
+}
+
+impl BranchCondition {
+    pub const fn mnemonic(self) -> &'static str {
+        match self {
+            Self::Eq => "beq",
+            Self::Ne => "bne",
+            Self::Lt => "blt",
+            Self::Ge => "bge",
+            Self::Ltu => "bltu",
+            Self::Geu => "bgeu",
+        }
+    }
+
+    /// Whether the branch is taken for the operands `a` and `b`.
+    pub const fn holds(self, a: u32, b: u32) -> bool {
+        match self {
+            Self::Eq => a == b,
+            Self::Ne => a != b,
+            Self::Lt => (a as i32) < (b as i32),
+            Self::Ge => (a as i32) >= (b as i32),
+            Self::Ltu => a < b,
+            Self::Geu => a >= b,
+        }
+    }
+}
+
+/// The width of a memory access.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Width {
+    Byte,
+    Half,
+    Word,
+}
+
+impl Width {
+    /// The number of bytes accessed.
+    pub const fn bytes(self) -> u32 {
+        match self {
+            Self::Byte => 1,
+            Self::Half => 2,
+            Self::Word => 4,
+        }
+    }
+}
+
+/// A decoded RV32IM instruction. Registers are indices into `x0..x31`, and immediates and offsets
+/// are sign extended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RiscvInstruction {
+    /// `rd = imm`, where the low 12 bits of `imm` are zero.
+    Lui { rd: usize, imm: u32 },
+    /// `rd = pc + imm`, where the low 12 bits of `imm` are zero.
+    Auipc { rd: usize, imm: u32 },
+    /// `rd = pc + 4; pc += offset`
+    Jal { rd: usize, offset: i32 },
+    /// `rd = pc + 4; pc = (rs1 + offset) & !1`
+    Jalr { rd: usize, rs1: usize, offset: i32 },
+    /// `pc += offset` if `cond` holds for `rs1` and `rs2`.
+    Branch {
+        cond: BranchCondition,
+        rs1: usize,
+        rs2: usize,
+        offset: i32,
+    },
+    /// `rd = mem[rs1 + offset]`, sign extended unless `unsigned`.
+    Load {
+        width: Width,
+        unsigned: bool,
+        rd: usize,
+        rs1: usize,
+        offset: i32,
+    },
+    /// `mem[rs1 + offset] = rs2`, truncated to `width`.
+    Store {
+        width: Width,
+        rs1: usize,
+        rs2: usize,
+        offset: i32,
+    },
+    /// `rd = rs1 op imm`. Only `Add`, `Slt`, `Sltu`, `Xor`, `Or`, `And` and the shifts have an
+    /// immediate form.
+    OpImm {
+        op: RiscvOp,
+        rd: usize,
+        rs1: usize,
+        imm: i32,
+    },
+    /// `rd = rs1 op rs2`
+    Op {
+        op: RiscvOp,
+        rd: usize,
+        rs1: usize,
+        rs2: usize,
+    },
+    /// A memory ordering fence, which has no effect on a single hart.
+    Fence,
+    /// An environment call, which halts the machine.
+    Ecall,
+    /// A breakpoint, which halts the machine.
+    Ebreak,
+}
+
+/// Sign extend the low `bits` bits of `value`.
+const fn sign_extend(value: u32, bits: u32) -> i32 {
+    ((value << (32 - bits)) as i32) >> (32 - bits)
+}
+
+impl RiscvInstruction {
+    /// Decode a 32-bit instruction word, or return `None` if it is not an RV32IM instruction.
+    pub const fn decode(word: u32) -> Option<Self> {
+        let opcode = word & 0x7F;
+        let rd = ((word >> 7) & 0x1F) as usize;
+        let funct3 = (word >> 12) & 0x7;
+        let rs1 = ((word >> 15) & 0x1F) as usize;
+        let rs2 = ((word >> 20) & 0x1F) as usize;
+        let funct7 = word >> 25;
+        let imm_i = (word as i32) >> 20;
+
+        let inst = match opcode {
+            0x37 => Self::Lui {
+                rd,
+                imm: word & 0xFFFF_F000,
+            },
+            0x17 => Self::Auipc {
+                rd,
+                imm: word & 0xFFFF_F000,
+            },
+            0x6F => {
+                let imm = (word >> 31 & 1) << 20
+                    | (word >> 12 & 0xFF) << 12
+                    | (word >> 20 & 1) << 11
+                    | (word >> 21 & 0x3FF) << 1;
+                Self::Jal {
+                    rd,
+                    offset: sign_extend(imm, 21),
+                }
+            }
+            0x67 if funct3 == 0 => Self::Jalr {
+                rd,
+                rs1,
+                offset: imm_i,
+            },
+            0x63 => {
+                let cond = match funct3 {
+                    0 => BranchCondition::Eq,
+                    1 => BranchCondition::Ne,
+                    4 => BranchCondition::Lt,
+                    5 => BranchCondition::Ge,
+                    6 => BranchCondition::Ltu,
+                    7 => BranchCondition::Geu,
+                    _ => return None,
+                };
+                let imm = (word >> 31 & 1) << 12
+                    | (word >> 7 & 1) << 11
+                    | (word >> 25 & 0x3F) << 5
+                    | (word >> 8 & 0xF) << 1;
+                Self::Branch {
+                    cond,
+                    rs1,
+                    rs2,
+                    offset: sign_extend(imm, 13),
+                }
+            }
+            0x03 => {
+                let (width, unsigned) = match funct3 {
+                    0 => (Width::Byte, false),
+                    1 => (Width::Half, false),
+                    2 => (Width::Word, false),
+                    4 => (Width::Byte, true),
+                    5 => (Width::Half, true),
+                    _ => return None,
+                };
+                Self::Load {
+                    width,
+                    unsigned,
+                    rd,
+                    rs1,
+                    offset: imm_i,
+                }
+            }
+            0x23 => {
+                let width = match funct3 {
+                    0 => Width::Byte,
+                    1 => Width::Half,
+                    2 => Width::Word,
+                    _ => return None,
+                };
+                Self::Store {
+                    width,
+                    rs1,
+                    rs2,
+                    offset: sign_extend(funct7 << 5 | (word >> 7 & 0x1F), 12),
+                }
+            }
+            0x13 => {
+                let (op, imm) = match (funct3, funct7) {
+                    (0, _) => (RiscvOp::Add, imm_i),
+                    (2, _) => (RiscvOp::Slt, imm_i),
+                    (3, _) => (RiscvOp::Sltu, imm_i),
+                    (4, _) => (RiscvOp::Xor, imm_i),
+                    (6, _) => (RiscvOp::Or, imm_i),
+                    (7, _) => (RiscvOp::And, imm_i),
+                    (1, 0x00) => (RiscvOp::Sll, rs2 as i32),
+                    (5, 0x00) => (RiscvOp::Srl, rs2 as i32),
+                    (5, 0x20) => (RiscvOp::Sra, rs2 as i32),
+                    _ => return None,
+                };
+                Self::OpImm { op, rd, rs1, imm }
+            }
+            0x33 => {
+                let op = match (funct7, funct3) {
+                    (0x00, 0) => RiscvOp::Add,
+                    (0x20, 0) => RiscvOp::Sub,
+                    (0x00, 1) => RiscvOp::Sll,
+                    (0x00, 2) => RiscvOp::Slt,
+                    (0x00, 3) => RiscvOp::Sltu,
+                    (0x00, 4) => RiscvOp::Xor,
+                    (0x00, 5) => RiscvOp::Srl,
+                    (0x20, 5) => RiscvOp::Sra,
+                    (0x00, 6) => RiscvOp::Or,
+                    (0x00, 7) => RiscvOp::And,
+                    (0x01, 0) => RiscvOp::Mul,
+                    (0x01, 1) => RiscvOp::Mulh,
+                    (0x01, 2) => RiscvOp::Mulhsu,
+                    (0x01, 3) => RiscvOp::Mulhu,
+                    (0x01, 4) => RiscvOp::Div,
+                    (0x01, 5) => RiscvOp::Divu,
+                    (0x01, 6) => RiscvOp::Rem,
+                    (0x01, 7) => RiscvOp::Remu,
+                    _ => return None,
+                };
+                Self::Op { op, rd, rs1, rs2 }
+            }
+            0x0F if funct3 == 0 => Self::Fence,
+            0x73 if word == 0x0000_0073 => Self::Ecall,
+            0x73 if word == 0x0010_0073 => Self::Ebreak,
+            _ => return None,
+        };
+        Some(inst)
+    }
+}
+
+/// Writes the instruction in RISC-V assembly, with offsets relative to the instruction.
+impl fmt::Display for RiscvInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Lui { rd, imm } => write!(f, "lui x{rd}, {:#x}", imm >> 12),
+            Self::Auipc { rd, imm } => write!(f, "auipc x{rd}, {:#x}", imm >> 12),
+            Self::Jal { rd, offset } => write!(f, "jal x{rd}, {offset}"),
+            Self::Jalr { rd, rs1, offset } => write!(f, "jalr x{rd}, {offset}(x{rs1})"),
+            Self::Branch {
+                cond,
+                rs1,
+                rs2,
+                offset,
+            } => write!(f, "{} x{rs1}, x{rs2}, {offset}", cond.mnemonic()),
+            Self::Load {
+                width,
+                unsigned,
+                rd,
+                rs1,
+                offset,
+            } => {
+                let name = match width {
+                    Width::Byte => "lb",
+                    Width::Half => "lh",
+                    Width::Word => "lw",
+                };
+                let suffix = if unsigned { "u" } else { "" };
+                write!(f, "{name}{suffix} x{rd}, {offset}(x{rs1})")
+            }
+            Self::Store {
+                width,
+                rs1,
+                rs2,
+                offset,
+            } => {
+                let name = match width {
+                    Width::Byte => "sb",
+                    Width::Half => "sh",
+                    Width::Word => "sw",
+                };
+                write!(f, "{name} x{rs2}, {offset}(x{rs1})")
+            }
+            Self::OpImm { op, rd, rs1, imm } => {
+                write!(f, "{}i x{rd}, x{rs1}, {imm}", op.mnemonic())
+            }
+            Self::Op { op, rd, rs1, rs2 } => write!(f, "{} x{rd}, x{rs1}, x{rs2}", op.mnemonic()),
+            Self::Fence => write!(f, "fence"),
+            Self::Ecall => write!(f, "ecall"),
+            Self::Ebreak => write!(f, "ebreak"),
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use alloc::collections::BTreeMap;
+
+use super::{NUM_RISCV_REGISTERS, RiscvError, RiscvInstruction, RiscvProgram, Width};
+use crate::MAX_STEPS;
+
+/// An RV32IM interpreter with a byte-addressed little-endian memory. It serves as the reference
+/// semantics for programs translated to the ALU, see [`RiscvProgram::to_alu_program`].
+///
+/// `x0` always reads as zero and writes to it are discarded. Memory outside the program image
+/// reads as zero, and `memory` only holds the nonzero bytes of the image. A load or store whose
+/// address is not a multiple of its width traps, as RV32I permits. `ECALL` and `EBREAK` halt the
+/// machine.
+#[derive(Clone, Debug)]
+pub struct RiscvMachine {
+    pub regs: [u32; NUM_RISCV_REGISTERS],
+    pub pc: u32,
+    pub memory: BTreeMap<u32, u8>,
+}
+
+impl RiscvMachine {
+    /// A machine with `program` loaded, the pc at its entry point and the registers set to
+    /// `regs`, except that `x0` is zero.
+    pub fn new(program: &RiscvProgram, mut regs: [u32; NUM_RISCV_REGISTERS]) -> Self {
+        regs[0] = 0;
+        let memory = program
+            .segments
+            .iter()
+            .flat_map(|segment| (segment.addr..).zip(segment.data.iter().copied()))
+            .filter(|&(_, byte)| byte != 0)
+            .collect();
+        Self {
+            regs,
+            pc: program.entry,
+            memory,
+        }
+    }
+
+    /// Read `width` bytes starting at `addr` as a little-endian word.
+    pub fn read(&self, addr: u32, width: Width) -> u32 {
+        (0..width.bytes()).fold(0, |word, i| {
+            let byte = self.memory.get(&addr.wrapping_add(i)).copied().unwrap_or(0);
+            word | (byte as u32) << (8 * i)
+        })
+    }
+
+    /// Write the low `width` bytes of `value` starting at `addr`, little-endian.
+    pub fn write(&mut self, addr: u32, width: Width, value: u32) {
+        for i in 0..width.bytes() {
+            self.memory
+                .insert(addr.wrapping_add(i), (value >> (8 * i)) as u8);
+        }
+    }
+
+    /// Fetch and decode the instruction at the pc.
+    pub fn fetch(&self) -> Result<RiscvInstruction, RiscvError> {
+        if !self.pc.is_multiple_of(4) {
+            return Err(RiscvError::MisalignedPc { pc: self.pc });
+        }
+        let word = self.read(self.pc, Width::Word);
+        RiscvInstruction::decode(word).ok_or(RiscvError::IllegalInstruction { pc: self.pc, word })
+    }
+
+    /// Execute one instruction. Returns `true` if it was an `ECALL` or `EBREAK`, which leave the
+    /// pc pointing at them.
+    pub fn step(&mut self) -> Result<bool, RiscvError> {
+        let inst = self.fetch()?;
+        let pc = self.pc;
+        let mut next_pc = pc.wrapping_add(4);
+        let regs = self.regs;
+        let reg = |r: usize| regs[r];
+        let rd_value = match inst {
+            RiscvInstruction::Lui { rd, imm } => Some((rd, imm)),
+            RiscvInstruction::Auipc { rd, imm } => Some((rd, pc.wrapping_add(imm))),
+            RiscvInstruction::Jal { rd, offset } => {
+                next_pc = pc.wrapping_add_signed(offset);
+                Some((rd, pc.wrapping_add(4)))
+            }
+            RiscvInstruction::Jalr { rd, rs1, offset } => {
+                next_pc = reg(rs1).wrapping_add_signed(offset) & !1;
+                Some((rd, pc.wrapping_add(4)))
+            }
+            RiscvInstruction::Branch {
+                cond,
+                rs1,
+                rs2,
+                offset,
+            } => {
+                if cond.holds(reg(rs1), reg(rs2)) {
+                    next_pc = pc.wrapping_add_signed(offset);
+                }
+                None
+            }
+            RiscvInstruction::Load {
+                width,
+                unsigned,
+                rd,
+                rs1,
+                offset,
+            } => {
+                let addr = reg(rs1).wrapping_add_signed(offset);
+                if !addr.is_multiple_of(width.bytes()) {
+                    return Err(RiscvError::MisalignedAccess { pc, addr });
+                }
+                let value = self.read(addr, width);
+                let bits = 8 * width.bytes();
+                let value = if unsigned || bits == 32 {
+                    value
+                } else {
+                    (((value << (32 - bits)) as i32) >> (32 - bits)) as u32
+                };
+                Some((rd, value))
+            }
+            RiscvInstruction::Store {
+                width,
+                rs1,
+                rs2,
+                offset,
+            } => {
+                let (addr, value) = (reg(rs1).wrapping_add_signed(offset), reg(rs2));
+                if !addr.is_multiple_of(width.bytes()) {
+                    return Err(RiscvError::MisalignedAccess { pc, addr });
+                }
+                self.write(addr, width, value);
+                None
+            }
+            RiscvInstruction::OpImm { op, rd, rs1, imm } => {
+                Some((rd, op.eval(reg(rs1), imm as u32)))
+            }
+            RiscvInstruction::Op { op, rd, rs1, rs2 } => Some((rd, op.eval(reg(rs1), reg(rs2)))),
+            RiscvInstruction::Fence => None,
+            RiscvInstruction::Ecall | RiscvInstruction::Ebreak => return Ok(true),
+        };
+        if let Some((rd, value)) = rd_value
+            && rd != 0
+        {
+            self.regs[rd] = value;
+        }
+        self.pc = next_pc;
+        Ok(false)
+    }
+
+    /// Run until an `ECALL` or `EBREAK` and return the number of instructions executed before it.
+    ///
+    /// Fails if an instruction cannot be fetched, a load or store is misaligned or the machine
+    /// does not halt within
+    /// [`MAX_STEPS`] instructions.
+    pub fn run(&mut self) -> Result<usize, RiscvError> {
+        for steps in 0..MAX_STEPS {
+            if self.step()? {
+                return Ok(steps);
+            }
+        }
+        Err(RiscvError::DidNotHalt)
+    }
+}
@@ -0,0 +1,240 @@
+//! An RV32IM frontend: loading RISC-V programs, running them and translating them to the ALU.
+//!
+//! A [`RiscvProgram`] is loaded from a flat binary or from a 32-bit little-endian RISC-V ELF
+//! executable. [`RiscvMachine`] runs it with the RV32IM semantics, with `x0` hard-wired to zero,
+//! trapping on misaligned loads and stores, and halts at `ECALL` or `EBREAK`.
+//!
+//! [`RiscvProgram::to_alu_program`] translates the program instruction by instruction into an
+//! ALU program with 32 registers, whose registers `r0..r31` stand for `x0..x31`, and
+//! [`RiscvProgram::to_rom_chip`] commits to it as a [`RomAluChip`]. The ALU proves a subset of
+//! RV32IM, so the translation rejects the instructions it has no counterpart for. See
+//! [`RiscvProgram::to_alu_program`] for the supported subset.
+
+mod decode;
+mod machine;
+mod translate;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+pub use decode::*;
+pub use machine::*;
+use p3_field::PrimeField64;
+use p3_matrix::dense::RowMajorMatrix;
+
+use crate::{DidNotHalt, RomAluChip};
+
+/// The number of RISC-V integer registers, `x0..x31`.
+pub const NUM_RISCV_REGISTERS: usize = 32;
+
+/// A contiguous piece of the initial memory image.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub addr: u32,
+    pub data: Vec<u8>,
+    /// Whether the segment holds code.
+    pub executable: bool,
+}
+
+/// A RISC-V program: its initial memory image and the address execution starts at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RiscvProgram {
+    pub entry: u32,
+    pub segments: Vec<Segment>,
+}
+
+/// An error in loading, running or translating a RISC-V program. Addresses are byte addresses.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RiscvError {
+    /// The ELF file is malformed or not a 32-bit little-endian RISC-V executable.
+    InvalidElf(&'static str),
+    /// The word at `pc` is not an RV32IM instruction.
+    IllegalInstruction { pc: u32, word: u32 },
+    /// The pc is not a multiple of 4.
+    MisalignedPc { pc: u32 },
+    /// The load or store at `pc` accesses `addr`, which is not a multiple of its width.
+    MisalignedAccess { pc: u32, addr: u32 },
+    /// The machine did not halt within [`MAX_STEPS`](crate::MAX_STEPS) instructions.
+    DidNotHalt,
+    /// The instruction at `pc` has no ALU counterpart.
+    Unsupported { pc: u32, inst: RiscvInstruction },
+    /// The `jal` or `jalr` at `pc` saves a return address or jumps to a computed target. The
+    /// ALU's pc is an instruction index rather than a byte address, and it has no indirect jump.
+    LinkedJump { pc: u32, inst: RiscvInstruction },
+    /// The load or store at `pc` accesses a byte or a halfword, but the ALU's memory holds a
+    /// word per address.
+    SubwordAccess { pc: u32, inst: RiscvInstruction },
+    /// The jump or branch at `pc` targets an address outside the code, or a misaligned one.
+    InvalidTarget { pc: u32, target: u32 },
+    /// The ALU's memory starts zeroed, so it cannot hold the nonzero byte at `addr`.
+    InitializedData { addr: u32 },
+    /// The program's layout cannot be translated to the ALU.
+    UnsupportedLayout(&'static str),
+}
+
+impl fmt::Display for RiscvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidElf(reason) => write!(f, "invalid ELF file: {reason}"),
+            Self::IllegalInstruction { pc, word } => {
+                write!(f, "illegal instruction {word:#010x} at {pc:#x}")
+            }
+            Self::MisalignedPc { pc } => write!(f, "misaligned pc {pc:#x}"),
+            Self::MisalignedAccess { pc, addr } => {
+                write!(f, "misaligned access to {addr:#x} at {pc:#x}")
+            }
+            Self::DidNotHalt => write!(f, "the program did not halt"),
+            Self::Unsupported { pc, inst } => {
+                write!(f, "`{inst}` at {pc:#x} is not supported by the ALU")
+            }
+            Self::LinkedJump { pc, inst } => write!(
+                f,
+                "`{inst}` at {pc:#x} saves a return address or jumps indirectly, which the ALU cannot"
+            ),
+            Self::SubwordAccess { pc, inst } => write!(
+                f,
+                "`{inst}` at {pc:#x} accesses part of a word, but the ALU's memory holds whole words"
+            ),
+            Self::InvalidTarget { pc, target } => {
+                write!(f, "the jump at {pc:#x} to {target:#x} leaves the code")
+            }
+            Self::InitializedData { addr } => {
+                write!(
+                    f,
+                    "the ALU cannot initialize memory, but {addr:#x} is nonzero"
+                )
+            }
+            Self::UnsupportedLayout(reason) => write!(f, "unsupported program layout: {reason}"),
+        }
+    }
+}
+
+impl core::error::Error for RiscvError {}
+
+const PT_LOAD: u32 = 1;
+const PF_X: u32 = 1;
+const EM_RISCV: u16 = 0xF3;
+/// The size of an `Elf32_Phdr`.
+const PH_SIZE: usize = 32;
+
+/// The largest total memory size of the segments loaded from an ELF file. The loader allocates
+/// each segment's whole memory image, so this bounds what a malformed file can make it allocate.
+pub const MAX_IMAGE_SIZE: usize = 1 << 24;
+
+impl RiscvProgram {
+    /// A program consisting of the code `bytes`, loaded at `base` and entered there.
+    pub fn from_flat(bytes: &[u8], base: u32) -> Self {
+        Self {
+            entry: base,
+            segments: vec![Segment {
+                addr: base,
+                data: bytes.to_vec(),
+                executable: true,
+            }],
+        }
+    }
+
+    /// Load the `PT_LOAD` segments of an ELF executable. Each segment is extended with zeros
+    /// from its file size to its memory size, and the memory sizes may total at most
+    /// [`MAX_IMAGE_SIZE`].
+    pub fn from_elf(bytes: &[u8]) -> Result<Self, RiscvError> {
+        let slice_at = |offset: usize, len: usize| {
+            offset
+                .checked_add(len)
+                .and_then(|end| bytes.get(offset..end))
+        };
+        let u16_at = |offset: usize| -> Result<u16, RiscvError> {
+            slice_at(offset, 2)
+                .map(|b| u16::from_le_bytes([b[0], b[1]]))
+                .ok_or(RiscvError::InvalidElf("truncated file"))
+        };
+        let u32_at = |offset: usize| -> Result<u32, RiscvError> {
+            slice_at(offset, 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .ok_or(RiscvError::InvalidElf("truncated file"))
+        };
+
+        if bytes.get(..4) != Some(b"\x7fELF") {
+            return Err(RiscvError::InvalidElf("missing ELF magic"));
+        }
+        if bytes[4..].first() != Some(&1) {
+            return Err(RiscvError::InvalidElf("not a 32-bit ELF file"));
+        }
+        if bytes[5..].first() != Some(&1) {
+            return Err(RiscvError::InvalidElf("not a little-endian ELF file"));
+        }
+        if u16_at(18)? != EM_RISCV {
+            return Err(RiscvError::InvalidElf("not a RISC-V ELF file"));
+        }
+        let entry = u32_at(24)?;
+        let ph_offset = u32_at(28)? as usize;
+        let ph_size = u16_at(42)? as usize;
+        let ph_count = u16_at(44)? as usize;
+        // A smaller entry size would make the headers overlap, or all alias the first one.
+        if ph_count > 0 && ph_size < PH_SIZE {
+            return Err(RiscvError::InvalidElf("program header entries too small"));
+        }
+
+        let mut segments = Vec::new();
+        let mut image_size = 0;
+        for i in 0..ph_count {
+            let header = ph_offset + i * ph_size;
+            if u32_at(header)? != PT_LOAD {
+                continue;
+            }
+            let offset = u32_at(header + 4)? as usize;
+            let addr = u32_at(header + 8)?;
+            let file_size = u32_at(header + 16)? as usize;
+            let mem_size = u32_at(header + 20)? as usize;
+            let flags = u32_at(header + 24)?;
+            if file_size > mem_size {
+                return Err(RiscvError::InvalidElf(
+                    "segment larger in the file than in memory",
+                ));
+            }
+            image_size += mem_size;
+            if image_size > MAX_IMAGE_SIZE {
+                return Err(RiscvError::InvalidElf("memory image too large"));
+            }
+            if u64::from(addr) + mem_size as u64 > 1 << 32 {
+                return Err(RiscvError::InvalidElf("segment beyond the address space"));
+            }
+            let mut data = slice_at(offset, file_size)
+                .ok_or(RiscvError::InvalidElf("truncated segment"))?
+                .to_vec();
+            data.resize(mem_size, 0);
+            segments.push(Segment {
+                addr,
+                data,
+                executable: flags & PF_X != 0,
+            });
+        }
+        Ok(Self { entry, segments })
+    }
+
+    /// Translate the program with [`Self::to_alu_program`] into a [`RomAluChip`]`<32>`, whose
+    /// proofs show that this program ran.
+    pub fn to_rom_chip(&self) -> Result<RomAluChip<NUM_RISCV_REGISTERS>, RiscvError> {
+        Ok(RomAluChip::new(self.to_alu_program()?))
+    }
+
+    /// Translate the program with [`Self::to_rom_chip`] and generate the trace of running it
+    /// from `initial_regs`, see [`RomAluChip::generate_trace`]. `x0` starts at zero whatever
+    /// `initial_regs[0]` is.
+    ///
+    /// The program is first run on [`RiscvMachine`], so that a program which does not halt or
+    /// makes a misaligned load or store fails here rather than producing an unprovable trace.
+    pub fn generate_trace<F: PrimeField64>(
+        &self,
+        mut initial_regs: [u32; NUM_RISCV_REGISTERS],
+    ) -> Result<RowMajorMatrix<F>, RiscvError> {
+        initial_regs[0] = 0;
+        let chip = self.to_rom_chip()?;
+        RiscvMachine::new(self, initial_regs).run()?;
+        let (trace, _) = chip
+            .try_generate_trace(initial_regs)
+            .map_err(|DidNotHalt| RiscvError::DidNotHalt)?;
+        Ok(trace)
+    }
+}
@@ -0,0 +1,198 @@
+use alloc::vec::Vec;
+
+use super::{BranchCondition, RiscvError, RiscvInstruction, RiscvOp, RiscvProgram, Segment, Width};
+use crate::{Instruction, Opcode};
+
+impl RiscvProgram {
+    /// Translate the program into an ALU program for [`AluChip`](crate::AluChip)`<32>`, one ALU
+    /// instruction per RISC-V instruction, so that ALU pc `i` runs the instruction at byte
+    /// address `code + 4 * i`. Run from the same registers with `r0 = 0`, the ALU program ends
+    /// with the registers [`RiscvMachine`](super::RiscvMachine) halts with.
+    ///
+    /// The program must have a single executable segment, which starts at the entry point, and
+    /// every other byte of its image must be zero, as the ALU's memory starts zeroed. The
+    /// translation supports:
+    /// - `add`, `sub`, `mul`, `and`, `or`, `xor`, `sll`, `srl`, `sra`, `slt`, `sltu`, `divu` and
+    ///   `remu`;
+    /// - `addi`, `slli`, `srli`, `srai`, `xori rd, rs, -1` (`not`), `sltiu rd, rs, 1` (`seqz`)
+    ///   and any immediate operation on `x0`;
+    /// - `lui` and `auipc`, whose results are known in advance;
+    /// - `lw` and `sw`, whose addresses must be word-aligned since the ALU's memory holds a word
+    ///   per address. The translation cannot see the addresses, so
+    ///   [`RiscvProgram::generate_trace`] first runs the program on
+    ///   [`RiscvMachine`](super::RiscvMachine), which fails with
+    ///   [`RiscvError::MisalignedAccess`] on a misaligned one;
+    /// - `beq`, `bne`, `blt`, `bge`, `bltu`, `bgeu` and `jal x0` to targets within the code or
+    ///   just past it;
+    /// - `fence`, and `ecall` and `ebreak`, which halt the program.
+    ///
+    /// Instructions writing `x0` become `NOP`s. The ALU has no counterpart for:
+    /// - `jal` saving a return address and `jalr`, which are [`RiscvError::LinkedJump`], as the
+    ///   ALU's pc is an instruction index while a return address is a byte address, and the ALU
+    ///   has no indirect jump;
+    /// - `lb`, `lbu`, `lh`, `lhu`, `sb` and `sh`, which are [`RiscvError::SubwordAccess`], as the
+    ///   ALU's memory holds whole words;
+    /// - the other immediate operations, `mulh`, `mulhsu`, `mulhu`, `div` and `rem`, which are
+    ///   [`RiscvError::Unsupported`].
+    pub fn to_alu_program(&self) -> Result<Vec<Instruction>, RiscvError> {
+        let mut code_segments = self.segments.iter().filter(|segment| segment.executable);
+        let code = match (code_segments.next(), code_segments.next()) {
+            (Some(code), None) => code,
+            (None, _) => return Err(RiscvError::UnsupportedLayout("no executable segment")),
+            (Some(_), Some(_)) => {
+                return Err(RiscvError::UnsupportedLayout(
+                    "more than one executable segment",
+                ));
+            }
+        };
+        if code.addr != self.entry {
+            return Err(RiscvError::UnsupportedLayout(
+                "the entry point is not the start of the code",
+            ));
+        }
+        if !code.data.len().is_multiple_of(4) {
+            return Err(RiscvError::UnsupportedLayout(
+                "the code is not a whole number of instructions",
+            ));
+        }
+        for segment in self.segments.iter().filter(|segment| !segment.executable) {
+            if let Some(i) = segment.data.iter().position(|&byte| byte != 0) {
+                return Err(RiscvError::InitializedData {
+                    addr: segment.addr.wrapping_add(i as u32),
+                });
+            }
+        }
+
+        code.data
+            .chunks_exact(4)
+            .zip((code.addr..).step_by(4))
+            .map(|(word, pc)| {
+                let word = u32::from_le_bytes(word.try_into().unwrap());
+                let inst = RiscvInstruction::decode(word)
+                    .ok_or(RiscvError::IllegalInstruction { pc, word })?;
+                translate(code, pc, inst)
+            })
+            .collect()
+    }
+}
+
+/// Translate the instruction `inst` at `pc` of `code`.
+fn translate(code: &Segment, pc: u32, inst: RiscvInstruction) -> Result<Instruction, RiscvError> {
+    let len = (code.data.len() / 4) as u32;
+    // The ALU pc of the target of a jump at pc by offset. The end of the code halts both machines.
+    let target = |offset: i32| -> Result<u32, RiscvError> {
+        let target = pc.wrapping_add_signed(offset);
+        let distance = target.wrapping_sub(code.addr);
+        if !distance.is_multiple_of(4) || distance / 4 > len {
+            return Err(RiscvError::InvalidTarget { pc, target });
+        }
+        Ok(distance / 4)
+    };
+    let unsupported = Err(RiscvError::Unsupported { pc, inst });
+
+    let alu = match inst {
+        RiscvInstruction::Lui { rd: 0, .. }
+        | RiscvInstruction::Auipc { rd: 0, .. }
+        | RiscvInstruction::Load { rd: 0, .. }
+        | RiscvInstruction::OpImm { rd: 0, .. }
+        | RiscvInstruction::Op { rd: 0, .. }
+        | RiscvInstruction::Fence => Instruction::nop(),
+        RiscvInstruction::Lui { rd, imm } => Instruction::loadi(rd, imm),
+        RiscvInstruction::Auipc { rd, imm } => Instruction::loadi(rd, pc.wrapping_add(imm)),
+        RiscvInstruction::Jal { rd: 0, offset } => Instruction::jmp(target(offset)?),
+        RiscvInstruction::Jal { .. } | RiscvInstruction::Jalr { .. } => {
+            return Err(RiscvError::LinkedJump { pc, inst });
+        }
+        RiscvInstruction::Branch {
+            cond,
+            rs1,
+            rs2,
+            offset,
+        } => {
+            let op = match cond {
+                BranchCondition::Eq => Opcode::BEQ,
+                BranchCondition::Ne => Opcode::BNE,
+                BranchCondition::Lt => Opcode::BLT,
+                BranchCondition::Ge => Opcode::BGE,
+                BranchCondition::Ltu => Opcode::BLTU,
+                BranchCondition::Geu => Opcode::BGEU,
+            };
+            Instruction::branch(op, rs1, rs2, target(offset)?)
+        }
+        RiscvInstruction::Load {
+            width: Width::Word,
+            rd,
+            rs1,
+            offset,
+            ..
+        } => Instruction::load(rd, rs1, offset as u32),
+        RiscvInstruction::Store {
+            width: Width::Word,
+            rs1,
+            rs2,
+            offset,
+        } => Instruction::store(rs1, rs2, offset as u32),
+        RiscvInstruction::Load { .. } | RiscvInstruction::Store { .. } => {
+            return Err(RiscvError::SubwordAccess { pc, inst });
+        }
+        RiscvInstruction::OpImm {
+            op: RiscvOp::Add,
+            rd,
+            rs1,
+            imm,
+        } => Instruction::addi(rd, rs1, imm as u32),
+        RiscvInstruction::OpImm {
+            op: op @ (RiscvOp::Sll | RiscvOp::Srl | RiscvOp::Sra),
+            rd,
+            rs1,
+            imm,
+        } => {
+            let op = match op {
+                RiscvOp::Sll => Opcode::SLLI,
+                RiscvOp::Srl => Opcode::SRLI,
+                _ => Opcode::SRAI,
+            };
+            Instruction::with_imm(op, rd, rs1, imm as u32)
+        }
+        RiscvInstruction::OpImm {
+            op: RiscvOp::Xor,
+            rd,
+            rs1,
+            imm: -1,
+        } => Instruction::not(rd, rs1),
+        RiscvInstruction::OpImm {
+            op: RiscvOp::Sltu,
+            rd,
+            rs1,
+            imm: 1,
+        } => Instruction::eq(rd, rs1, 0),
+        RiscvInstruction::OpImm {
+            op,
+            rd,
+            rs1: 0,
+            imm,
+        } => Instruction::loadi(rd, op.eval(0, imm as u32)),
+        RiscvInstruction::Op { op, rd, rs1, rs2 } => {
+            let op = match op {
+                RiscvOp::Add => Opcode::ADD,
+                RiscvOp::Sub => Opcode::SUB,
+                RiscvOp::Mul => Opcode::MUL,
+                RiscvOp::And => Opcode::AND,
+                RiscvOp::Or => Opcode::OR,
+                RiscvOp::Xor => Opcode::XOR,
+                RiscvOp::Sll => Opcode::SLL,
+                RiscvOp::Srl => Opcode::SRL,
+                RiscvOp::Sra => Opcode::SRA,
+                RiscvOp::Slt => Opcode::SLT,
+                RiscvOp::Sltu => Opcode::LT,
+                RiscvOp::Divu => Opcode::DIVU,
+                RiscvOp::Remu => Opcode::REMU,
+                _ => return unsupported,
+            };
+            Instruction::new(op, rd, rs1, rs2)
+        }
+        RiscvInstruction::Ecall | RiscvInstruction::Ebreak => Instruction::jmp(len),
+        _ => return unsupported,
+    };
+    Ok(alu)
+}
@@ -0,0 +1,265 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::{Borrow, BorrowMut};
+
+use p3_air::{
+    Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, BaseAirWithPublicValues, Interaction,
+    PairBuilder, PermutationAirBuilder, VirtualPairCol,
+};
+use p3_field::{ExtensionField, Field, PrimeCharacteristicRing, PrimeField64};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_uni_stark::LogUp;
+
+use crate::columns::{AluRow, NUM_LIMBS, NUM_ROM_COLS, RomRow, num_alu_cols, u32_to_limbs};
+use crate::memory::{
+    NUM_MEMORY_CHALLENGES, NUM_MEMORY_PERMUTATION_COLS, generate_memory_permutation,
+};
+use crate::{AluChip, DidNotHalt, Instruction, NUM_REGISTERS};
+
+/// The bus the trace fetches instructions from the ROM over.
+pub const ROM_BUS: usize = 0;
+
+/// An [`AluChip`] whose program is fixed by a preprocessed ROM rather than chosen by the prover.
+///
+/// Row `pc` of the ROM holds the encoding of instruction `pc`, see [`Instruction::encode`], and
+/// the rows past the program hold a `JMP` to the pc just past it, the halting pc. Every row of
+/// the trace looks up its `(pc, inst, imm)` in the ROM with a LogUp argument, so it may fetch the
+/// ROM rows in any order and any number of times, as branches need. The trace decodes the
+/// instruction word into its selectors. A proof therefore shows that this particular program maps
+/// the initial registers to the final ones.
+///
+/// Once the program halts, the trace is padded with the halting `JMP` at the halting pc, which
+/// loops back to itself, and the last row of the trace must be at the halting pc. The program
+/// must therefore halt by running off its end or by branching to the halting pc.
+///
+/// The ROM is committed with `p3_uni_stark::setup_preprocessed`, which the verifier runs on its
+/// own copy of the program.
+#[derive(Clone, Debug)]
+pub struct RomAluChip<const NUM_REGS: usize = NUM_REGISTERS> {
+    program: Vec<Instruction>,
+    height: usize,
+}
+
+impl<const NUM_REGS: usize> RomAluChip<NUM_REGS> {
+    /// Create a chip whose ROM has the smallest height holding the program and its halting row.
+    ///
+    /// # Panics
+    /// Panics if `program` is empty, refers to a register outside `0..NUM_REGS` or branches past
+    /// the halting pc.
+    pub fn new(program: Vec<Instruction>) -> Self {
+        assert!(!program.is_empty(), "Program cannot be empty");
+        for inst in &program {
+            let max_reg = inst.dest.max(inst.src1).max(inst.src2);
+            assert!(max_reg < NUM_REGS, "Invalid register: {max_reg}");
+            assert!(
+                !inst.op.is_branch() || inst.imm as usize <= program.len(),
+                "Branch target past the halting pc: {inst:?}"
+            );
+        }
+        let height = (program.len() + 1).next_power_of_two();
+        Self { program, height }
+    }
+
+    /// Set the height of the ROM, for a program which runs for more steps than its ROM has rows.
+    ///
+    /// # Panics
+    /// Panics if `height` is not a power of two or leaves no room for the halting row.
+    pub fn with_height(mut self, height: usize) -> Self {
+        assert!(
+            height.is_power_of_two() && height > self.program.len(),
+            "Invalid ROM height: {height}"
+        );
+        self.height = height;
+        self
+    }
+
+    pub fn program(&self) -> &[Instruction] {
+        &self.program
+    }
+
+    /// The number of rows in the ROM and in every trace proven against it.
+    pub const fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The instruction the ROM holds past the program: a `JMP` to itself.
+    const fn halt(&self) -> Instruction {
+        Instruction::jmp(self.program.len() as u32)
+    }
+
+    /// Generate the ROM: one row per instruction, padded with the halting row to [`Self::height`].
+    pub fn generate_rom<F: Field>(&self) -> RowMajorMatrix<F> {
+        let width = NUM_ROM_COLS;
+        let mut rom = RowMajorMatrix::new(F::zero_vec(self.height * width), width);
+
+        let halting_pc = self.program.len();
+        let padding = core::iter::repeat((halting_pc, self.halt()));
+        for ((pc, inst), row) in self
+            .program
+            .iter()
+            .copied()
+            .enumerate()
+            .chain(padding)
+            .zip(rom.rows_mut())
+        {
+            let row: &mut RomRow<F> = row.borrow_mut();
+            let [word, imm] = inst.encode();
+            row.pc = F::from_usize(pc);
+            row.inst = F::from_u32(word);
+            row.imm = u32_to_limbs(imm);
+        }
+
+        rom
+    }
+
+    /// Generate the execution trace of the ROM's program starting from `initial_regs`.
+    ///
+    /// The trace ends with at least one row of the halting `JMP`. It has [`Self::height`] rows, or
+    /// more if the program runs for more steps than that, in which case it must be proven
+    /// against `self.with_height(trace.height())`.
+    pub fn generate_trace<F: PrimeField64>(
+        &self,
+        initial_regs: [u32; NUM_REGS],
+    ) -> RowMajorMatrix<F> {
+        self.try_generate_trace(initial_regs)
+            .unwrap_or_else(|e| panic!("{e}"))
+            .0
+    }
+
+    /// Generate the trace as [`Self::generate_trace`] does, together with the final registers,
+    /// failing with [`DidNotHalt`] rather than panicking if the program does not halt.
+    pub fn try_generate_trace<F: PrimeField64>(
+        &self,
+        initial_regs: [u32; NUM_REGS],
+    ) -> Result<(RowMajorMatrix<F>, [u32; NUM_REGS]), DidNotHalt> {
+        let (mut trace, final_regs) = AluChip::generate_padded_trace(
+            &self.program,
+            initial_regs,
+            self.halt(),
+            1,
+            self.height,
+        )?;
+
+        // Every ROM row past the program holds the halting row, so its fetches are all counted on
+        // the first of them.
+        let pcs: Vec<usize> = trace
+            .row_slices()
+            .map(|row| {
+                let row: &AluRow<F, NUM_REGS> = (*row).borrow();
+                row.pc.as_canonical_u64() as usize
+            })
+            .collect();
+        for pc in pcs {
+            let row: &mut AluRow<F, NUM_REGS> = trace.row_mut(pc).borrow_mut();
+            row.rom_multiplicity += F::ONE;
+        }
+
+        Ok((trace, final_regs))
+    }
+
+    /// The lookup of each trace row's `(pc, inst, imm)` in the ROM.
+    fn rom_lookup<F: Field>(&self) -> LogUp<F> {
+        let indices: Vec<usize> = (0..num_alu_cols::<NUM_REGS>()).collect();
+        let cols: &AluRow<usize, NUM_REGS> = indices.as_slice().borrow();
+        let fetch = [cols.pc, cols.inst, cols.imm[0], cols.imm[1]];
+        LogUp {
+            sends: vec![Interaction {
+                fields: fetch.map(VirtualPairCol::single_main).to_vec(),
+                count: VirtualPairCol::ONE,
+                bus: ROM_BUS,
+            }],
+            receives: vec![Interaction {
+                fields: (0..NUM_ROM_COLS)
+                    .map(VirtualPairCol::single_preprocessed)
+                    .collect(),
+                count: VirtualPairCol::single_main(cols.rom_multiplicity),
+                bus: ROM_BUS,
+            }],
+        }
+    }
+}
+
+impl<F: Field, const NUM_REGS: usize> BaseAir<F> for RomAluChip<NUM_REGS> {
+    fn width(&self) -> usize {
+        num_alu_cols::<NUM_REGS>()
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        Some(self.generate_rom())
+    }
+
+    fn num_permutation_challenges(&self) -> usize {
+        NUM_MEMORY_CHALLENGES + LogUp::<F>::NUM_CHALLENGES
+    }
+
+    fn permutation_width(&self) -> usize {
+        NUM_MEMORY_PERMUTATION_COLS + self.rom_lookup::<F>().width()
+    }
+
+    /// The memory permutation columns followed by the ROM lookup columns.
+    fn permutation_trace<EF: ExtensionField<F>>(
+        &self,
+        main: &RowMajorMatrix<F>,
+        challenges: &[EF],
+    ) -> Option<RowMajorMatrix<EF>> {
+        let (memory_challenges, rom_challenges) = challenges.split_at(NUM_MEMORY_CHALLENGES);
+        let memory = generate_memory_permutation::<F, EF, NUM_REGS>(main, memory_challenges);
+        let rom = self.generate_rom();
+        let lookup = self
+            .rom_lookup()
+            .generate_trace(Some(&rom), main, rom_challenges);
+        let width = memory.width() + lookup.width();
+        let mut values = Vec::with_capacity(main.height() * width);
+        for (memory, lookup) in memory
+            .values
+            .chunks_exact(memory.width())
+            .zip(lookup.values.chunks_exact(lookup.width()))
+        {
+            values.extend_from_slice(memory);
+            values.extend_from_slice(lookup);
+        }
+        Some(RowMajorMatrix::new(values, width))
+    }
+}
+
+impl<F: Field, const NUM_REGS: usize> BaseAirWithPublicValues<F> for RomAluChip<NUM_REGS> {
+    fn num_public_values(&self) -> usize {
+        2 * NUM_REGS * NUM_LIMBS
+    }
+}
+
+impl<AB, const NUM_REGS: usize> Air<AB> for RomAluChip<NUM_REGS>
+where
+    AB: PairBuilder + PermutationAirBuilder + AirBuilderWithPublicValues,
+    AB::Var: Copy,
+{
+    fn eval(&self, builder: &mut AB) {
+        AluChip::<NUM_REGS>.eval(builder);
+
+        let main = builder.main();
+        let local = main.row_slice(0).expect("Matrix is empty?");
+        let row: &AluRow<AB::Var, NUM_REGS> = (*local).borrow();
+        let preprocessed = builder.preprocessed();
+        let rom = preprocessed.row_slice(0).expect("Matrix is empty?");
+
+        // The trace fetches its instruction from the ROM at its own pc. The AluChip constraints
+        // decode the instruction word into the selectors.
+        builder.label("rom fetch");
+        self.rom_lookup().eval(
+            builder,
+            &rom,
+            &local,
+            NUM_MEMORY_PERMUTATION_COLS,
+            NUM_MEMORY_CHALLENGES,
+            AB::ExprEF::ZERO,
+        );
+
+        // The trace ends on the halting `JMP`, so the final registers are those the program
+        // halted with rather than any it passed through.
+        builder.label("rom halt");
+        builder
+            .when_last_row()
+            .assert_eq(row.pc, AB::Expr::from_usize(self.program.len()));
+    }
+}
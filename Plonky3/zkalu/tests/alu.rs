@@ -0,0 +1,869 @@
+use core::borrow::{Borrow, BorrowMut};
+
+use p3_baby_bear::BabyBear;
+use p3_field::{PrimeCharacteristicRing, PrimeField32};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_uni_stark::{
+    ConstraintReport, find_constraint_failures_with_permutation, prove, prove_with_preprocessed,
+    setup_preprocessed, verify,
+};
+use zkalu::config::{Challenge, alu_test_config};
+use zkalu::{
+    AdderChip, AluChip, AluRow, Instruction, Opcode, RomAluChip, execute, prove_program,
+    prove_rom_program, verify_program, verify_rom_program,
+};
+
+type F = BabyBear;
+
+/// The mixed `ADD`/`SUB` program from the `my_alu` example, extended to 64 instructions.
+fn sample_program() -> Vec<Instruction> {
+    let mut program = vec![
+        Instruction::add(0, 0, 1),
+        Instruction::sub(1, 2, 0),
+        Instruction::add(3, 0, 1),
+        Instruction::sub(2, 3, 1),
+    ];
+    let filler = [
+        Instruction::add(0, 0, 1),
+        Instruction::sub(1, 1, 0),
+        Instruction::add(2, 2, 3),
+        Instruction::sub(3, 3, 2),
+    ];
+    program.extend(filler.iter().cycle().take(60));
+    program
+}
+
+/// The constraints of [`AluChip`] which `trace` violates under fixed permutation challenges, with
+/// the public values of `initial_regs` and `final_regs`.
+fn constraint_failures(
+    trace: &RowMajorMatrix<F>,
+    initial_regs: &[u32; 4],
+    final_regs: &[u32; 4],
+) -> ConstraintReport<Challenge> {
+    find_constraint_failures_with_permutation(
+        &AluChip::<4>,
+        trace,
+        &[Challenge::from_u32(7), Challenge::from_u32(11)],
+        &AluChip::public_values(initial_regs, final_regs),
+    )
+}
+
+#[test]
+fn test_execute() {
+    let program = &sample_program()[..4];
+    let final_regs = execute(program, [1, 2, 5, 0]);
+    assert_eq!(final_regs, [3, 2, 3, 5]);
+}
+
+#[test]
+fn test_prove_program() {
+    let config = alu_test_config();
+    let initial_regs = [1, 2, 5, 0];
+    let (proof, final_regs) = prove_program(&config, &sample_program(), initial_regs);
+    assert_eq!(final_regs, execute(&sample_program(), initial_regs));
+    verify_program(&config, &proof, &initial_regs, &final_regs).expect("verification failed");
+}
+
+#[test]
+fn test_prove_non_power_of_two_lengths() {
+    let config = alu_test_config();
+    let initial_regs = [1, 2, 5, 0];
+    for len in [1, 3, 5, 63, 65] {
+        let program: Vec<_> = sample_program().into_iter().cycle().take(len).collect();
+        let (proof, final_regs) = prove_program(&config, &program, initial_regs);
+        assert_eq!(final_regs, execute(&program, initial_regs));
+        verify_program(&config, &proof, &initial_regs, &final_regs)
+            .unwrap_or_else(|e| panic!("verification failed for length {len}: {e:?}"));
+    }
+}
+
+#[test]
+fn test_nop() {
+    let program = [
+        Instruction::add(0, 0, 1),
+        Instruction::nop(),
+        Instruction::sub(1, 0, 1),
+    ];
+    assert_eq!(execute(&program, [1, 2, 5, 0]), [3, 1, 5, 0]);
+}
+
+#[test]
+fn test_mul() {
+    let config = alu_test_config();
+    let program = [
+        Instruction::mul(2, 0, 1),
+        Instruction::mul(3, 2, 2),
+        Instruction::sub(0, 3, 1),
+        Instruction::mul(1, 0, 0),
+        Instruction::add(2, 2, 3),
+    ];
+    let initial_regs = [3, 4, 0, 0];
+    let (proof, final_regs) = prove_program(&config, &program, initial_regs);
+    assert_eq!(final_regs, [140, 140 * 140, 156, 144]);
+    verify_program(&config, &proof, &initial_regs, &final_regs).expect("verification failed");
+}
+
+#[test]
+fn test_u32_wraparound() {
+    let config = alu_test_config();
+    let program = [
+        Instruction::sub(0, 0, 1), // 0 - 1 = 0xFFFFFFFF
+        Instruction::add(1, 0, 0), // 0xFFFFFFFF + 0xFFFFFFFF = 0xFFFFFFFE
+        Instruction::mul(2, 0, 0), // 0xFFFFFFFF * 0xFFFFFFFF = 1
+        Instruction::mul(3, 3, 3), // 0x10000 * 0x10000 = 0
+        Instruction::sub(0, 2, 1), // 1 - 0xFFFFFFFE = 3
+    ];
+    let initial_regs = [0, 1, 0, 0x10000];
+    let (proof, final_regs) = prove_program(&config, &program, initial_regs);
+    assert_eq!(final_regs, [3, 0xFFFF_FFFE, 1, 0]);
+    verify_program(&config, &proof, &initial_regs, &final_regs).expect("verification failed");
+}
+
+#[test]
+fn test_bitwise() {
+    let config = alu_test_config();
+    let program = [
+        Instruction::and(2, 0, 1),
+        Instruction::or(3, 0, 1),
+        Instruction::xor(0, 0, 1),
+        Instruction::not(1, 1),
+        Instruction::and(2, 2, 1), // A mask which clears every bit.
+    ];
+    let initial_regs = [0xF0F0_1234, 0xFF00_FF00, 0, 0];
+    let (proof, final_regs) = prove_program(&config, &program, initial_regs);
+    assert_eq!(final_regs, [0x0FF0_ED34, 0x00FF_00FF, 0, 0xFFF0_FF34]);
+    verify_program(&config, &proof, &initial_regs, &final_regs).expect("verification failed");
+}
+
+#[test]
+fn test_tampered_xor_result() {
+    let program = [Instruction::xor(0, 0, 1), Instruction::nop()];
+    let initial_regs = [0b1010, 0b0110, 0, 0];
+    let mut trace = AluChip::generate_trace(&program, initial_regs);
+    // Claim 0b1010 ^ 0b0110 = 0b1110 (the OR) consistently across the result columns and
+    // the next row, so only the bitwise constraint can catch it.
+    let row: &mut AluRow<F> = trace.row_mut(0).borrow_mut();
+    row.result[0] = F::from_u32(0b1110);
+    row.result_bits[1] = F::ONE;
+    let row: &mut AluRow<F> = trace.row_mut(1).borrow_mut();
+    row.regs[0][0] = F::from_u32(0b1110);
+    // The NOP reads r0 as both of its operands.
+    row.src1_val[0] = F::from_u32(0b1110);
+    row.src1_bits[1] = F::ONE;
+    row.src2_val[0] = F::from_u32(0b1110);
+    row.src2_bits[1] = F::ONE;
+    let final_regs = [0b1110, 0b0110, 0, 0];
+    let report = constraint_failures(&trace, &initial_regs, &final_regs);
+    assert_eq!(report.failing_rows("bitwise"), [0]);
+    assert!(
+        report.failures.iter().all(|f| f.label == Some("bitwise")),
+        "{report}"
+    );
+}
+
+#[test]
+fn test_constraint_report() {
+    let program = sample_program();
+    let initial_regs = [1, 2, 5, 0];
+    let final_regs = execute(&program, initial_regs);
+    let public_values = AluChip::public_values(&initial_regs, &final_regs);
+    let challenges = [Challenge::from_u32(7), Challenge::from_u32(11)];
+    let mut trace = AluChip::<4>::generate_trace(&program, initial_regs);
+    let report = find_constraint_failures_with_permutation(
+        &AluChip::<4>,
+        &trace,
+        &challenges,
+        &public_values,
+    );
+    assert!(report.is_ok(), "{report}");
+
+    // A second destination selector on rows 12 and 40 breaks the one-hot constraint there.
+    for i in [12, 40] {
+        let row: &mut AluRow<F> = trace.row_mut(i).borrow_mut();
+        row.dest[3] += F::ONE;
+    }
+    let report = find_constraint_failures_with_permutation(
+        &AluChip::<4>,
+        &trace,
+        &challenges,
+        &public_values,
+    );
+    assert_eq!(report.failing_rows("dest one-hot"), [12, 40]);
+    assert_eq!(report.failing_rows("instruction word"), [12, 40]);
+    assert!(report.failing_rows("bitwise").is_empty());
+    assert!(
+        report
+            .to_string()
+            .contains("constraint 46 (dest one-hot) fails on rows 12 (1), 40 (1)"),
+        "{report}"
+    );
+}
+
+#[test]
+fn test_comparisons() {
+    let config = alu_test_config();
+    let minus_one = -1i32 as u32;
+    let cases = [
+        (Instruction::eq(3, 0, 1), [5, 5], 1),
+        (Instruction::eq(3, 0, 1), [5, 6], 0),
+        (Instruction::eq(3, 0, 1), [0x0001_0000, 1], 0),
+        (Instruction::lt(3, 0, 1), [5, 6], 1),
+        (Instruction::lt(3, 0, 1), [6, 5], 0),
+        (Instruction::lt(3, 0, 1), [5, 5], 0),
+        (Instruction::lt(3, 0, 1), [0x0000_FFFF, 0x0001_0000], 1),
+        (Instruction::lt(3, 0, 1), [1, minus_one], 1),
+        (Instruction::slt(3, 0, 1), [1, minus_one], 0),
+        (Instruction::slt(3, 0, 1), [minus_one, 1], 1),
+        (Instruction::slt(3, 0, 1), [-7i32 as u32, -3i32 as u32], 1),
+        (Instruction::slt(3, 0, 1), [0x7FFF_FFFF, 0x8000_0000], 0),
+    ];
+    for (inst, [a, b], expected) in cases {
+        let initial_regs = [a, b, 0, 0];
+        let (proof, final_regs) = prove_program(&config, &[inst], initial_regs);
+        assert_eq!(final_regs[3], expected, "{inst:?} on {a:#x}, {b:#x}");
+        verify_program(&config, &proof, &initial_regs, &final_regs).expect("verification failed");
+    }
+}
+
+/// The constraint failures of a single-instruction program writing to `r3`, with its result
+/// replaced by `forged` consistently in the result columns, the next row and the public final
+/// registers.
+fn forged_result_failures(
+    inst: Instruction,
+    initial_regs: [u32; 4],
+    forged: u32,
+) -> ConstraintReport<Challenge> {
+    assert_eq!(inst.dest, 3);
+    let program = [inst, Instruction::nop()];
+    let mut trace = AluChip::generate_trace(&program, initial_regs);
+    let row: &mut AluRow<F> = trace.row_mut(0).borrow_mut();
+    row.result = [forged & 0xFFFF, forged >> 16].map(F::from_u32);
+    row.result_bits = core::array::from_fn(|i| F::from_bool(forged >> i & 1 == 1));
+    let row: &mut AluRow<F> = trace.row_mut(1).borrow_mut();
+    row.regs[3] = [forged & 0xFFFF, forged >> 16].map(F::from_u32);
+    let mut final_regs = initial_regs;
+    final_regs[3] = forged;
+    constraint_failures(&trace, &initial_regs, &final_regs)
+}
+
+#[test]
+fn test_forged_eq() {
+    // 5 == 0x10005 is false; the limbs differ only in the high half.
+    let report = forged_result_failures(Instruction::eq(3, 0, 1), [5, 0x0001_0005, 0, 0], 1);
+    assert_eq!(report.failing_rows("equality"), [0]);
+}
+
+#[test]
+fn test_forged_lt() {
+    let report = forged_result_failures(Instruction::lt(3, 0, 1), [5, 6, 0, 0], 0);
+    assert_eq!(report.failing_rows("less than"), [0]);
+}
+
+#[test]
+fn test_forged_slt() {
+    // -1 < 1 as signed integers, but not as unsigned ones.
+    let report = forged_result_failures(Instruction::slt(3, 0, 1), [-1i32 as u32, 1, 0, 0], 0);
+    assert_eq!(report.failing_rows("less than"), [0]);
+}
+
+#[test]
+fn test_shifts() {
+    let config = alu_test_config();
+    let x = 0x8000_F00D;
+    let cases = [
+        (Instruction::sll(3, 0, 1), [x, 4], x << 4),
+        (Instruction::sll(3, 0, 1), [x, 36], x << 4),
+        (Instruction::srl(3, 0, 1), [x, 31], 1),
+        (Instruction::srl(3, 0, 1), [x, 0], x),
+        (Instruction::sra(3, 0, 1), [x, 4], 0xF800_0F00),
+        (Instruction::sra(3, 0, 1), [0x7000_0000, 28], 7),
+        (Instruction::slli(3, 0, 31), [1, 0], 1 << 31),
+        (Instruction::srli(3, 0, 16), [x, 0], 0x8000),
+        (Instruction::srai(3, 0, 31), [x, 0], u32::MAX),
+    ];
+    for (inst, [a, b], expected) in cases {
+        let initial_regs = [a, b, 0, 0];
+        let (proof, final_regs) = prove_program(&config, &[inst], initial_regs);
+        assert_eq!(final_regs[3], expected, "{inst:?} on {a:#x}, {b:#x}");
+        verify_program(&config, &proof, &initial_regs, &final_regs).expect("verification failed");
+    }
+}
+
+#[test]
+fn test_forged_shift() {
+    let report = forged_result_failures(Instruction::sll(3, 0, 1), [1, 4, 0, 0], 8);
+    assert_eq!(report.failing_rows("shift"), [0]);
+    // An arithmetic shift of a negative number shifts in ones, not zeros.
+    let report =
+        forged_result_failures(Instruction::srai(3, 0, 4), [0x8000_0000, 0, 0, 0], 1 << 27);
+    assert_eq!(report.failing_rows("shift"), [0]);
+}
+
+#[test]
+fn test_division() {
+    let config = alu_test_config();
+    let cases = [
+        ([7, 2], 3, 1),
+        ([2, 7], 0, 2),
+        ([100, 100], 1, 0),
+        ([u32::MAX, 1], u32::MAX, 0),
+        ([u32::MAX, 0x0001_0000], 0xFFFF, 0xFFFF),
+        (
+            [0xDEAD_BEEF, 0x1234],
+            0xDEAD_BEEF / 0x1234,
+            0xDEAD_BEEF % 0x1234,
+        ),
+        // Division by zero, as in RISC-V.
+        ([42, 0], u32::MAX, 42),
+        ([0, 0], u32::MAX, 0),
+    ];
+    for ([a, b], quotient, remainder) in cases {
+        let program = [Instruction::divu(2, 0, 1), Instruction::remu(3, 0, 1)];
+        let initial_regs = [a, b, 0, 0];
+        let (proof, final_regs) = prove_program(&config, &program, initial_regs);
+        assert_eq!(final_regs, [a, b, quotient, remainder], "{a} / {b}");
+        verify_program(&config, &proof, &initial_regs, &final_regs).expect("verification failed");
+    }
+}
+
+/// The constraint failures of `inst` (writing to `r3`) on `7 / 2` with the division witnessed as
+/// `7 = 2 * 2 + 3`, which satisfies the product check but not `remainder < src2`.
+fn forged_division_failures(inst: Instruction) -> ConstraintReport<Challenge> {
+    assert_eq!(inst.dest, 3);
+    let initial_regs = [7, 2, 0, 0];
+    let (quotient, remainder) = (2, 3);
+    let forged = if inst.op == Opcode::DIVU {
+        quotient
+    } else {
+        remainder
+    };
+    let mut trace = AluChip::generate_trace(&[inst, Instruction::nop()], initial_regs);
+    let row: &mut AluRow<F> = trace.row_mut(0).borrow_mut();
+    row.result = [F::from_u32(forged), F::ZERO];
+    row.result_bits = core::array::from_fn(|i| F::from_bool(forged >> i & 1 == 1));
+    row.quotient = [F::from_u32(quotient), F::ZERO];
+    row.quotient_bits = core::array::from_fn(|i| F::from_bool(quotient >> i & 1 == 1));
+    row.remainder = [F::from_u32(remainder), F::ZERO];
+    row.remainder_bits = core::array::from_fn(|i| F::from_bool(remainder >> i & 1 == 1));
+    // remainder - src2 = 1 with no borrow.
+    row.diff = [F::ONE, F::ZERO];
+    row.diff_bits = core::array::from_fn(|i| F::from_bool(i == 0));
+    row.borrow = [F::ZERO; 2];
+    let row: &mut AluRow<F> = trace.row_mut(1).borrow_mut();
+    row.regs[3] = [F::from_u32(forged), F::ZERO];
+    let final_regs = [7, 2, 0, forged];
+    constraint_failures(&trace, &initial_regs, &final_regs)
+}
+
+#[test]
+fn test_forged_divu() {
+    let report = forged_division_failures(Instruction::divu(3, 0, 1));
+    assert_eq!(report.failing_rows("division remainder"), [0]);
+    assert_eq!(report.failures.len(), 1);
+}
+
+#[test]
+fn test_forged_remu() {
+    let report = forged_division_failures(Instruction::remu(3, 0, 1));
+    assert_eq!(report.failing_rows("division remainder"), [0]);
+    assert_eq!(report.failures.len(), 1);
+}
+
+#[test]
+fn test_forged_div_by_zero() {
+    let report = forged_result_failures(Instruction::divu(3, 0, 1), [7, 0, 0, 0], 0);
+    assert_eq!(report.failing_rows("division result"), [0]);
+}
+
+#[test]
+fn test_immediates() {
+    let config = alu_test_config();
+    // Computes 10! mod 2^32 from all-zero registers.
+    let mut program = vec![Instruction::loadi(0, 1), Instruction::loadi(1, 1)];
+    for _ in 0..10 {
+        program.push(Instruction::mul(0, 0, 1));
+        program.push(Instruction::addi(1, 1, 1));
+    }
+    program.push(Instruction::mov(2, 0));
+    program.push(Instruction::addi(3, 2, u32::MAX)); // r3 = r2 - 1
+    let initial_regs = [0; 4];
+    let (proof, final_regs) = prove_program(&config, &program, initial_regs);
+    assert_eq!(final_regs, [3_628_800, 11, 3_628_800, 3_628_799]);
+    verify_program(&config, &proof, &initial_regs, &final_regs).expect("verification failed");
+}
+
+#[test]
+fn test_forged_loadi() {
+    let report = forged_result_failures(Instruction::loadi(3, 42), [0; 4], 43);
+    assert_eq!(report.failing_rows("moves"), [0]);
+}
+
+#[test]
+fn test_forged_mov() {
+    let report = forged_result_failures(Instruction::mov(3, 0), [7, 0, 0, 0], 8);
+    assert_eq!(report.failing_rows("moves"), [0]);
+}
+
+#[test]
+fn test_register_counts() {
+    let config = alu_test_config();
+    // Sums 1..=15 into the last register, writing every other register along the way.
+    let mut program: Vec<_> = (1..16).map(|i| Instruction::loadi(i, i as u32)).collect();
+    program.extend((1..15).map(|i| Instruction::add(15, 15, i)));
+    let initial_regs = [0; 16];
+    let (proof, final_regs) = prove_program(&config, &program, initial_regs);
+    assert_eq!(final_regs[15], 120);
+    verify_program(&config, &proof, &initial_regs, &final_regs).expect("verification failed");
+
+    let program = [Instruction::mov(1, 0)];
+    let initial_regs = [9, 0];
+    let (proof, final_regs) = prove_program(&config, &program, initial_regs);
+    assert_eq!(final_regs, [9, 9]);
+    verify_program(&config, &proof, &initial_regs, &final_regs).expect("verification failed");
+}
+
+#[test]
+#[should_panic(expected = "Invalid dest register")]
+fn test_register_out_of_range() {
+    AluChip::generate_trace::<F>(&[Instruction::loadi(4, 1)], [0; 4]);
+}
+
+#[test]
+fn test_branches() {
+    let config = alu_test_config();
+    // Computes the 10th Fibonacci number in a loop, halting by branching past the end.
+    let program = [
+        Instruction::loadi(3, 10),
+        Instruction::beq(2, 3, 6),
+        Instruction::add(0, 0, 1),
+        Instruction::sub(1, 0, 1),
+        Instruction::addi(2, 2, 1),
+        Instruction::jmp(1),
+    ];
+    let initial_regs = [0, 1, 0, 0];
+    let (proof, final_regs) = prove_program(&config, &program, initial_regs);
+    assert_eq!(final_regs, [55, 34, 10, 10]);
+    verify_program(&config, &proof, &initial_regs, &final_regs).expect("verification failed");
+
+    // BNE falls through on equal operands and skips the LOADI otherwise.
+    let program = [Instruction::bne(0, 1, 2), Instruction::loadi(2, 7)];
+    for (initial_regs, expected) in [([5, 5, 0, 0], 7), ([5, 0x0001_0005, 0, 0], 0)] {
+        let (proof, final_regs) = prove_program(&config, &program, initial_regs);
+        assert_eq!(final_regs[2], expected, "BNE on {initial_regs:?}");
+        verify_program(&config, &proof, &initial_regs, &final_regs).expect("verification failed");
+    }
+}
+
+#[test]
+#[should_panic(expected = "did not halt")]
+fn test_non_halting_program() {
+    execute(&[Instruction::jmp(0)], [0; 4]);
+}
+
+#[test]
+fn test_forged_branch() {
+    // r0 == r1, so the BNE must fall through to the ADDI. Take the trace of a JMP over it and
+    // relabel the JMP as the BNE, claiming its operands differ.
+    let initial_regs = [5, 5, 0, 0];
+    let program = [
+        Instruction::jmp(2),
+        Instruction::addi(2, 2, 1),
+        Instruction::nop(),
+    ];
+    let mut trace = AluChip::generate_trace(&program, initial_regs);
+    let row: &mut AluRow<F> = trace.row_mut(0).borrow_mut();
+    row.op_jmp = F::ZERO;
+    row.op_bne = F::ONE;
+    row.src2 = [F::ZERO, F::ONE, F::ZERO, F::ZERO];
+    row.inst = F::from_u32(Instruction::bne(0, 1, 2).encode()[0]);
+    let report = constraint_failures(&trace, &initial_regs, &initial_regs);
+    assert_eq!(report.failing_rows("equality"), [0]);
+    assert!(
+        report.failures.iter().all(|f| f.label == Some("equality")),
+        "{report}"
+    );
+}
+
+#[test]
+fn test_order_branches() {
+    let config = alu_test_config();
+    let minus_one = -1i32 as u32;
+    // Each branch skips the LOADI when taken.
+    let cases = [
+        (Opcode::BLT, [minus_one, 1], true),
+        (Opcode::BLT, [1, minus_one], false),
+        (Opcode::BLT, [5, 5], false),
+        (Opcode::BGE, [5, 5], true),
+        (Opcode::BGE, [minus_one, 1], false),
+        (Opcode::BLTU, [1, minus_one], true),
+        (Opcode::BLTU, [0x0001_0000, 0xFFFF], false),
+        (Opcode::BGEU, [minus_one, 1], true),
+        (Opcode::BGEU, [5, 6], false),
+    ];
+    for (op, [a, b], taken) in cases {
+        let program = [Instruction::branch(op, 0, 1, 2), Instruction::loadi(2, 7)];
+        let initial_regs = [a, b, 0, 0];
+        let (proof, final_regs) = prove_program(&config, &program, initial_regs);
+        let expected = if taken { 0 } else { 7 };
+        assert_eq!(final_regs[2], expected, "{op:?} on {a:#x}, {b:#x}");
+        verify_program(&config, &proof, &initial_regs, &final_regs).expect("verification failed");
+    }
+}
+
+#[test]
+fn test_forged_order_branch() {
+    // r0 == r1, so the BLT must fall through to the ADDI. Take the trace of a JMP over it and
+    // relabel the JMP as the BLT, claiming r0 < r1.
+    let initial_regs = [5, 5, 0, 0];
+    let program = [
+        Instruction::jmp(2),
+        Instruction::addi(2, 2, 1),
+        Instruction::nop(),
+    ];
+    let mut trace = AluChip::generate_trace(&program, initial_regs);
+    let row: &mut AluRow<F> = trace.row_mut(0).borrow_mut();
+    row.op_jmp = F::ZERO;
+    row.op_blt = F::ONE;
+    row.src2 = [F::ZERO, F::ONE, F::ZERO, F::ZERO];
+    row.inst = F::from_u32(Instruction::blt(0, 1, 2).encode()[0]);
+    row.result[0] = F::ONE;
+    row.result_bits[0] = F::ONE;
+    let report = constraint_failures(&trace, &initial_regs, &initial_regs);
+    assert_eq!(report.failing_rows("less than"), [0]);
+    assert!(
+        report.failures.iter().all(|f| f.label == Some("less than")),
+        "{report}"
+    );
+}
+
+#[test]
+fn test_forged_branch_target() {
+    // Claim the JMP lands on the ADDI it skips.
+    let initial_regs = [0; 4];
+    let program = [
+        Instruction::jmp(2),
+        Instruction::addi(0, 0, 1),
+        Instruction::nop(),
+    ];
+    let mut trace = AluChip::generate_trace(&program, initial_regs);
+    let row: &mut AluRow<F> = trace.row_mut(0).borrow_mut();
+    row.imm = [F::ONE, F::ZERO];
+    let report = constraint_failures(&trace, &initial_regs, &initial_regs);
+    assert_eq!(report.failing_rows("transition"), [0]);
+    assert_eq!(report.failures.len(), 1);
+}
+
+#[test]
+fn test_memory() {
+    let config = alu_test_config();
+    let program = [
+        Instruction::loadi(0, 0xFFFF_FFFE),
+        Instruction::store(0, 1, 3), // mem[1] = r1 = 7, the address wraps around
+        Instruction::store(0, 2, 0x0001_0002), // mem[0x10000] = r2 = 9
+        Instruction::load(3, 1, 0xFFFF_FFFA), // r3 = mem[r1 - 6] = mem[1] = 7
+        Instruction::addi(3, 3, 1),
+        Instruction::store(0, 3, 3), // mem[1] = r3 = 8, overwriting the 7
+        Instruction::loadi(2, 0),
+        Instruction::load(1, 0, 3),           // r1 = mem[1] = 8
+        Instruction::load(2, 2, 0x0001_0000), // r2 = mem[0x10000] = 9
+        Instruction::load(0, 0, 5),           // r0 = mem[3] = 0, never written
+    ];
+    let initial_regs = [0, 7, 9, 0];
+    let (proof, final_regs) = prove_program(&config, &program, initial_regs);
+    assert_eq!(final_regs, [0, 8, 9, 8]);
+    verify_program(&config, &proof, &initial_regs, &final_regs).expect("verification failed");
+
+    let chip = RomAluChip::new(program.to_vec());
+    let (proof, final_regs) = prove_rom_program(&config, &chip, initial_regs);
+    assert_eq!(final_regs, [0, 8, 9, 8]);
+    verify_rom_program(&config, &chip, &proof, &initial_regs, &final_regs)
+        .expect("verification failed");
+}
+
+#[test]
+fn test_forged_load() {
+    // Memory starts out zero. The LOAD's row still agrees with the memory table's, which reads
+    // zero, so only the permutation between them can catch it.
+    let report = forged_result_failures(Instruction::load(3, 0, 0), [0; 4], 1);
+    assert_eq!(report.failing_rows("memory permutation"), [1]);
+    assert_eq!(report.failures.len(), 1);
+}
+
+#[test]
+fn test_forged_memory_table() {
+    // Claim the LOAD reads 6 back from the word the STORE set to 5, in both the LOAD's row and
+    // the memory table, so that only the memory table's read check can catch it.
+    let program = [Instruction::store(0, 1, 0), Instruction::load(3, 0, 0)];
+    let initial_regs = [0, 5, 0, 0];
+    let forged = 6;
+    let mut trace = AluChip::generate_trace(&program, initial_regs);
+    let row: &mut AluRow<F> = trace.row_mut(1).borrow_mut();
+    row.result[0] = F::from_u32(forged);
+    row.result_bits = core::array::from_fn(|i| F::from_bool(forged >> i & 1 == 1));
+    // The memory table lists the STORE and then the LOAD.
+    row.mem.value[0] = F::from_u32(forged);
+    let report = constraint_failures(&trace, &initial_regs, &[0, 5, 0, forged]);
+    assert_eq!(report.failing_rows("memory table"), [0]);
+    assert!(
+        report
+            .failures
+            .iter()
+            .all(|f| f.label == Some("memory table")),
+        "{report}"
+    );
+}
+
+#[test]
+fn test_encoding() {
+    let program = [
+        Instruction::add(1, 2, 3),
+        Instruction::not(31, 0),
+        Instruction::addi(0, 1, u32::MAX),
+        Instruction::loadi(2, 1 << 31),
+        Instruction::beq(1, 2, 7),
+        Instruction::load(3, 2, 8),
+        Instruction::store(2, 3, 8),
+        Instruction::nop(),
+    ];
+    for inst in program {
+        let [word, imm] = inst.encode();
+        assert!(word < 1 << 20, "{inst}");
+        assert_eq!(imm, inst.imm);
+        assert_eq!(Instruction::decode([word, imm]), Some(inst));
+    }
+    assert_eq!(
+        Instruction::add(1, 2, 3).encode(),
+        [0b00011_00010_00001_00000, 0]
+    );
+    assert_eq!(Instruction::nop().encode()[0], Opcode::NOP as u32);
+    // An instruction writing no register encodes dest as zero.
+    let store = Instruction {
+        dest: 3,
+        ..Instruction::store(1, 2, 0)
+    };
+    assert_eq!(
+        Instruction::decode(store.encode()),
+        Some(Instruction::store(1, 2, 0))
+    );
+
+    assert_eq!(Instruction::decode([zkalu::NUM_OPCODES as u32, 0]), None);
+    assert_eq!(Instruction::decode([1 << 20, 0]), None);
+}
+
+#[test]
+#[should_panic(expected = "Register does not fit in an instruction word")]
+fn test_encode_register_out_of_range() {
+    Instruction::add(32, 0, 0).encode();
+}
+
+#[test]
+fn test_forged_instruction_word() {
+    let program = [Instruction::add(2, 0, 1), Instruction::nop()];
+    let initial_regs = [3, 2, 0, 0];
+    let mut trace = AluChip::generate_trace(&program, initial_regs);
+    // Claim the first row runs SUB while its selectors still say ADD.
+    let row: &mut AluRow<F> = trace.row_mut(0).borrow_mut();
+    row.inst = F::from_u32(Instruction::sub(2, 0, 1).encode()[0]);
+    let final_regs = execute(&program, initial_regs);
+    let report = constraint_failures(&trace, &initial_regs, &final_regs);
+    assert_eq!(report.failing_rows("instruction word"), [0]);
+    assert_eq!(report.failures.len(), 1);
+}
+
+/// A ROM of a loop summing 1..=10 into r0, which runs for more steps than the ROM has rows.
+fn rom_loop_chip() -> RomAluChip<4> {
+    RomAluChip::new(vec![
+        Instruction::loadi(1, 10),
+        Instruction::beq(1, 3, 5), // halt once r1 reaches zero
+        Instruction::add(0, 0, 1),
+        Instruction::addi(1, 1, u32::MAX),
+        Instruction::jmp(1),
+    ])
+}
+
+/// The constraints of `chip` which `trace` violates under fixed permutation challenges, with the
+/// public values of `initial_regs` and `final_regs`.
+fn rom_constraint_failures(
+    chip: &RomAluChip<4>,
+    trace: &RowMajorMatrix<F>,
+    initial_regs: &[u32; 4],
+    final_regs: &[u32; 4],
+) -> ConstraintReport<Challenge> {
+    find_constraint_failures_with_permutation(
+        chip,
+        trace,
+        &[7, 11, 13, 17].map(Challenge::from_u32),
+        &AluChip::public_values(initial_regs, final_regs),
+    )
+}
+
+#[test]
+fn test_rom_branches() {
+    let config = alu_test_config();
+    let chip = rom_loop_chip();
+    let initial_regs = [0; 4];
+    let (proof, final_regs) = prove_rom_program(&config, &chip, initial_regs);
+    assert_eq!(final_regs, [55, 0, 0, 0]);
+    assert!(1 << proof.degree_bits() > chip.height());
+    verify_rom_program(&config, &chip, &proof, &initial_regs, &final_regs)
+        .expect("verification failed");
+}
+
+#[test]
+#[cfg_attr(
+    debug_assertions,
+    should_panic(expected = "constraints had nonzero value on row 7")
+)]
+fn test_rom_cut_short() {
+    let config = alu_test_config();
+    let chip = rom_loop_chip();
+    let initial_regs = [0; 4];
+    // Keep the first rows of the loop and claim the registers it has reached as the result.
+    let full = chip.generate_trace::<F>(initial_regs);
+    let (width, height) = (full.width(), chip.height());
+    let mut trace = RowMajorMatrix::new(full.values[..height * width].to_vec(), width);
+    let next: &AluRow<F> = full.values[height * width..(height + 1) * width].borrow();
+    let final_regs = next
+        .regs
+        .map(|[lo, hi]| lo.as_canonical_u32() | hi.as_canonical_u32() << 16);
+    assert_ne!(final_regs, execute(chip.program(), initial_regs));
+    let pcs: Vec<usize> = trace
+        .values
+        .chunks_exact(width)
+        .map(|row| {
+            let row: &AluRow<F> = row.borrow();
+            row.pc.as_canonical_u32() as usize
+        })
+        .collect();
+    for r in 0..height {
+        let row: &mut AluRow<F> = trace.row_mut(r).borrow_mut();
+        row.rom_multiplicity = F::from_usize(pcs.iter().filter(|&&pc| pc == r).count());
+    }
+
+    let report = rom_constraint_failures(&chip, &trace, &initial_regs, &final_regs);
+    assert_eq!(report.failing_rows("rom halt"), [height - 1]);
+    assert_eq!(report.failures.len(), 1, "{report}");
+
+    // Debug builds refuse to prove the trace; release builds prove it for the verifier to reject.
+    let (rom_data, _) = setup_preprocessed(&config, &chip).unwrap();
+    let public_values = AluChip::public_values(&initial_regs, &final_regs);
+    let proof = prove_with_preprocessed(&config, &chip, trace, &public_values, Some(&rom_data));
+    assert!(verify_rom_program(&config, &chip, &proof, &initial_regs, &final_regs).is_err());
+}
+
+#[test]
+#[should_panic(expected = "Branch target past the halting pc")]
+fn test_rom_rejects_far_branches() {
+    RomAluChip::<4>::new(vec![Instruction::jmp(3), Instruction::nop()]);
+}
+
+#[test]
+fn test_rom_program() {
+    let config = alu_test_config();
+    let chip = RomAluChip::new(sample_program()[..5].to_vec());
+    let initial_regs = [1, 2, 5, 0];
+    let (proof, final_regs) = prove_rom_program(&config, &chip, initial_regs);
+    assert_eq!(final_regs, execute(chip.program(), initial_regs));
+    verify_rom_program(&config, &chip, &proof, &initial_regs, &final_regs)
+        .expect("verification failed");
+}
+
+#[test]
+fn test_rom_wrong_program() {
+    let config = alu_test_config();
+    let chip = RomAluChip::new(vec![Instruction::add(2, 0, 1)]);
+    let initial_regs = [1, 2, 0, 0];
+    let (proof, final_regs) = prove_rom_program(&config, &chip, initial_regs);
+    // A program with the same effect, but not the one the proof was made for.
+    let other_chip = RomAluChip::new(vec![Instruction::add(2, 1, 0)]);
+    assert_eq!(execute(other_chip.program(), initial_regs), final_regs);
+    assert!(verify_rom_program(&config, &other_chip, &proof, &initial_regs, &final_regs).is_err());
+}
+
+#[test]
+fn test_rom_forged_instruction() {
+    let chip = RomAluChip::<4>::new(vec![Instruction::add(2, 0, 1), Instruction::nop()]);
+    // Run a valid trace of a different program against the ROM. Its fetches are not in the ROM,
+    // so the lookup's running sum ends away from zero on the last row.
+    let program = [Instruction::sub(2, 0, 1), Instruction::nop()];
+    let initial_regs = [3, 2, 0, 0];
+    let trace = RomAluChip::<4>::new(program.to_vec()).generate_trace(initial_regs);
+    let final_regs = execute(&program, initial_regs);
+    let report = rom_constraint_failures(&chip, &trace, &initial_regs, &final_regs);
+    assert_eq!(report.failing_rows("rom fetch"), [3]);
+    assert_eq!(report.failures.len(), 1, "{report}");
+}
+
+#[test]
+fn test_wrong_final_regs() {
+    let config = alu_test_config();
+    let initial_regs = [1, 2, 5, 0];
+    let (proof, mut final_regs) = prove_program(&config, &sample_program(), initial_regs);
+    final_regs[2] += 1;
+    assert!(verify_program(&config, &proof, &initial_regs, &final_regs).is_err());
+}
+
+#[test]
+fn test_wrong_initial_regs() {
+    let config = alu_test_config();
+    let initial_regs = [1, 2, 5, 0];
+    let (proof, final_regs) = prove_program(&config, &sample_program(), initial_regs);
+    let claimed_initial = [1, 2, 5, 1];
+    assert!(verify_program(&config, &proof, &claimed_initial, &final_regs).is_err());
+}
+
+#[test]
+fn test_forged_final_regs() {
+    let initial_regs = [1, 2, 5, 0];
+    let program = sample_program();
+    let trace = AluChip::generate_trace(&program, initial_regs);
+    let mut final_regs = execute(&program, initial_regs);
+    final_regs[0] += 1;
+    let report = constraint_failures(&trace, &initial_regs, &final_regs);
+    assert_eq!(report.failing_rows("final registers"), [63]);
+    assert_eq!(report.failures.len(), 1);
+}
+
+#[test]
+fn test_adder() {
+    let config = alu_test_config();
+    let program: Vec<_> = [
+        Instruction::add(2, 0, 1),
+        Instruction::add(3, 2, 2),
+        Instruction::add(0, 3, 1),
+        Instruction::add(1, 1, 1),
+    ]
+    .into_iter()
+    .cycle()
+    .take(16)
+    .collect();
+    let trace = AdderChip::generate_trace(&program, [1, 2, 0, 0].map(F::from_u32));
+    let proof = prove(&config, &AdderChip, trace, &vec![]);
+    verify(&config, &AdderChip, &proof, &vec![]).expect("verification failed");
+}
+
+#[test]
+fn test_tampered_trace() {
+    let mut trace = AluChip::generate_trace(&sample_program(), [1, 2, 5, 0]);
+    // Overwrite r0 on the second row; the first ADD no longer produces it.
+    trace.values[zkalu::NUM_ALU_COLS] += F::ONE;
+    let initial_regs = [1, 2, 5, 0];
+    let final_regs = execute(&sample_program(), initial_regs);
+    let report = constraint_failures(&trace, &initial_regs, &final_regs);
+    assert_eq!(report.failing_rows("transition"), [0, 1]);
+    assert_eq!(report.failing_rows("operands"), [1]);
+}
+
+#[test]
+fn test_tampered_mul_result() {
+    let program = [Instruction::mul(0, 0, 1), Instruction::add(1, 0, 0)];
+    let initial_regs = [3, 4, 0, 0];
+    let mut trace = AluChip::generate_trace(&program, initial_regs);
+    // Claim 3 * 4 = 13 in the result column of the first row.
+    let row: &mut AluRow<F> = trace.row_mut(0).borrow_mut();
+    row.result[0] += F::ONE;
+    let final_regs = execute(&program, initial_regs);
+    let report = constraint_failures(&trace, &initial_regs, &final_regs);
+    assert_eq!(report.failing_rows("mul"), [0]);
+}
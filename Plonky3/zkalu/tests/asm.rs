@@ -0,0 +1,255 @@
+use zkalu::config::alu_test_config;
+use zkalu::{
+    AsmError, AsmErrorKind, Instruction, Opcode, assemble, disassemble, execute, prove_program,
+    verify_program,
+};
+
+/// A program using every instruction form, with branches forwards, backwards and to the end.
+fn sample_program() -> Vec<Instruction> {
+    vec![
+        Instruction::loadi(1, 3),
+        Instruction::add(0, 0, 1),
+        Instruction::addi(1, 1, (-1i32) as u32),
+        Instruction::bne(1, 2, 1),
+        Instruction::not(3, 0),
+        Instruction::mov(2, 3),
+        Instruction::store(2, 0, 8),
+        Instruction::load(3, 2, (-4i32) as u32),
+        Instruction::beq(0, 0, 10),
+        Instruction::nop(),
+        Instruction::jmp(12),
+        Instruction::remu(3, 1, 0),
+    ]
+}
+
+const SAMPLE_TEXT: &str = "    loadi r1, 3
+L1:
+    add r0, r0, r1
+    addi r1, r1, -1
+    bne r1, r2, L1
+    not r3, r0
+    mov r2, r3
+    store r0, 8(r2)
+    load r3, -4(r2)
+    beq r0, r0, L10
+    nop
+L10:
+    jmp L12
+    remu r3, r1, r0
+L12:
+";
+
+#[test]
+fn test_disassemble() {
+    assert_eq!(disassemble(&sample_program()), SAMPLE_TEXT);
+}
+
+#[test]
+fn test_round_trip() {
+    assert_eq!(assemble(SAMPLE_TEXT), Ok(sample_program()));
+
+    let every_opcode: Vec<_> = Opcode::ALL
+        .into_iter()
+        .map(|op| match op {
+            Opcode::NOT => Instruction::not(1, 2),
+            Opcode::MOV => Instruction::mov(1, 2),
+            Opcode::ADDI | Opcode::LOAD => Instruction::with_imm(op, 1, 2, 0x8000_0000),
+            Opcode::SLLI | Opcode::SRLI | Opcode::SRAI => Instruction::with_imm(op, 1, 2, 31),
+            Opcode::LOADI => Instruction::loadi(1, u32::MAX),
+            Opcode::JMP => Instruction::jmp(100),
+            Opcode::BEQ => Instruction::beq(1, 2, 0),
+            Opcode::BNE => Instruction::bne(1, 2, 21),
+            Opcode::BLT | Opcode::BGE | Opcode::BLTU | Opcode::BGEU => {
+                Instruction::branch(op, 1, 2, 5)
+            }
+            Opcode::STORE => Instruction::store(1, 2, 7),
+            Opcode::NOP => Instruction::nop(),
+            op => Instruction::new(op, 1, 2, 3),
+        })
+        .collect();
+    let text = disassemble(&every_opcode);
+    assert_eq!(assemble(&text), Ok(every_opcode));
+    assert!(text.contains("jmp 100\n"), "{text}");
+}
+
+#[test]
+fn test_round_trip_non_canonical() {
+    // Each sets a field its opcode does not use, which the mnemonic form would drop.
+    let non_canonical = vec![
+        Instruction::new(Opcode::NOT, 1, 2, 3),
+        Instruction::new(Opcode::MOV, 1, 2, 3),
+        Instruction {
+            imm: 5,
+            ..Instruction::add(1, 2, 3)
+        },
+        Instruction {
+            src2: 3,
+            ..Instruction::addi(1, 2, 4)
+        },
+        Instruction {
+            src1: 2,
+            ..Instruction::loadi(1, 4)
+        },
+        Instruction {
+            dest: 1,
+            ..Instruction::jmp(0)
+        },
+        Instruction {
+            dest: 1,
+            ..Instruction::beq(2, 3, 0)
+        },
+        Instruction {
+            dest: 1,
+            ..Instruction::store(2, 3, 4)
+        },
+        Instruction::with_imm(Opcode::NOP, 1, 2, 4),
+    ];
+    let text = disassemble(&non_canonical);
+    assert_eq!(assemble(&text), Ok(non_canonical.clone()));
+    for inst in non_canonical {
+        assert_eq!(assemble(&inst.to_string()), Ok(vec![inst]));
+    }
+    assert_eq!(
+        Instruction::new(Opcode::NOT, 1, 2, 3).to_string(),
+        ".inst not, r1, r2, r3, 0"
+    );
+    assert_eq!(
+        assemble("    .INST jmp, r0, r0, r0, 3"),
+        Ok(vec![Instruction::jmp(3)])
+    );
+}
+
+#[test]
+fn test_display() {
+    assert_eq!(Instruction::beq(0, 1, 7).to_string(), "beq r0, r1, 7");
+    assert_eq!(
+        Instruction::addi(0, 1, (-7i32) as u32).to_string(),
+        "addi r0, r1, -7"
+    );
+    assert_eq!(
+        Instruction::loadi(0, u32::MAX).to_string(),
+        "loadi r0, 4294967295"
+    );
+    assert_eq!(Instruction::store(1, 2, 4).to_string(), "store r2, 4(r1)");
+}
+
+#[test]
+fn test_syntax() {
+    let source = "
+        # Sum 0x3 + 2 + 1.
+        start: LOADI r1, 0x3 ; comments may follow instructions
+        loop:
+        Add r0, r0, r1
+        addi r1,r1,-0x1
+        bne r1, r2, loop
+        done: end:
+        load r3, (r2)
+    ";
+    let program = assemble(source).unwrap();
+    assert_eq!(
+        program,
+        vec![
+            Instruction::loadi(1, 3),
+            Instruction::add(0, 0, 1),
+            Instruction::addi(1, 1, u32::MAX),
+            Instruction::bne(1, 2, 1),
+            Instruction::load(3, 2, 0),
+        ]
+    );
+    assert_eq!(execute(&program, [0, 0, 0, 0]), [6, 0, 0, 0]);
+    assert_eq!(assemble("loadi r0, -2147483648").unwrap()[0].imm, 1 << 31);
+    assert_eq!(assemble("jmp end\nend:"), Ok(vec![Instruction::jmp(1)]));
+}
+
+fn assert_error(source: &str, line: usize, column: usize, kind: AsmErrorKind) {
+    assert_eq!(assemble(source), Err(AsmError { line, column, kind }));
+}
+
+#[test]
+fn test_errors() {
+    assert_error(
+        "nop\n  frob r0, r1",
+        2,
+        3,
+        AsmErrorKind::UnknownMnemonic("frob".to_string()),
+    );
+    assert_error(
+        "add r0, r1",
+        1,
+        1,
+        AsmErrorKind::WrongOperandCount {
+            expected: 3,
+            found: 2,
+        },
+    );
+    assert_error("add r0, r1, 5", 1, 13, AsmErrorKind::ExpectedRegister);
+    assert_error("addi r0, r1, 5x", 1, 14, AsmErrorKind::InvalidImmediate);
+    assert_error(
+        "loadi r0, 0x100000000",
+        1,
+        11,
+        AsmErrorKind::InvalidImmediate,
+    );
+    assert_error(
+        "loadi r0, -2147483649",
+        1,
+        11,
+        AsmErrorKind::InvalidImmediate,
+    );
+    assert_error("load r0, r1", 1, 10, AsmErrorKind::ExpectedMemoryOperand);
+    assert_error("9lives: nop", 1, 1, AsmErrorKind::InvalidLabel);
+    assert_error(
+        ".inst nope, r0, r0, r0, 0",
+        1,
+        7,
+        AsmErrorKind::UnknownMnemonic("nope".to_string()),
+    );
+    assert_error(
+        ".inst nop, r0, r0, r0",
+        1,
+        1,
+        AsmErrorKind::WrongOperandCount {
+            expected: 5,
+            found: 4,
+        },
+    );
+    assert_error(
+        "a: nop\n  a: nop",
+        2,
+        3,
+        AsmErrorKind::DuplicateLabel("a".to_string()),
+    );
+    assert_error(
+        "nop\nnop\n    jmp nowhere",
+        3,
+        9,
+        AsmErrorKind::UnknownLabel("nowhere".to_string()),
+    );
+
+    let error = assemble("nop\n  frob r0").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "line 2, column 3: unknown mnemonic `frob`"
+    );
+}
+
+#[test]
+fn test_prove_assembled_program() {
+    let program = assemble(
+        "
+            loadi r1, 5
+        loop:
+            add r0, r0, r1
+            addi r1, r1, -1
+            bne r1, r2, loop
+            store r0, 0(r2)
+            load r3, 0(r2)
+        ",
+    )
+    .unwrap();
+    let config = alu_test_config();
+    let initial_regs = [0; 4];
+    let (proof, final_regs) = prove_program(&config, &program, initial_regs);
+    assert_eq!(final_regs, [15, 0, 0, 15]);
+    verify_program(&config, &proof, &initial_regs, &final_regs).expect("verification failed");
+}
@@ -0,0 +1,101 @@
+use std::collections::HashSet;
+
+use p3_baby_bear::BabyBear;
+use p3_field::PrimeCharacteristicRing;
+use p3_matrix::dense::RowMajorMatrix;
+use zkalu::{AluChip, Instruction, NUM_ALU_COLS, num_alu_cols};
+
+type F = BabyBear;
+
+fn sample_trace() -> RowMajorMatrix<F> {
+    let program = [
+        Instruction::add(0, 0, 1),
+        Instruction::store(2, 0, 8),
+        Instruction::bne(0, 1, 4),
+        Instruction::nop(),
+    ];
+    AluChip::<4>::generate_trace(&program, [1, 2, 5, 0])
+}
+
+#[test]
+fn test_column_names() {
+    let names = AluChip::<4>::column_names();
+    assert_eq!(names.len(), NUM_ALU_COLS);
+    assert!(names.iter().all(|name| !name.is_empty()));
+    assert_eq!(names.iter().collect::<HashSet<_>>().len(), names.len());
+    assert_eq!(
+        &names[..12],
+        [
+            "r0_lo", "r0_hi", "r1_lo", "r1_hi", "r2_lo", "r2_hi", "r3_lo", "r3_hi", "pc", "clk",
+            "inst", "dest_0"
+        ]
+    );
+    assert!(names.contains(&"op_add".to_string()));
+    assert!(names.contains(&"src1_bits_31".to_string()));
+    assert_eq!(names.last().unwrap(), "mem_delta_bits_23");
+
+    assert_eq!(AluChip::<16>::column_names().len(), num_alu_cols::<16>());
+}
+
+#[test]
+fn test_trace_to_csv() {
+    let csv = AluChip::<4>::trace_to_csv(&sample_trace());
+    let lines: Vec<_> = csv.lines().collect();
+    assert_eq!(lines.len(), 1 + 4);
+
+    let header: Vec<_> = lines[0].split(',').collect();
+    assert_eq!(header.len(), 2 + NUM_ALU_COLS);
+    assert_eq!(header[..3], ["row", "instruction", "r0_lo"]);
+    let column = |name: &str| header.iter().position(|&h| h == name).unwrap();
+
+    assert!(lines[1].starts_with("0,\"add r0, r0, r1\",1,0,2,0,5,0,0,0,0,0,"));
+    // Split the quoted instruction off before splitting the values.
+    let values = |line: &str| -> Vec<u64> {
+        let (_, values) = line.rsplit_once('"').unwrap();
+        values[1..].split(',').map(|v| v.parse().unwrap()).collect()
+    };
+    let row1 = values(lines[2]);
+    assert_eq!(row1[column("op_store") - 2], 1);
+    assert_eq!(row1[column("r0_lo") - 2], 3);
+    // The memory table is sorted independently, so the store is on its first row.
+    let row0 = values(lines[1]);
+    assert_eq!(row0[column("mem_is_real") - 2], 1);
+    assert_eq!(row0[column("mem_value_lo") - 2], 3);
+    assert!(lines[3].starts_with("2,\"bne r0, r1, 4\","));
+    assert!(lines[4].starts_with("3,\"nop\","));
+}
+
+#[test]
+fn test_trace_to_json() {
+    let json = AluChip::<4>::trace_to_json(&sample_trace());
+    assert!(json.starts_with(
+        "[\n  {\"row\": 0, \"instruction\": \"add r0, r0, r1\", \"r0_lo\": 1, \"r0_hi\": 0,"
+    ));
+    assert!(json.ends_with("}\n]\n"));
+    assert_eq!(json.matches("\"row\"").count(), 4);
+    assert!(json.contains("\"instruction\": \"store r0, 8(r2)\""));
+}
+
+#[test]
+fn test_tampered_row() {
+    let mut trace = sample_trace();
+    let inst = AluChip::<4>::column_names()
+        .iter()
+        .position(|name| name == "inst")
+        .unwrap();
+    let width = num_alu_cols::<4>();
+    trace.values[width + inst] = F::from_u32(31);
+
+    let csv = AluChip::<4>::trace_to_csv(&trace);
+    assert!(csv.lines().nth(2).unwrap().starts_with("1,,"));
+    assert!(AluChip::<4>::trace_to_json(&trace).contains("\"row\": 1, \"instruction\": null,"));
+
+    let text = AluChip::<4>::format_row(&trace, 1);
+    assert!(text.starts_with("row 1: pc=1 <invalid instruction> | r0=3 r1=2 r2=5 r3=0\n"));
+    assert!(text.contains("\n  inst = 31\n"));
+    assert!(text.contains("\n  op_store = 1\n"));
+    assert!(!text.contains("op_add"));
+
+    let text = AluChip::<4>::format_row(&trace, 0);
+    assert!(text.starts_with("row 0: pc=0 add r0, r0, r1 | r0=1 r1=2 r2=5 r3=0\n"));
+}
@@ -0,0 +1,563 @@
+use p3_baby_bear::BabyBear;
+use zkalu::config::alu_test_config;
+use zkalu::riscv::MAX_IMAGE_SIZE;
+use zkalu::riscv::{
+    BranchCondition, NUM_RISCV_REGISTERS, RiscvError, RiscvInstruction, RiscvMachine, RiscvOp,
+    RiscvProgram, Segment, Width,
+};
+use zkalu::{Instruction, execute, prove_rom_program, verify_rom_program};
+
+fn r_type(funct7: u32, rs2: u32, rs1: u32, funct3: u32, rd: u32) -> u32 {
+    funct7 << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | 0x33
+}
+
+fn i_type(imm: i32, rs1: u32, funct3: u32, rd: u32, opcode: u32) -> u32 {
+    (imm as u32) << 20 | rs1 << 15 | funct3 << 12 | rd << 7 | opcode
+}
+
+fn s_type(imm: i32, rs2: u32, rs1: u32, funct3: u32) -> u32 {
+    let imm = imm as u32;
+    (imm >> 5 & 0x7F) << 25 | rs2 << 20 | rs1 << 15 | funct3 << 12 | (imm & 0x1F) << 7 | 0x23
+}
+
+fn b_type(offset: i32, rs2: u32, rs1: u32, funct3: u32) -> u32 {
+    let imm = offset as u32;
+    (imm >> 12 & 1) << 31
+        | (imm >> 5 & 0x3F) << 25
+        | rs2 << 20
+        | rs1 << 15
+        | funct3 << 12
+        | (imm >> 1 & 0xF) << 8
+        | (imm >> 11 & 1) << 7
+        | 0x63
+}
+
+fn u_type(imm: u32, rd: u32, opcode: u32) -> u32 {
+    imm << 12 | rd << 7 | opcode
+}
+
+fn j_type(offset: i32, rd: u32) -> u32 {
+    let imm = offset as u32;
+    (imm >> 20 & 1) << 31
+        | (imm >> 1 & 0x3FF) << 21
+        | (imm >> 11 & 1) << 20
+        | (imm >> 12 & 0xFF) << 12
+        | rd << 7
+        | 0x6F
+}
+
+fn addi(rd: u32, rs1: u32, imm: i32) -> u32 {
+    i_type(imm, rs1, 0, rd, 0x13)
+}
+
+const ECALL: u32 = 0x0000_0073;
+
+fn to_bytes(words: &[u32]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+/// A minimal ELF executable with the given `(vaddr, file bytes, memory size, flags)` segments.
+fn build_elf(entry: u32, segments: &[(u32, &[u8], u32, u32)]) -> Vec<u8> {
+    let header_size = 52 + 32 * segments.len();
+    let mut elf = vec![0; header_size];
+    elf[..7].copy_from_slice(b"\x7fELF\x01\x01\x01");
+    elf[16..18].copy_from_slice(&2u16.to_le_bytes());
+    elf[18..20].copy_from_slice(&0xF3u16.to_le_bytes());
+    elf[20..24].copy_from_slice(&1u32.to_le_bytes());
+    elf[24..28].copy_from_slice(&entry.to_le_bytes());
+    elf[28..32].copy_from_slice(&52u32.to_le_bytes());
+    elf[40..42].copy_from_slice(&52u16.to_le_bytes());
+    elf[42..44].copy_from_slice(&32u16.to_le_bytes());
+    elf[44..46].copy_from_slice(&(segments.len() as u16).to_le_bytes());
+    for (i, &(vaddr, data, mem_size, flags)) in segments.iter().enumerate() {
+        let fields = [
+            1,
+            elf.len() as u32,
+            vaddr,
+            vaddr,
+            data.len() as u32,
+            mem_size,
+            flags,
+            4,
+        ];
+        let header = 52 + 32 * i;
+        for (j, field) in fields.into_iter().enumerate() {
+            elf[header + 4 * j..header + 4 * j + 4].copy_from_slice(&field.to_le_bytes());
+        }
+        elf.extend_from_slice(data);
+    }
+    elf
+}
+
+#[test]
+fn test_decode() {
+    let cases = [
+        (
+            0x0050_0093,
+            RiscvInstruction::OpImm {
+                op: RiscvOp::Add,
+                rd: 1,
+                rs1: 0,
+                imm: 5,
+            },
+            "addi x1, x0, 5",
+        ),
+        (
+            0x0020_81B3,
+            RiscvInstruction::Op {
+                op: RiscvOp::Add,
+                rd: 3,
+                rs1: 1,
+                rs2: 2,
+            },
+            "add x3, x1, x2",
+        ),
+        (
+            r_type(1, 7, 6, 4, 5),
+            RiscvInstruction::Op {
+                op: RiscvOp::Div,
+                rd: 5,
+                rs1: 6,
+                rs2: 7,
+            },
+            "div x5, x6, x7",
+        ),
+        (
+            i_type(0x403, 2, 5, 1, 0x13),
+            RiscvInstruction::OpImm {
+                op: RiscvOp::Sra,
+                rd: 1,
+                rs1: 2,
+                imm: 3,
+            },
+            "srai x1, x2, 3",
+        ),
+        (
+            u_type(0xFFFFF, 4, 0x37),
+            RiscvInstruction::Lui {
+                rd: 4,
+                imm: 0xFFFF_F000,
+            },
+            "lui x4, 0xfffff",
+        ),
+        (
+            j_type(-8, 1),
+            RiscvInstruction::Jal { rd: 1, offset: -8 },
+            "jal x1, -8",
+        ),
+        (
+            b_type(-4094, 9, 8, 7),
+            RiscvInstruction::Branch {
+                cond: BranchCondition::Geu,
+                rs1: 8,
+                rs2: 9,
+                offset: -4094,
+            },
+            "bgeu x8, x9, -4094",
+        ),
+        (
+            i_type(-1, 2, 4, 1, 0x03),
+            RiscvInstruction::Load {
+                width: Width::Byte,
+                unsigned: true,
+                rd: 1,
+                rs1: 2,
+                offset: -1,
+            },
+            "lbu x1, -1(x2)",
+        ),
+        (
+            s_type(-2048, 3, 2, 1),
+            RiscvInstruction::Store {
+                width: Width::Half,
+                rs1: 2,
+                rs2: 3,
+                offset: -2048,
+            },
+            "sh x3, -2048(x2)",
+        ),
+        (ECALL, RiscvInstruction::Ecall, "ecall"),
+    ];
+    for (word, inst, text) in cases {
+        assert_eq!(RiscvInstruction::decode(word), Some(inst), "{word:#010x}");
+        assert_eq!(inst.to_string(), text);
+    }
+
+    // An all-zero word, a 16-bit compressed instruction and an unknown funct7 are illegal.
+    for word in [0, 0x4501, r_type(0x02, 2, 1, 0, 3)] {
+        assert_eq!(RiscvInstruction::decode(word), None, "{word:#010x}");
+    }
+}
+
+#[test]
+fn test_machine_semantics() {
+    let code = [
+        addi(1, 0, -7),
+        addi(2, 0, 2),
+        r_type(1, 2, 1, 4, 3), // div x3, x1, x2
+        r_type(1, 2, 1, 6, 4), // rem x4, x1, x2
+        u_type(0x80000, 5, 0x37),
+        addi(6, 0, -1),
+        r_type(1, 6, 5, 4, 7),         // div x7, x5, x6 overflows
+        r_type(1, 6, 5, 6, 8),         // rem x8, x5, x6 overflows
+        r_type(1, 1, 1, 1, 9),         // mulh x9, x1, x1
+        r_type(1, 6, 6, 3, 10),        // mulhu x10, x6, x6
+        r_type(0x20, 2, 1, 5, 11),     // sra x11, x1, x2
+        r_type(0, 2, 1, 5, 12),        // srl x12, x1, x2
+        s_type(0x100, 1, 0, 0),        // sb x1, 0x100(x0)
+        i_type(0x100, 0, 0, 13, 0x03), // lb x13, 0x100(x0)
+        i_type(0x100, 0, 4, 14, 0x03), // lbu x14, 0x100(x0)
+        r_type(1, 0, 1, 5, 15),        // divu x15, x1, x0
+        addi(0, 0, 5),
+        ECALL,
+    ];
+    let program = RiscvProgram::from_flat(&to_bytes(&code), 0x1000);
+    let mut machine = RiscvMachine::new(&program, [1; NUM_RISCV_REGISTERS]);
+    assert_eq!(machine.run(), Ok(code.len() - 1));
+    assert_eq!(machine.pc, 0x1000 + 4 * (code.len() as u32 - 1));
+
+    let expected = [
+        (0, 0),
+        (1, -7i32 as u32),
+        (3, -3i32 as u32),
+        (4, -1i32 as u32),
+        (7, 0x8000_0000),
+        (8, 0),
+        (9, 0),
+        (10, 0xFFFF_FFFE),
+        (11, -2i32 as u32),
+        (12, 0x3FFF_FFFE),
+        (13, -7i32 as u32),
+        (14, 0xF9),
+        (15, u32::MAX),
+        (16, 1),
+    ];
+    for (reg, value) in expected {
+        assert_eq!(machine.regs[reg], value, "x{reg}");
+    }
+}
+
+#[test]
+fn test_machine_errors() {
+    let program = RiscvProgram::from_flat(&to_bytes(&[addi(1, 1, 1), 0]), 0);
+    let mut machine = RiscvMachine::new(&program, [0; NUM_RISCV_REGISTERS]);
+    assert_eq!(
+        machine.run(),
+        Err(RiscvError::IllegalInstruction { pc: 4, word: 0 })
+    );
+
+    let program = RiscvProgram::from_flat(&to_bytes(&[i_type(2, 0, 0, 0, 0x67)]), 0);
+    let mut machine = RiscvMachine::new(&program, [0; NUM_RISCV_REGISTERS]);
+    assert_eq!(machine.run(), Err(RiscvError::MisalignedPc { pc: 2 }));
+
+    // The ALU's memory holds a word per address, so a misaligned word access would not read the
+    // bytes the machine does. Generating the trace runs the machine first and fails.
+    let lw = i_type(2, 0, 2, 1, 0x03); // lw x1, 2(x0)
+    let program = RiscvProgram::from_flat(&to_bytes(&[lw, ECALL]), 0);
+    let mut machine = RiscvMachine::new(&program, [0; NUM_RISCV_REGISTERS]);
+    assert_eq!(
+        machine.run(),
+        Err(RiscvError::MisalignedAccess { pc: 0, addr: 2 })
+    );
+    assert!(program.to_alu_program().is_ok());
+    assert_eq!(
+        program.generate_trace::<BabyBear>([0; NUM_RISCV_REGISTERS]),
+        Err(RiscvError::MisalignedAccess { pc: 0, addr: 2 })
+    );
+}
+
+#[test]
+fn test_elf() {
+    let code = to_bytes(&[addi(1, 0, 42), s_type(0, 1, 2, 2), ECALL]);
+    let elf = build_elf(0x1_0000, &[(0x1_0000, &code, 12, 5), (0x2_0000, &[], 8, 6)]);
+    let program = RiscvProgram::from_elf(&elf).unwrap();
+    assert_eq!(
+        program,
+        RiscvProgram {
+            entry: 0x1_0000,
+            segments: vec![
+                Segment {
+                    addr: 0x1_0000,
+                    data: code.clone(),
+                    executable: true,
+                },
+                Segment {
+                    addr: 0x2_0000,
+                    data: vec![0; 8],
+                    executable: false,
+                },
+            ],
+        }
+    );
+
+    let mut regs = [0; NUM_RISCV_REGISTERS];
+    regs[2] = 0x2_0004;
+    let mut machine = RiscvMachine::new(&program, regs);
+    assert_eq!(machine.run(), Ok(2));
+    assert_eq!(machine.read(0x2_0004, Width::Word), 42);
+
+    assert_eq!(
+        RiscvProgram::from_elf(&elf[..60]),
+        Err(RiscvError::InvalidElf("truncated file"))
+    );
+    let mut not_riscv = elf.clone();
+    not_riscv[18] = 0x3E;
+    assert_eq!(
+        RiscvProgram::from_elf(&not_riscv),
+        Err(RiscvError::InvalidElf("not a RISC-V ELF file"))
+    );
+    assert_eq!(
+        RiscvProgram::from_elf(b"\x7fELF\x02\x01"),
+        Err(RiscvError::InvalidElf("not a 32-bit ELF file"))
+    );
+
+    // Overwrite a field of the second segment's program header.
+    let with_field = |field: usize, value: u32| {
+        let mut elf = elf.clone();
+        let offset = 52 + 32 + 4 * field;
+        elf[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        RiscvProgram::from_elf(&elf)
+    };
+    assert_eq!(
+        with_field(1, u32::MAX),
+        Err(RiscvError::InvalidElf("truncated segment"))
+    );
+    assert_eq!(
+        with_field(5, u32::MAX),
+        Err(RiscvError::InvalidElf("memory image too large"))
+    );
+    // The segment fits on its own, but not together with the code.
+    assert_eq!(
+        with_field(5, MAX_IMAGE_SIZE as u32),
+        Err(RiscvError::InvalidElf("memory image too large"))
+    );
+    let mut aliased = elf.clone();
+    aliased[42..44].copy_from_slice(&0u16.to_le_bytes());
+    assert_eq!(
+        RiscvProgram::from_elf(&aliased),
+        Err(RiscvError::InvalidElf("program header entries too small"))
+    );
+    assert_eq!(
+        with_field(2, 0xFFFF_FFFC),
+        Err(RiscvError::InvalidElf("segment beyond the address space"))
+    );
+}
+
+/// Sums `x1 + (x1 - 1) + ... + 1` into `x2` and exercises the rest of the supported subset.
+fn sum_program() -> Vec<u32> {
+    vec![
+        addi(2, 0, 0),
+        r_type(0, 1, 2, 0, 2), // add x2, x2, x1
+        addi(1, 1, -1),
+        b_type(-8, 0, 1, 1),       // bne x1, x0, -8
+        s_type(8, 2, 0, 2),        // sw x2, 8(x0)
+        i_type(4, 3, 2, 4, 0x03),  // lw x4, 4(x3)
+        r_type(0, 4, 1, 3, 5),     // sltu x5, x1, x4
+        i_type(-1, 2, 4, 6, 0x13), // xori x6, x2, -1
+        i_type(1, 1, 3, 7, 0x13),  // sltiu x7, x1, 1
+        i_type(-1, 0, 6, 8, 0x13), // ori x8, x0, -1
+        u_type(0x12345, 9, 0x37),  // lui x9, 0x12345
+        u_type(1, 10, 0x17),       // auipc x10, 1
+        r_type(1, 9, 8, 5, 11),    // divu x11, x8, x9
+        r_type(0x20, 11, 9, 0, 0), // sub x0, x9, x11
+        0x0000_000F,               // fence
+        j_type(8, 0),              // j +8
+        r_type(0, 1, 1, 0, 12),    // add x12, x1, x1, skipped
+        r_type(0, 2, 2, 2, 13),    // slt x13, x2, x2
+        ECALL,
+        addi(14, 0, 1), // after the halt, never executed
+    ]
+}
+
+#[test]
+fn test_translate() {
+    let program = RiscvProgram::from_flat(&to_bytes(&sum_program()), 0x400);
+    let alu = program.to_alu_program().unwrap();
+    assert_eq!(alu.len(), 20);
+    assert_eq!(alu[3], Instruction::bne(1, 0, 1));
+    assert_eq!(alu[6], Instruction::lt(5, 1, 4));
+    assert_eq!(alu[7], Instruction::not(6, 2));
+    assert_eq!(alu[8], Instruction::eq(7, 1, 0));
+    assert_eq!(alu[9], Instruction::loadi(8, u32::MAX));
+    assert_eq!(alu[11], Instruction::loadi(10, 0x400 + 44 + 0x1000));
+    assert_eq!(alu[13], Instruction::nop());
+    assert_eq!(alu[15], Instruction::jmp(17));
+    assert_eq!(alu[18], Instruction::jmp(20));
+
+    let mut regs = [0; NUM_RISCV_REGISTERS];
+    regs[1] = 10;
+    regs[3] = 4;
+    let mut machine = RiscvMachine::new(&program, regs);
+    machine.run().unwrap();
+    assert_eq!(execute(&alu, regs), machine.regs);
+    assert_eq!(machine.regs[2], 55);
+    assert_eq!(machine.regs[4], 55);
+    assert_eq!(machine.regs[14], 0);
+}
+
+#[test]
+fn test_prove_translated_program() {
+    let program = RiscvProgram::from_flat(&to_bytes(&sum_program()), 0);
+    let mut regs = [0; NUM_RISCV_REGISTERS];
+    regs[1] = 5;
+    regs[3] = 4;
+    let mut machine = RiscvMachine::new(&program, regs);
+    machine.run().unwrap();
+
+    let chip = program.to_rom_chip().unwrap();
+    let trace = program.generate_trace::<BabyBear>(regs).unwrap();
+    assert_eq!(trace, chip.generate_trace(regs));
+
+    let config = alu_test_config();
+    let (proof, final_regs) = prove_rom_program(&config, &chip, regs);
+    assert_eq!(final_regs, machine.regs);
+    verify_rom_program(&config, &chip, &proof, &regs, &final_regs).expect("verification failed");
+}
+
+/// Shifts a negative number every way, then counts up to 3 with `blt` and takes or skips the
+/// other order branches.
+fn shift_program() -> Vec<u32> {
+    vec![
+        addi(1, 0, -20),
+        addi(2, 0, 3),
+        r_type(0, 2, 1, 1, 3),            // sll x3, x1, x2
+        r_type(0, 2, 1, 5, 4),            // srl x4, x1, x2
+        r_type(0x20, 2, 1, 5, 5),         // sra x5, x1, x2
+        i_type(4, 1, 1, 6, 0x13),         // slli x6, x1, 4
+        i_type(31, 1, 5, 7, 0x13),        // srli x7, x1, 31
+        i_type(0x400 | 2, 1, 5, 8, 0x13), // srai x8, x1, 2
+        addi(9, 9, 1),
+        b_type(-4, 2, 9, 4), // blt x9, x2, -4
+        b_type(8, 1, 9, 6),  // bltu x9, x1, +8, taken
+        addi(10, 0, 1),      // skipped
+        b_type(8, 9, 1, 5),  // bge x1, x9, +8, not taken
+        addi(11, 0, 1),
+        b_type(8, 9, 1, 7), // bgeu x1, x9, +8, taken
+        addi(12, 0, 1),     // skipped
+        ECALL,
+    ]
+}
+
+#[test]
+fn test_translate_shifts_and_branches() {
+    let program = RiscvProgram::from_flat(&to_bytes(&shift_program()), 0);
+    let alu = program.to_alu_program().unwrap();
+    assert_eq!(alu[2], Instruction::sll(3, 1, 2));
+    assert_eq!(alu[4], Instruction::sra(5, 1, 2));
+    assert_eq!(alu[7], Instruction::srai(8, 1, 2));
+    assert_eq!(alu[9], Instruction::blt(9, 2, 8));
+    assert_eq!(alu[10], Instruction::bltu(9, 1, 12));
+    assert_eq!(alu[12], Instruction::bge(1, 9, 14));
+    assert_eq!(alu[14], Instruction::bgeu(1, 9, 16));
+
+    let regs = [0; NUM_RISCV_REGISTERS];
+    let mut machine = RiscvMachine::new(&program, regs);
+    machine.run().unwrap();
+    let minus_20 = (-20i32) as u32;
+    assert_eq!(
+        machine.regs[3..=12],
+        [
+            minus_20 << 3,
+            minus_20 >> 3,
+            (-20i32 >> 3) as u32,
+            minus_20 << 4,
+            1,
+            (-5i32) as u32,
+            3,
+            0,
+            1,
+            0
+        ]
+    );
+
+    let config = alu_test_config();
+    let chip = program.to_rom_chip().unwrap();
+    let (proof, final_regs) = prove_rom_program(&config, &chip, regs);
+    assert_eq!(final_regs, machine.regs);
+    verify_rom_program(&config, &chip, &proof, &regs, &final_regs).expect("verification failed");
+}
+
+#[test]
+fn test_translation_errors() {
+    let translate = |words: &[u32]| RiscvProgram::from_flat(&to_bytes(words), 0).to_alu_program();
+
+    let jalr = i_type(0, 1, 0, 0, 0x67);
+    assert_eq!(
+        translate(&[ECALL, jalr]),
+        Err(RiscvError::LinkedJump {
+            pc: 4,
+            inst: RiscvInstruction::Jalr {
+                rd: 0,
+                rs1: 1,
+                offset: 0
+            },
+        })
+    );
+    let err = translate(&[i_type(3, 1, 7, 1, 0x13)]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "`andi x1, x1, 3` at 0x0 is not supported by the ALU"
+    );
+    assert!(matches!(
+        translate(&[j_type(4, 1)]), // jal saving a return address
+        Err(RiscvError::LinkedJump { pc: 0, .. })
+    ));
+    for word in [
+        i_type(0, 1, 0, 2, 0x03), // lb
+        s_type(0, 2, 1, 1),       // sh
+    ] {
+        assert!(
+            matches!(
+                translate(&[word]),
+                Err(RiscvError::SubwordAccess { pc: 0, .. })
+            ),
+            "{word:#010x}"
+        );
+    }
+    for word in [
+        r_type(1, 2, 1, 1, 3),      // mulh
+        i_type(5, 1, 0x7, 2, 0x13), // andi with a register
+    ] {
+        assert!(
+            matches!(
+                translate(&[word]),
+                Err(RiscvError::Unsupported { pc: 0, .. })
+            ),
+            "{word:#010x}"
+        );
+    }
+
+    assert_eq!(
+        translate(&[ECALL, b_type(8, 0, 0, 0)]),
+        Err(RiscvError::InvalidTarget { pc: 4, target: 12 })
+    );
+    assert_eq!(
+        translate(&[j_type(-4, 0)]),
+        Err(RiscvError::InvalidTarget {
+            pc: 0,
+            target: u32::MAX - 3
+        })
+    );
+    assert_eq!(
+        translate(&[0]),
+        Err(RiscvError::IllegalInstruction { pc: 0, word: 0 })
+    );
+
+    let mut program = RiscvProgram::from_flat(&to_bytes(&[ECALL]), 0);
+    program.segments.push(Segment {
+        addr: 0x100,
+        data: vec![0, 0, 7, 0],
+        executable: false,
+    });
+    assert_eq!(
+        program.to_alu_program(),
+        Err(RiscvError::InitializedData { addr: 0x102 })
+    );
+    program.entry = 4;
+    assert_eq!(
+        program.to_alu_program(),
+        Err(RiscvError::UnsupportedLayout(
+            "the entry point is not the start of the code"
+        ))
+    );
+}